@@ -8,7 +8,7 @@ using PrdAgent.Core.Models;
 namespace PrdAgent.Api.Controllers;
 
 /// <summary>
-/// Desktop 在线状态（心跳）
+/// Desktop 在线状态（心跳、群成员在线 / 正在输入）
 /// </summary>
 [ApiController]
 [Route("api/v1/desktop/presence")]
@@ -16,13 +16,18 @@ namespace PrdAgent.Api.Controllers;
 public class DesktopPresenceController : ControllerBase
 {
     private readonly ICacheManager _cache;
+    private readonly IGroupService _groupService;
 
     // online TTL：心跳 30s，TTL 90s，允许短暂抖动
     private static readonly TimeSpan PresenceTtl = TimeSpan.FromSeconds(90);
 
-    public DesktopPresenceController(ICacheManager cache)
+    // 正在输入：客户端每 3s 最多发一次，超过 TTL 未续期即视为停止输入
+    private static readonly TimeSpan TypingTtl = TimeSpan.FromSeconds(6);
+
+    public DesktopPresenceController(ICacheManager cache, IGroupService groupService)
     {
         _cache = cache;
+        _groupService = groupService;
     }
 
     private static string? GetUserId(ClaimsPrincipal user)
@@ -34,6 +39,8 @@ public class DesktopPresenceController : ControllerBase
     }
 
     private static string PresenceKey(string userId, string clientId) => $"desktop:presence:{userId}:{clientId}";
+    private static string PresenceKeyPatternUser(string userId) => $"desktop:presence:{userId}:*";
+    private static string TypingKey(string groupId, string userId) => $"desktop:typing:{groupId}:{userId}";
 
     [HttpPost("heartbeat")]
     public async Task<IActionResult> Heartbeat()
@@ -75,6 +82,86 @@ public class DesktopPresenceController : ControllerBase
             ttlSeconds = (int)PresenceTtl.TotalSeconds
         }));
     }
-}
 
+    /// <summary>
+    /// 群成员在线 / 正在输入状态（仅群成员可查）
+    /// </summary>
+    [HttpGet("groups/{groupId}")]
+    public async Task<IActionResult> GroupPresence(string groupId)
+    {
+        var userId = GetUserId(User);
+        if (string.IsNullOrEmpty(userId))
+        {
+            return Unauthorized(ApiResponse<object>.Fail(ErrorCodes.UNAUTHORIZED, "未授权"));
+        }
+        if (!await _groupService.IsMemberAsync(groupId, userId))
+        {
+            return StatusCode(StatusCodes.Status403Forbidden,
+                ApiResponse<object>.Fail(ErrorCodes.PERMISSION_DENIED, "您不是该群组成员"));
+        }
+
+        var members = await _groupService.GetMembersAsync(groupId);
+        var items = new List<GroupMemberPresence>();
+        foreach (var member in members)
+        {
+            DateTime? lastSeenAt = null;
+            foreach (var key in _cache.GetKeys(PresenceKeyPatternUser(member.UserId)).Take(20))
+            {
+                var entry = await _cache.GetAsync<DesktopPresenceEntry>(key);
+                if (entry != null && (lastSeenAt == null || entry.LastSeenAt > lastSeenAt))
+                {
+                    lastSeenAt = entry.LastSeenAt;
+                }
+            }
+            var online = lastSeenAt != null;
+            items.Add(new GroupMemberPresence
+            {
+                UserId = member.UserId,
+                Online = online,
+                Typing = online && await _cache.ExistsAsync(TypingKey(groupId, member.UserId)),
+                LastSeenAt = lastSeenAt
+            });
+        }
+
+        return Ok(ApiResponse<List<GroupMemberPresence>>.Ok(items));
+    }
+
+    /// <summary>
+    /// "正在输入"信号（仅群成员；TTL 内未续期自动消失）
+    /// </summary>
+    [HttpPost("typing")]
+    public async Task<IActionResult> Typing([FromBody] TypingRequest request)
+    {
+        var userId = GetUserId(User);
+        if (string.IsNullOrEmpty(userId))
+        {
+            return Unauthorized(ApiResponse<object>.Fail(ErrorCodes.UNAUTHORIZED, "未授权"));
+        }
+        var groupId = (request.GroupId ?? "").Trim();
+        if (string.IsNullOrEmpty(groupId))
+        {
+            return BadRequest(ApiResponse<object>.Fail(ErrorCodes.INVALID_FORMAT, "groupId 不能为空"));
+        }
+        if (!await _groupService.IsMemberAsync(groupId, userId))
+        {
+            return StatusCode(StatusCodes.Status403Forbidden,
+                ApiResponse<object>.Fail(ErrorCodes.PERMISSION_DENIED, "您不是该群组成员"));
+        }
 
+        await _cache.SetAsync(TypingKey(groupId, userId), DateTime.UtcNow, TypingTtl);
+        return Ok(ApiResponse<object>.Ok(new { groupId, ttlSeconds = (int)TypingTtl.TotalSeconds }));
+    }
+
+    public class TypingRequest
+    {
+        public string? GroupId { get; set; }
+    }
+
+    public class GroupMemberPresence
+    {
+        public string UserId { get; set; } = string.Empty;
+        public bool Online { get; set; }
+        public bool Typing { get; set; }
+        public DateTime? LastSeenAt { get; set; }
+    }
+}
@@ -236,6 +236,47 @@ public class GroupsController : ControllerBase
         }
     }
 
+    /// <summary>
+    /// 邀请预览（加入前查看群组名称、PRD 标题与成员数，不要求是群成员）
+    /// </summary>
+    [HttpGet("invite/{inviteCode}")]
+    [ProducesResponseType(typeof(ApiResponse<GroupInvitePreviewResponse>), StatusCodes.Status200OK)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status404NotFound)]
+    public async Task<IActionResult> PreviewInvite(string inviteCode)
+    {
+        var userId = GetUserId(User);
+        if (string.IsNullOrEmpty(userId))
+        {
+            return Unauthorized(ApiResponse<object>.Fail(ErrorCodes.UNAUTHORIZED, "未授权"));
+        }
+
+        var group = await _groupService.GetByInviteCodeAsync(inviteCode);
+        if (group == null)
+        {
+            return NotFound(ApiResponse<object>.Fail(ErrorCodes.INVALID_INVITE_LINK, "邀请码无效"));
+        }
+        if (group.InviteExpireAt.HasValue && group.InviteExpireAt.Value < DateTime.UtcNow)
+        {
+            return BadRequest(ApiResponse<object>.Fail(ErrorCodes.INVITE_EXPIRED, "邀请码已过期"));
+        }
+
+        var document = await _documentService.GetByIdAsync(group.PrdDocumentId);
+        var members = await _groupService.GetMembersAsync(group.GroupId);
+        var owner = await _userService.GetByIdAsync(group.OwnerId);
+
+        var response = new GroupInvitePreviewResponse
+        {
+            GroupId = group.GroupId,
+            GroupName = group.GroupName,
+            PrdTitle = document?.Title ?? group.PrdTitleSnapshot,
+            MemberCount = members.Count,
+            OwnerDisplayName = owner?.DisplayName ?? owner?.Username,
+            AlreadyMember = members.Any(m => m.UserId == userId)
+        };
+
+        return Ok(ApiResponse<GroupInvitePreviewResponse>.Ok(response));
+    }
+
     /// <summary>
     /// 打开群组会话（用于桌面端进入群组后进行问答/引导）
     /// </summary>
@@ -852,6 +893,206 @@ public class GroupsController : ControllerBase
         }
     }
 
+    /// <summary>
+    /// 移除群成员（群主/管理员可操作；不能移除群主）
+    /// </summary>
+    [HttpDelete("{groupId}/members/{userId}")]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status200OK)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status400BadRequest)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status403Forbidden)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status404NotFound)]
+    public async Task<IActionResult> RemoveMember(string groupId, string userId)
+    {
+        var (group, actor, target, error) = await LoadMemberActionAsync(groupId, userId);
+        if (error != null)
+        {
+            return error;
+        }
+
+        if (target!.UserId == group!.OwnerId)
+        {
+            return BadRequest(ApiResponse<object>.Fail(ErrorCodes.PERMISSION_DENIED, "不能移除群主"));
+        }
+
+        await _groupService.RemoveMemberAsync(groupId, userId);
+
+        var targetUser = await _userService.GetByIdAsync(userId);
+        var targetName = targetUser?.DisplayName ?? targetUser?.Username ?? userId;
+        await PublishSystemMessageAsync(groupId, $"{actor!.DisplayName ?? actor.Username} 将 {targetName} 移出了群组");
+
+        _logger.LogInformation("User {TargetUserId} removed from group {GroupId} by {ActorUserId}",
+            userId, groupId, actor.UserId);
+        return Ok(ApiResponse<object>.Ok(new object()));
+    }
+
+    /// <summary>
+    /// 修改群成员角色（群主/管理员可操作）
+    /// </summary>
+    [HttpPut("{groupId}/members/{userId}/role")]
+    [ProducesResponseType(typeof(ApiResponse<GroupMemberResponse>), StatusCodes.Status200OK)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status403Forbidden)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status404NotFound)]
+    public async Task<IActionResult> UpdateMemberRole(string groupId, string userId, [FromBody] UpdateGroupMemberRoleRequest request)
+    {
+        var (group, _, target, error) = await LoadMemberActionAsync(groupId, userId);
+        if (error != null)
+        {
+            return error;
+        }
+
+        await _db.GroupMembers.UpdateOneAsync(
+            m => m.GroupId == groupId && m.UserId == userId,
+            Builders<GroupMember>.Update.Set(m => m.MemberRole, request.MemberRole));
+        target!.MemberRole = request.MemberRole;
+
+        _logger.LogInformation("Member {UserId} role in group {GroupId} -> {Role}", userId, groupId, request.MemberRole);
+        return await MemberResponseAsync(group!, target);
+    }
+
+    /// <summary>
+    /// 编辑群成员标签（群主/管理员可操作；整体替换）
+    /// </summary>
+    [HttpPut("{groupId}/members/{userId}/tags")]
+    [ProducesResponseType(typeof(ApiResponse<GroupMemberResponse>), StatusCodes.Status200OK)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status400BadRequest)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status403Forbidden)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status404NotFound)]
+    public async Task<IActionResult> UpdateMemberTags(string groupId, string userId, [FromBody] UpdateGroupMemberTagsRequest request)
+    {
+        var (isValid, errorMessage) = request.Validate();
+        if (!isValid)
+        {
+            return BadRequest(ApiResponse<object>.Fail(ErrorCodes.INVALID_FORMAT, errorMessage!));
+        }
+
+        var (group, _, target, error) = await LoadMemberActionAsync(groupId, userId);
+        if (error != null)
+        {
+            return error;
+        }
+
+        var tags = request.Tags
+            .Select(t => new GroupMemberTag { Name = t.Name.Trim(), Role = t.Role.Trim().ToLowerInvariant() })
+            .GroupBy(t => t.Role)
+            .Select(g => g.First())
+            .ToList();
+        await _db.GroupMembers.UpdateOneAsync(
+            m => m.GroupId == groupId && m.UserId == userId,
+            Builders<GroupMember>.Update.Set(m => m.Tags, tags));
+        target!.Tags = tags;
+
+        return await MemberResponseAsync(group!, target);
+    }
+
+    /// <summary>
+    /// 转让群主（仅群主/管理员可操作；新群主须为现有成员）
+    /// </summary>
+    [HttpPost("{groupId}/owner/transfer")]
+    [ProducesResponseType(typeof(ApiResponse<GroupResponse>), StatusCodes.Status200OK)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status400BadRequest)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status403Forbidden)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status404NotFound)]
+    public async Task<IActionResult> TransferOwnership(string groupId, [FromBody] TransferGroupOwnershipRequest request)
+    {
+        var (isValid, errorMessage) = request.Validate();
+        if (!isValid)
+        {
+            return BadRequest(ApiResponse<object>.Fail(ErrorCodes.INVALID_FORMAT, errorMessage!));
+        }
+
+        var newOwnerId = request.NewOwnerUserId.Trim();
+        var (group, actor, _, error) = await LoadMemberActionAsync(groupId, newOwnerId);
+        if (error != null)
+        {
+            return error;
+        }
+
+        if (group!.OwnerId == newOwnerId)
+        {
+            return BadRequest(ApiResponse<object>.Fail(ErrorCodes.INVALID_FORMAT, "该成员已是群主"));
+        }
+
+        await _db.Groups.UpdateOneAsync(
+            g => g.GroupId == groupId,
+            Builders<Group>.Update.Set(g => g.OwnerId, newOwnerId));
+
+        var newOwner = await _userService.GetByIdAsync(newOwnerId);
+        var newOwnerName = newOwner?.DisplayName ?? newOwner?.Username ?? newOwnerId;
+        await PublishSystemMessageAsync(groupId, $"{actor!.DisplayName ?? actor.Username} 将群主转让给 {newOwnerName}");
+
+        _logger.LogInformation("Group {GroupId} ownership transferred {OldOwnerId} -> {NewOwnerId}",
+            groupId, group.OwnerId, newOwnerId);
+        return await GetGroup(groupId);
+    }
+
+    /// <summary>
+    /// 成员管理的前置校验：群组存在、操作者为群主/管理员、目标用户是群成员
+    /// </summary>
+    private async Task<(Group? Group, User? Actor, GroupMember? Target, IActionResult? Error)> LoadMemberActionAsync(
+        string groupId, string targetUserId)
+    {
+        var userId = GetUserId(User);
+        var actor = string.IsNullOrEmpty(userId) ? null : await _userService.GetByIdAsync(userId);
+        if (actor == null)
+        {
+            return (null, null, null, Unauthorized(ApiResponse<object>.Fail(ErrorCodes.UNAUTHORIZED, "未授权")));
+        }
+
+        var group = await _groupService.GetByIdAsync(groupId);
+        if (group == null)
+        {
+            return (null, actor, null, NotFound(ApiResponse<object>.Fail(ErrorCodes.GROUP_NOT_FOUND, "群组不存在")));
+        }
+
+        if (actor.Role != UserRole.ADMIN && group.OwnerId != actor.UserId)
+        {
+            return (group, actor, null, StatusCode(StatusCodes.Status403Forbidden,
+                ApiResponse<object>.Fail(ErrorCodes.PERMISSION_DENIED, "仅群主/管理员可管理成员")));
+        }
+
+        var target = await _db.GroupMembers
+            .Find(m => m.GroupId == groupId && m.UserId == targetUserId)
+            .FirstOrDefaultAsync();
+        if (target == null)
+        {
+            return (group, actor, null, NotFound(ApiResponse<object>.Fail(ErrorCodes.NOT_FOUND, "该用户不是群成员")));
+        }
+
+        return (group, actor, target, null);
+    }
+
+    private async Task<IActionResult> MemberResponseAsync(Group group, GroupMember member)
+    {
+        var user = await _userService.GetByIdAsync(member.UserId);
+        if (user == null)
+        {
+            return NotFound(ApiResponse<object>.Fail(ErrorCodes.USER_NOT_FOUND, "用户不存在"));
+        }
+
+        var tags = member.Tags ?? new List<GroupMemberTag>();
+        if (tags.Count == 0)
+        {
+            tags = user.UserType == UserType.Bot
+                ? BuildDefaultBotTags(user.BotKind ?? BotKind.DEV)
+                : BuildDefaultHumanTags(member.MemberRole);
+        }
+
+        return Ok(ApiResponse<GroupMemberResponse>.Ok(new GroupMemberResponse
+        {
+            UserId = user.UserId,
+            Username = user.Username,
+            DisplayName = user.DisplayName,
+            MemberRole = member.MemberRole,
+            IsBot = user.UserType == UserType.Bot,
+            BotKind = user.UserType == UserType.Bot ? user.BotKind : null,
+            AvatarFileName = user.AvatarFileName,
+            AvatarUrl = BuildAvatarUrl(user),
+            Tags = tags.Select(t => new GroupMemberTagDto { Name = t.Name, Role = t.Role }).ToList(),
+            JoinedAt = member.JoinedAt,
+            IsOwner = member.UserId == group.OwnerId
+        }));
+    }
+
     /// <summary>
     /// 获取群组消息历史（分页，按 GroupSeq 升序返回）
     /// </summary>
@@ -1006,6 +1247,86 @@ public class GroupsController : ControllerBase
         return Ok(ApiResponse<List<MessageResponse>>.Ok(result));
     }
 
+    /// <summary>
+    /// 获取群组中的单条消息（桌面端补拉回复线程中缺失的祖先消息）
+    /// </summary>
+    [HttpGet("{groupId}/messages/{messageId}")]
+    [ProducesResponseType(typeof(ApiResponse<MessageResponse>), StatusCodes.Status200OK)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status403Forbidden)]
+    [ProducesResponseType(typeof(ApiResponse<object>), StatusCodes.Status404NotFound)]
+    public async Task<IActionResult> GetGroupMessage(string groupId, string messageId)
+    {
+        var userId = GetUserId(User);
+        if (string.IsNullOrEmpty(userId))
+        {
+            return Unauthorized(ApiResponse<object>.Fail(ErrorCodes.UNAUTHORIZED, "未授权"));
+        }
+
+        var group = await _groupService.GetByIdAsync(groupId);
+        if (group == null)
+        {
+            return NotFound(ApiResponse<object>.Fail(ErrorCodes.GROUP_NOT_FOUND, "群组不存在"));
+        }
+
+        var members = await _groupService.GetMembersAsync(groupId);
+        if (!members.Any(m => m.UserId == userId))
+        {
+            return StatusCode(StatusCodes.Status403Forbidden,
+                ApiResponse<object>.Fail(ErrorCodes.PERMISSION_DENIED, "您不是该群组成员"));
+        }
+
+        var message = await _messageRepository.FindByIdAsync(messageId);
+        if (message == null || message.GroupId != groupId)
+        {
+            return NotFound(ApiResponse<object>.Fail(ErrorCodes.NOT_FOUND, "消息不存在"));
+        }
+
+        // 与列表接口一致：清理上下文之前的消息不再返回
+        var resetTicks = await _cache.GetAsync<long?>(CacheKeys.ForGroupContextReset(groupId));
+        if (resetTicks.HasValue && resetTicks.Value > 0 && message.Timestamp <= new DateTime(resetTicks.Value, DateTimeKind.Utc))
+        {
+            return NotFound(ApiResponse<object>.Fail(ErrorCodes.NOT_FOUND, "消息不存在"));
+        }
+
+        string? senderName = null;
+        UserRole? senderRole = null;
+        string? senderAvatarUrl = null;
+        List<GroupMemberTag>? senderTags = null;
+        if (!string.IsNullOrWhiteSpace(message.SenderId))
+        {
+            var sender = await _userService.GetByIdAsync(message.SenderId);
+            if (sender != null)
+            {
+                senderName = (sender.DisplayName ?? sender.Username ?? sender.UserId).Trim();
+                senderRole = sender.Role;
+                senderAvatarUrl = AvatarUrlBuilder.Build(_cfg, sender);
+                senderTags = members.FirstOrDefault(m => m.UserId == message.SenderId)?.Tags;
+            }
+        }
+
+        var response = new MessageResponse
+        {
+            Id = message.Id,
+            GroupSeq = message.GroupSeq,
+            SenderId = message.SenderId,
+            SenderName = senderName,
+            SenderRole = senderRole,
+            SenderAvatarUrl = senderAvatarUrl,
+            SenderTags = senderTags,
+            Role = message.Role,
+            Content = message.Content,
+            ThinkingContent = message.ThinkingContent,
+            ReplyToMessageId = message.ReplyToMessageId,
+            ResendOfMessageId = message.ResendOfMessageId,
+            ViewRole = message.ViewRole,
+            Timestamp = message.Timestamp,
+            TokenUsage = message.TokenUsage,
+            AttachmentIds = message.AttachmentIds != null && message.AttachmentIds.Count > 0 ? message.AttachmentIds : null
+        };
+
+        return Ok(ApiResponse<MessageResponse>.Ok(response));
+    }
+
     /// <summary>
     /// 清理群组上下文（仅清理服务端 LLM 上下文缓存，不删除消息历史）
     /// </summary>
@@ -31,9 +31,11 @@ namespace PrdAgent.Api.Json;
 [JsonSerializable(typeof(ApiResponse<DocumentContentInfo>))]
 [JsonSerializable(typeof(ApiResponse<GroupResponse>))]
 [JsonSerializable(typeof(ApiResponse<JoinGroupResponse>))]
+[JsonSerializable(typeof(ApiResponse<GroupInvitePreviewResponse>))]
 [JsonSerializable(typeof(ApiResponse<List<GroupResponse>>))]
 [JsonSerializable(typeof(ApiResponse<List<GroupMemberResponse>>))]
 [JsonSerializable(typeof(ApiResponse<BootstrapGroupBotsResponse>))]
+[JsonSerializable(typeof(ApiResponse<MessageResponse>))]
 [JsonSerializable(typeof(ApiResponse<List<MessageResponse>>))]
 [JsonSerializable(typeof(ApiResponse<UserListResponse>))]
 [JsonSerializable(typeof(ApiResponse<UserDetailResponse>))]
@@ -136,3 +136,48 @@ public class UpdateGroupNameRequest
         return (true, null);
     }
 }
+
+/// <summary>
+/// 修改群成员角色请求
+/// </summary>
+public class UpdateGroupMemberRoleRequest
+{
+    /// <summary>新的成员角色</summary>
+    public UserRole MemberRole { get; set; } = UserRole.DEV;
+}
+
+/// <summary>
+/// 编辑群成员标签请求
+/// </summary>
+public class UpdateGroupMemberTagsRequest
+{
+    public const int MaxTags = 10;
+
+    /// <summary>新的标签列表（整体替换）</summary>
+    public List<GroupMemberTag> Tags { get; set; } = new();
+
+    public (bool IsValid, string? ErrorMessage) Validate()
+    {
+        if (Tags.Count > MaxTags)
+            return (false, $"标签最多 {MaxTags} 个");
+        if (Tags.Any(t => string.IsNullOrWhiteSpace(t.Name) || string.IsNullOrWhiteSpace(t.Role)))
+            return (false, "标签名称与 role 不能为空");
+        return (true, null);
+    }
+}
+
+/// <summary>
+/// 转让群主请求
+/// </summary>
+public class TransferGroupOwnershipRequest
+{
+    /// <summary>新群主的用户ID（须为现有成员）</summary>
+    public string NewOwnerUserId { get; set; } = string.Empty;
+
+    public (bool IsValid, string? ErrorMessage) Validate()
+    {
+        if (string.IsNullOrWhiteSpace(NewOwnerUserId))
+            return (false, "新群主不能为空");
+        return (true, null);
+    }
+}
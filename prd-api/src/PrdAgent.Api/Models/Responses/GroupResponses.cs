@@ -29,6 +29,20 @@ public class JoinGroupResponse
     public DateTime JoinedAt { get; set; }
 }
 
+/// <summary>
+/// 邀请预览响应（加入前展示）
+/// </summary>
+public class GroupInvitePreviewResponse
+{
+    public string GroupId { get; set; } = string.Empty;
+    public string GroupName { get; set; } = string.Empty;
+    public string? PrdTitle { get; set; }
+    public int MemberCount { get; set; }
+    public string? OwnerDisplayName { get; set; }
+    /// <summary>当前用户已是成员时，客户端直接打开群组</summary>
+    public bool AlreadyMember { get; set; }
+}
+
 /// <summary>
 /// 群组成员响应
 /// </summary>
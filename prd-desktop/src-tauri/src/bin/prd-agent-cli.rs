@@ -0,0 +1,11 @@
+//! 无界面命令行（自动化脚本用），与桌面端共用 ApiClient、配置与命令实现：
+//!
+//! ```sh
+//! cargo run --bin prd-agent-cli -- login alice --password ******
+//! cargo run --bin prd-agent-cli -- ask --group <groupId> "这个需求的验收标准是什么？"
+//! ```
+
+#[tokio::main]
+async fn main() -> std::process::ExitCode {
+    prd_agent_desktop_lib::cli::run(std::env::args().skip(1).collect()).await
+}
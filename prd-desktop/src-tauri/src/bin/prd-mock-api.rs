@@ -0,0 +1,23 @@
+//! 本地 mock API（离线开发，无需 .NET 后端 / MongoDB / Redis）：
+//!
+//! ```sh
+//! cargo run --bin prd-mock-api --features mock-server -- 5055
+//! API_BASE_URL=http://127.0.0.1:5055 pnpm tauri dev
+//! ```
+
+use prd_agent_desktop_lib::mock_server::MockServer;
+use std::net::SocketAddr;
+
+const DEFAULT_PORT: u16 = 5055;
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let port = std::env::args()
+        .nth(1)
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_PORT);
+    let server = MockServer::bind(SocketAddr::from(([127, 0, 0, 1], port))).await?;
+    println!("mock API listening on {}", server.base_url());
+    println!("任意用户名均可登录；密码为 \"wrong\" 时模拟登录失败。Ctrl+C 退出");
+    tokio::signal::ctrl_c().await
+}
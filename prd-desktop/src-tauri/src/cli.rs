@@ -0,0 +1,537 @@
+//! 无界面命令行（`prd-agent-cli`）：复用 ApiClient、配置读取与命令实现，供自动化脚本调用。
+//!
+//! - 配置与桌面端共用 app 数据目录下的 config.json（apiBaseUrl / clientId）
+//! - 登录态保存在同目录的 cli-session.json，每次命令结束后回写（refresh 后的新 token 也会保存）
+//! - 非流式命令把 data 以 JSON 输出到 stdout；ask / skills run 把回复增量直接写到 stdout
+//! - 失败时错误信息写到 stderr，退出码为 1
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use tokio_util::sync::CancellationToken;
+
+use crate::commands::session::{stream_chat_run, stream_message, SendMessageRequest, StreamSink};
+use crate::commands::{auth, config, defect, document, group, skill};
+use crate::models::{ApiResponse, DefectListFilter};
+use crate::services::mcp_server::{self, McpSettings};
+use crate::services::{api_client, ApiClient};
+
+const SESSION_FILE: &str = "cli-session.json";
+const DEFAULT_ROLE: &str = "PM";
+
+const USAGE: &str = "\
+用法: prd-agent-cli [--api <url>] [--data-dir <dir>] <command> [args]
+
+命令:
+  login <username> [--password <pwd>]      登录（未传密码时读取 PRD_AGENT_PASSWORD 或 stdin）
+  logout                                   清除本地登录态
+  upload <file> [--session <id>] [--type <documentType>]
+                                           上传 PRD；传 --session 时作为附加文档上传到会话
+  groups                                   列出我的群组
+  ask (--group <id> | --session <id>) [--role <role>] [<question>...]
+                                           提问并流式输出回复（未传问题时读取 stdin）
+  defects list [--scope <s>] [--status <s>] [--severity <s>] [--keyword <k>] [--limit <n>] [--all]
+  defects create --assignee <userId> [--title <t>] [--severity <s>] [--template <id>] [--submit] <content>...
+  skills list [--role <role>]
+  skills run <skillKey> (--group <id> | --session <id>) [--role <role>] [--input <text>] [--param k=v]...
+  mcp                                      以 stdio 传输运行 MCP 服务（工具 allowlist 取 config.json 的 mcp 字段）
+";
+
+/// 无值开关（其余 `--xxx` 均需要一个值）
+const SWITCHES: &[&str] = &["all", "submit", "help"];
+
+/// 解析后的参数：位置参数 + `--key value` / `--key=value` 选项（选项可出现在任意位置）
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Vec<String>>,
+}
+
+impl Args {
+    fn parse(raw: Vec<String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options: HashMap<String, Vec<String>> = HashMap::new();
+        let mut iter = raw.into_iter();
+        while let Some(arg) = iter.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                positional.push(arg);
+                continue;
+            };
+            if name.is_empty() {
+                // `--` 之后全部视为位置参数（问题内容以 -- 开头时使用）
+                positional.extend(iter.by_ref());
+                break;
+            }
+            let (key, value) = match name.split_once('=') {
+                Some((k, v)) => (k.to_string(), v.to_string()),
+                None if SWITCHES.contains(&name) => (name.to_string(), String::new()),
+                None => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| format!("选项 --{} 缺少值", name))?;
+                    (name.to_string(), value)
+                }
+            };
+            options.entry(key).or_default().push(value);
+        }
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn opt(&self, key: &str) -> Option<String> {
+        self.options
+            .get(key)
+            .and_then(|v| v.last())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    }
+
+    fn opts(&self, key: &str) -> &[String] {
+        self.options.get(key).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn switch(&self, key: &str) -> bool {
+        self.options.contains_key(key)
+    }
+
+    fn require(&self, key: &str) -> Result<String, String> {
+        self.opt(key).ok_or_else(|| format!("缺少 --{}", key))
+    }
+
+    /// 第 index 个位置参数起的剩余部分（以空格拼接）
+    fn rest(&self, index: usize) -> String {
+        self.positional
+            .get(index..)
+            .unwrap_or_default()
+            .join(" ")
+            .trim()
+            .to_string()
+    }
+}
+
+/// 落盘的登录态（与 ApiClient 内存中的 token / refresh 会话一一对应）
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CliSession {
+    access_token: Option<String>,
+    user_id: Option<String>,
+    refresh_token: Option<String>,
+    session_key: Option<String>,
+    client_type: Option<String>,
+}
+
+impl CliSession {
+    fn load(dir: &Path) -> Self {
+        std::fs::read_to_string(dir.join(SESSION_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn capture() -> Self {
+        let (user_id, refresh_token, session_key, client_type) =
+            match api_client::get_auth_session() {
+                Some((u, r, s, c)) => (Some(u), Some(r), Some(s), Some(c)),
+                None => (None, None, None, None),
+            };
+        Self {
+            access_token: api_client::get_auth_token(),
+            user_id,
+            refresh_token,
+            session_key,
+            client_type,
+        }
+    }
+
+    fn apply(self) {
+        if let Some(token) = self.access_token.filter(|t| !t.trim().is_empty()) {
+            ApiClient::set_token(token);
+        }
+        ApiClient::set_auth_session(
+            self.user_id,
+            self.refresh_token,
+            self.session_key,
+            self.client_type,
+        );
+    }
+
+    fn save(&self, dir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(dir).map_err(|e| format!("创建数据目录失败: {}", e))?;
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize session: {}", e))?;
+        write_private(&dir.join(SESSION_FILE), content.as_bytes())
+            .map_err(|e| format!("写入登录态失败: {}", e))
+    }
+}
+
+/// 写入只有当前用户可读写的文件（含 refresh token）；unix 上为 0600，已存在的文件也会收紧权限
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(content)
+}
+
+/// 把流式回复写到 stdout：delta 直接输出，done / error 结束本次流
+struct StdoutSink {
+    token: CancellationToken,
+    error: Option<String>,
+}
+
+impl StdoutSink {
+    fn new(token: CancellationToken) -> Self {
+        Self { token, error: None }
+    }
+
+    fn fail(&mut self, message: String) {
+        self.error.get_or_insert(message);
+        self.token.cancel();
+    }
+
+    fn finish(self) -> Result<(), String> {
+        println!();
+        match self.error {
+            Some(message) => Err(message),
+            None => Ok(()),
+        }
+    }
+}
+
+impl StreamSink for StdoutSink {
+    fn phase(&mut self, _phase: &str) {}
+
+    fn keepalive(&mut self) {}
+
+    fn event(&mut self, event: serde_json::Value) {
+        let text = |key: &str| event.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        match event.get("type").and_then(|v| v.as_str()) {
+            Some("delta") => {
+                if let Some(content) = text("content").or_else(|| text("deltaContent")) {
+                    let mut stdout = std::io::stdout().lock();
+                    let _ = stdout.write_all(content.as_bytes());
+                    let _ = stdout.flush();
+                }
+            }
+            Some("done") => self.token.cancel(),
+            Some("error") => {
+                let message = text("errorMessage")
+                    .or_else(|| text("message"))
+                    .unwrap_or_else(|| "stream error".to_string());
+                self.fail(message);
+            }
+            _ => {}
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.fail(message);
+    }
+
+    fn auth_expired(&mut self) {
+        self.fail("登录已过期，请重新执行 login".to_string());
+    }
+
+    fn cancelled(&mut self) {}
+}
+
+/// CLI 入口：args 不含程序名
+pub async fn run(args: Vec<String>) -> ExitCode {
+    let args = match Args::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.positional.is_empty() || args.switch("help") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let data_dir = args
+        .opt("data-dir")
+        .or_else(|| std::env::var("PRD_AGENT_DATA_DIR").ok())
+        .map(PathBuf::from)
+        .or_else(config::headless_app_data_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    // 优先级：--api > config.json > API_BASE_URL / 编译期默认值（与桌面端一致）
+    if let Ok(Some(cfg)) = config::read_config_in(&data_dir) {
+        let base_url = cfg.api_base_url.trim().to_string();
+        if !base_url.is_empty() {
+            api_client::set_api_base_url(base_url);
+        }
+        api_client::set_client_id(cfg.client_id);
+        if let Some(mcp) = cfg.mcp {
+            // 只取 allowlist；HTTP 传输属于桌面端，stdio 模式下不启动
+            mcp_server::set_settings(McpSettings {
+                enabled: false,
+                ..mcp
+            });
+        }
+    }
+    if let Some(api) = args.opt("api") {
+        api_client::set_api_base_url(api.trim_end_matches('/').to_string());
+    }
+    CliSession::load(&data_dir).apply();
+
+    let result = dispatch(&args, &data_dir).await;
+
+    if args.positional[0] != "logout" && api_client::get_auth_token().is_some() {
+        if let Err(e) = CliSession::capture().save(&data_dir) {
+            eprintln!("warning: {}", e);
+        }
+    }
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn dispatch(args: &Args, data_dir: &Path) -> Result<(), String> {
+    let sub = args.positional.get(1).map(String::as_str);
+    match (args.positional[0].as_str(), sub) {
+        ("login", _) => login(args).await,
+        ("logout", _) => {
+            ApiClient::clear_token();
+            match std::fs::remove_file(data_dir.join(SESSION_FILE)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(format!("删除登录态失败: {}", e))
+                }
+                _ => Ok(()),
+            }
+        }
+        ("upload", _) => upload(args).await,
+        ("groups", _) => print_data(group::get_groups().await?),
+        ("ask", _) => ask(args).await,
+        ("defects", Some("list")) => list_defects(args).await,
+        ("defects", Some("create")) => create_defect(args).await,
+        ("skills", Some("list")) => print_data(skill::get_skills(args.opt("role")).await?),
+        ("skills", Some("run")) => run_skill(args).await,
+        ("mcp", _) => mcp_server::serve_stdio()
+            .await
+            .map_err(|e| format!("MCP stdio 传输中断: {}", e)),
+        (command, _) => Err(format!("未知命令: {}\n\n{}", command, USAGE)),
+    }
+}
+
+/// 取出 data；success=false 时转为 `CODE: message` 错误
+fn into_data<T>(response: ApiResponse<T>) -> Result<T, String> {
+    if !response.success {
+        return Err(response
+            .error
+            .map(|e| format!("{}: {}", e.code, e.message))
+            .unwrap_or_else(|| "请求失败".to_string()));
+    }
+    response.data.ok_or_else(|| "响应缺少 data".to_string())
+}
+
+fn print_json<T: Serialize>(data: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("Failed to serialize response: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn print_data<T: Serialize>(response: ApiResponse<T>) -> Result<(), String> {
+    print_json(&into_data(response)?)
+}
+
+/// 位置参数为空时从 stdin 读取全部内容（便于管道传入长文本）
+fn text_or_stdin(args: &Args, index: usize, what: &str) -> Result<String, String> {
+    let text = args.rest(index);
+    if !text.is_empty() {
+        return Ok(text);
+    }
+    let mut buf = String::new();
+    std::io::stdin()
+        .read_to_string(&mut buf)
+        .map_err(|e| format!("读取 stdin 失败: {}", e))?;
+    let buf = buf.trim().to_string();
+    if buf.is_empty() {
+        return Err(format!("缺少{}", what));
+    }
+    Ok(buf)
+}
+
+async fn login(args: &Args) -> Result<(), String> {
+    let username = args
+        .positional
+        .get(1)
+        .cloned()
+        .ok_or_else(|| "缺少 username".to_string())?;
+    let password = match args
+        .opt("password")
+        .or_else(|| std::env::var("PRD_AGENT_PASSWORD").ok())
+    {
+        Some(password) => password,
+        None => {
+            eprint!("password: ");
+            let _ = std::io::stderr().flush();
+            let mut line = String::new();
+            std::io::stdin()
+                .lock()
+                .read_line(&mut line)
+                .map_err(|e| format!("读取密码失败: {}", e))?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    let data = into_data(auth::login(username, password).await?)?;
+    eprintln!(
+        "已登录 {}（{}）@ {}",
+        data.user.display_name,
+        data.user.username,
+        api_client::get_api_base_url()
+    );
+    Ok(())
+}
+
+async fn upload(args: &Args) -> Result<(), String> {
+    let file = args
+        .positional
+        .get(1)
+        .cloned()
+        .ok_or_else(|| "缺少文件路径".to_string())?;
+
+    if let Some(session_id) = args.opt("session") {
+        let resp = document::upload_file_to_session(session_id, file, args.opt("type")).await?;
+        return print_data(resp);
+    }
+
+    let content = std::fs::read_to_string(&file).map_err(|e| format!("读取文件失败: {}", e))?;
+    print_data(document::upload_document(content).await?)
+}
+
+/// --session 优先；否则以 --role（默认 PM）打开 --group 的会话
+async fn resolve_session(args: &Args) -> Result<String, String> {
+    if let Some(session_id) = args.opt("session") {
+        return Ok(session_id);
+    }
+    let group_id = args
+        .opt("group")
+        .ok_or_else(|| "需要 --group 或 --session".to_string())?;
+    let role = args.opt("role").unwrap_or_else(|| DEFAULT_ROLE.to_string());
+    let opened = into_data(group::open_group_session(group_id, role).await?)?;
+    Ok(opened.session_id)
+}
+
+/// Ctrl+C 时取消流（已输出的内容保留）
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let token = CancellationToken::new();
+    let on_signal = token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_signal.cancel();
+        }
+    });
+    token
+}
+
+async fn ask(args: &Args) -> Result<(), String> {
+    let session_id = resolve_session(args).await?;
+    let request = SendMessageRequest {
+        content: text_or_stdin(args, 1, "问题")?,
+        role: args.opt("role"),
+        ..Default::default()
+    };
+
+    let token = cancel_on_ctrl_c();
+    let mut sink = StdoutSink::new(token.clone());
+    stream_message(&session_id, &request, &token, &mut sink).await?;
+    sink.finish()
+}
+
+async fn list_defects(args: &Args) -> Result<(), String> {
+    let limit = match args.opt("limit") {
+        Some(v) => Some(
+            v.parse::<i64>()
+                .map_err(|_| "--limit 需要整数".to_string())?,
+        ),
+        None => None,
+    };
+    let filter = DefectListFilter {
+        scope: args.opt("scope"),
+        status: args.opt("status").map(Into::into),
+        severity: args.opt("severity").map(Into::into),
+        keyword: args.opt("keyword"),
+        limit,
+        ..Default::default()
+    };
+
+    if args.switch("all") {
+        print_data(defect::fetch_all_defects(&ApiClient::new(), &filter).await?)
+    } else {
+        print_data(defect::list_defects(Some(filter)).await?)
+    }
+}
+
+async fn create_defect(args: &Args) -> Result<(), String> {
+    let assignee = args.require("assignee")?;
+    let content = text_or_stdin(args, 2, "缺陷内容")?;
+    let severity = args.opt("severity").unwrap_or_else(|| "major".to_string());
+
+    let created = into_data(
+        defect::create_defect(
+            content,
+            severity,
+            args.opt("title"),
+            assignee,
+            args.opt("template"),
+        )
+        .await?,
+    )?;
+
+    if args.switch("submit") {
+        return print_data(defect::submit_defect(created.defect.id, None).await?);
+    }
+    print_json(&created)
+}
+
+async fn run_skill(args: &Args) -> Result<(), String> {
+    let skill_key = args
+        .positional
+        .get(2)
+        .cloned()
+        .ok_or_else(|| "缺少 skillKey".to_string())?;
+    let session_id = resolve_session(args).await?;
+
+    let mut parameters = HashMap::new();
+    for pair in args.opts("param") {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("--param 需要 key=value: {}", pair))?;
+        parameters.insert(key.trim().to_string(), value.to_string());
+    }
+
+    let run = into_data(
+        skill::execute_skill(
+            skill_key,
+            session_id,
+            args.opt("input"),
+            None,
+            (!parameters.is_empty()).then_some(parameters),
+        )
+        .await?,
+    )?;
+
+    let token = cancel_on_ctrl_c();
+    let mut sink = StdoutSink::new(token.clone());
+    stream_chat_run(&run.run_id, 0, &token, &mut sink).await?;
+    sink.finish()
+}
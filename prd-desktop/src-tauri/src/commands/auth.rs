@@ -2,7 +2,7 @@ use serde::Serialize;
 use tauri::command;
 
 use crate::models::{ApiResponse, LoginResponse};
-use crate::services::ApiClient;
+use crate::services::{notifier, ApiClient};
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -36,6 +36,11 @@ pub async fn login(
                 Some(data.session_key.clone()),
                 Some(data.client_type.clone()),
             );
+            notifier::set_identity(
+                Some(data.user.user_id.clone()),
+                Some(data.user.username.clone()),
+                Some(data.user.display_name.clone()),
+            );
         }
     }
 
@@ -53,13 +58,17 @@ pub async fn set_auth_token(token: Option<String>) -> Result<(), String> {
 }
 
 /// 前端持久化登录态恢复时，同步 refresh 会话信息到 Rust（用于后续自动 refresh）
+/// username / displayName 用于识别群消息中的 @ 我（旧前端不传时仅按 userId 识别指派）
 #[command]
 pub async fn set_auth_session(
     user_id: Option<String>,
     refresh_token: Option<String>,
     session_key: Option<String>,
     client_type: Option<String>,
+    username: Option<String>,
+    display_name: Option<String>,
 ) -> Result<(), String> {
+    notifier::set_identity(user_id.clone(), username, display_name);
     ApiClient::set_auth_session(user_id, refresh_token, session_key, client_type);
     Ok(())
 }
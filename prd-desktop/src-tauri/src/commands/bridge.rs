@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle};
+
+use super::config::{load_config_from_file, save_config_to_file};
+use crate::services::bridge_server::{self, BridgeSettings, BridgeStatus};
+
+/// 新配对的返回值：token 只在这里出现一次，由用户复制到浏览器扩展 / 脚本中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgePairingCreated {
+    pub id: String,
+    pub origin: Option<String>,
+    pub token: String,
+    pub url: String,
+}
+
+/// 写入 config.json 并立即生效
+fn persist(app: &AppHandle, settings: BridgeSettings) -> Result<(), String> {
+    let mut cfg = load_config_from_file(app)?;
+    cfg.bridge = Some(settings.clone());
+    save_config_to_file(app, &cfg)?;
+    bridge_server::set_settings(app, settings);
+    Ok(())
+}
+
+/// 获取桥接服务状态（含已配对的调用方，不含 token）
+#[command]
+pub async fn get_bridge_status() -> Result<BridgeStatus, String> {
+    Ok(bridge_server::status())
+}
+
+/// 开启 / 关闭桥接服务，可修改端口；已有配对保持不变
+#[command]
+pub async fn set_bridge_enabled(
+    app: AppHandle,
+    enabled: bool,
+    port: Option<u16>,
+) -> Result<BridgeStatus, String> {
+    let mut settings = bridge_server::get_settings();
+    if let Some(port) = port {
+        if port < 1024 {
+            return Err("端口需在 1024-65535 之间".to_string());
+        }
+        settings.port = port;
+    }
+    settings.enabled = enabled;
+    persist(&app, settings)?;
+    Ok(bridge_server::status())
+}
+
+/// 新建配对；origin 为空时只允许脚本等非浏览器调用方使用该 token
+#[command]
+pub async fn pair_bridge_client(
+    app: AppHandle,
+    origin: Option<String>,
+    label: Option<String>,
+) -> Result<BridgePairingCreated, String> {
+    let pairing = bridge_server::new_pairing(origin, label)?;
+    let mut settings = bridge_server::get_settings();
+    settings.pairings.push(pairing.clone());
+    let port = settings.port;
+    persist(&app, settings)?;
+    Ok(BridgePairingCreated {
+        id: pairing.id,
+        origin: pairing.origin,
+        token: pairing.token,
+        url: format!("http://127.0.0.1:{}", port),
+    })
+}
+
+/// 撤销配对（对应 token 立即失效）
+#[command]
+pub async fn revoke_bridge_client(app: AppHandle, id: String) -> Result<BridgeStatus, String> {
+    let mut settings = bridge_server::get_settings();
+    let before = settings.pairings.len();
+    settings.pairings.retain(|p| p.id != id);
+    if settings.pairings.len() == before {
+        return Err("配对不存在".to_string());
+    }
+    persist(&app, settings)?;
+    Ok(bridge_server::status())
+}
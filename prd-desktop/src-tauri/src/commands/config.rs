@@ -1,14 +1,19 @@
 use reqwest::{Client, Url};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tauri::Manager;
 use uuid::Uuid;
 
-use crate::services::api_client;
+use crate::services::{
+    api_client, bridge_server, logging, mcp_server, network_inspector, notifier, session_documents,
+    usage_ledger,
+};
 
 /// 应用配置结构
+/// - Option 字段为各功能的设置分组；旧前端保存配置时可能不带这些字段，
+///   save_config 会用文件中的旧值补齐（见 `merge_missing_from`）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppConfig {
@@ -17,6 +22,24 @@ pub struct AppConfig {
     pub is_developer: bool,
     #[serde(default)]
     pub client_id: String,
+    /// 通知设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<notifier::NotificationSettings>,
+    /// 日志级别设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<logging::LoggingConfig>,
+    /// MCP 服务设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp: Option<mcp_server::McpSettings>,
+    /// 本机桥接 API 设置（含配对 token）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridge: Option<bridge_server::BridgeSettings>,
+    /// 会话文档上下文预算（模型上限 / 提示阈值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_budget: Option<session_documents::ContextBudgetSettings>,
+    /// token 用量价格表（清除走 save_usage_pricing）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_pricing: Option<usage_ledger::UsagePricing>,
 }
 
 impl Default for AppConfig {
@@ -25,10 +48,34 @@ impl Default for AppConfig {
             api_base_url: api_client::get_default_api_url(),
             is_developer: false,
             client_id: Uuid::new_v4().to_string(),
+            notifications: None,
+            logging: None,
+            mcp: None,
+            bridge: None,
+            context_budget: None,
+            usage_pricing: None,
         }
     }
 }
 
+impl AppConfig {
+    /// 用文件中的旧配置补齐未传的 clientId 与各设置分组
+    fn merge_missing_from(&mut self, existing: AppConfig) {
+        if self.client_id.trim().is_empty() && !existing.client_id.trim().is_empty() {
+            self.client_id = existing.client_id;
+        }
+        self.notifications = self.notifications.take().or(existing.notifications);
+        self.logging = self.logging.take().or(existing.logging);
+        self.mcp = self.mcp.take().or(existing.mcp);
+        self.bridge = self.bridge.take().or(existing.bridge);
+        self.context_budget = self.context_budget.take().or(existing.context_budget);
+        self.usage_pricing = self.usage_pricing.take().or(existing.usage_pricing);
+    }
+}
+
+/// tauri.conf.json 中的 identifier，决定 app_data_dir 的目录名
+const APP_IDENTIFIER: &str = "com.prdagent.app";
+
 /// 获取配置文件路径
 fn get_config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_data_dir = app
@@ -45,30 +92,59 @@ fn get_config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(app_data_dir.join("config.json"))
 }
 
-/// 加载配置
-fn load_config_from_file(app: &tauri::AppHandle) -> Result<AppConfig, String> {
-    let config_path = get_config_path(app)?;
+/// 与 tauri `app_data_dir()` 相同的目录（<data_dir>/<identifier>），供没有 AppHandle 的 CLI 使用
+pub(crate) fn headless_app_data_dir() -> Option<PathBuf> {
+    let data_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+    data_dir.map(|dir| dir.join(APP_IDENTIFIER))
+}
 
-    if config_path.exists() {
-        let content = fs::read_to_string(&config_path)
-            .map_err(|e| format!("Failed to read config file: {}", e))?;
-        let mut parsed = serde_json::from_str::<AppConfig>(&content)
-            .map_err(|e| format!("Failed to parse config file: {}", e))?;
+/// 读取指定目录下的 config.json；文件不存在时返回 None
+pub(crate) fn read_config_in(dir: &Path) -> Result<Option<AppConfig>, String> {
+    let config_path = dir.join("config.json");
+    if !config_path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| format!("Failed to read config file: {}", e))?;
+    serde_json::from_str::<AppConfig>(&content)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse config file: {}", e))
+}
 
-        // 兼容旧配置：缺少 clientId 时自动补齐并落盘
-        if parsed.client_id.trim().is_empty() {
-            parsed.client_id = Uuid::new_v4().to_string();
-            let _ = save_config_to_file(app, &parsed);
+/// 加载配置
+pub(crate) fn load_config_from_file(app: &tauri::AppHandle) -> Result<AppConfig, String> {
+    let config_path = get_config_path(app)?;
+    let dir = config_path.parent().unwrap_or(Path::new("."));
+
+    match read_config_in(dir)? {
+        Some(mut parsed) => {
+            // 兼容旧配置：缺少 clientId 时自动补齐并落盘
+            if parsed.client_id.trim().is_empty() {
+                parsed.client_id = Uuid::new_v4().to_string();
+                let _ = save_config_to_file(app, &parsed);
+            }
+            Ok(parsed)
         }
-
-        Ok(parsed)
-    } else {
-        Ok(AppConfig::default())
+        None => Ok(AppConfig::default()),
     }
 }
 
 /// 保存配置到文件
-fn save_config_to_file(app: &tauri::AppHandle, config: &AppConfig) -> Result<(), String> {
+pub(crate) fn save_config_to_file(
+    app: &tauri::AppHandle,
+    config: &AppConfig,
+) -> Result<(), String> {
     let config_path = get_config_path(app)?;
     let content = serde_json::to_string_pretty(config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
@@ -105,21 +181,23 @@ pub async fn save_config(app: tauri::AppHandle, config: AppConfig) -> Result<(),
 
     // 更新内存中的 API URL
     api_client::set_api_base_url(to_save.api_base_url.clone());
-    // clientId：若前端未传（兼容旧版），则保留旧值或生成新值，避免写入空串导致 clientId 丢失
+    if let Ok(existing) = load_config_from_file(&app) {
+        to_save.merge_missing_from(existing);
+    }
+    // clientId：文件中也没有时生成新值，避免写入空串导致 clientId 丢失
     if to_save.client_id.trim().is_empty() {
-        let existing = load_config_from_file(&app).ok();
-        to_save.client_id = existing
-            .and_then(|x| {
-                if x.client_id.trim().is_empty() {
-                    None
-                } else {
-                    Some(x.client_id)
-                }
-            })
-            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        to_save.client_id = Uuid::new_v4().to_string();
     }
     api_client::set_client_id(to_save.client_id.clone());
 
+    notifier::set_settings(to_save.notifications.clone().unwrap_or_default());
+    network_inspector::set_enabled(to_save.is_developer);
+    logging::apply_config(&to_save.logging.clone().unwrap_or_default())?;
+    mcp_server::set_settings(to_save.mcp.clone().unwrap_or_default());
+    bridge_server::set_settings(&app, to_save.bridge.clone().unwrap_or_default());
+    session_documents::set_settings(to_save.context_budget.clone().unwrap_or_default());
+    usage_ledger::set_pricing(to_save.usage_pricing.clone());
+
     // 持久化到文件
     save_config_to_file(&app, &to_save)
 }
@@ -325,5 +403,48 @@ pub fn init_config(app: &tauri::AppHandle) {
         if !cfg.client_id.trim().is_empty() {
             api_client::set_client_id(cfg.client_id);
         }
+
+        notifier::set_settings(cfg.notifications.unwrap_or_default());
+        network_inspector::set_enabled(cfg.is_developer);
+
+        if let Err(e) = logging::apply_config(&cfg.logging.unwrap_or_default()) {
+            tracing::warn!("ignoring logging config: {}", e);
+        }
+
+        mcp_server::set_settings(cfg.mcp.unwrap_or_default());
+        bridge_server::set_settings(app, cfg.bridge.unwrap_or_default());
+        session_documents::set_settings(cfg.context_budget.unwrap_or_default());
+        usage_ledger::set_pricing(cfg.usage_pricing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_keeps_passed_sections_and_fills_missing_ones() {
+        let existing = AppConfig {
+            client_id: "existing-client".to_string(),
+            notifications: Some(notifier::NotificationSettings::default()),
+            logging: Some(logging::LoggingConfig::default()),
+            ..AppConfig::default()
+        };
+        let mut incoming = AppConfig {
+            client_id: " ".to_string(),
+            mcp: Some(mcp_server::McpSettings::default()),
+            ..AppConfig::default()
+        };
+        incoming.merge_missing_from(existing);
+        assert_eq!(incoming.client_id, "existing-client");
+        assert!(incoming.notifications.is_some());
+        assert!(incoming.logging.is_some());
+        assert!(incoming.mcp.is_some());
+        assert!(incoming.bridge.is_none());
+
+        let mut own_id = AppConfig::default();
+        let id = own_id.client_id.clone();
+        own_id.merge_missing_from(AppConfig::default());
+        assert_eq!(own_id.client_id, id);
     }
 }
@@ -0,0 +1,491 @@
+//! 清理群组上下文前的本地快照：记录被清掉的消息区间、会话文档与角色，
+//! 之后可整体恢复，或"从第 X 条消息继续"——把该区间的对话整理为一条消息，
+//! 通过 chat-run（skipAiReply）写回群里，服务端拼接上下文时会重新带上它。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, Manager};
+use uuid::Uuid;
+
+use super::group::{clear_context, rejected};
+use super::session::{
+    create_chat_run, get_group_message_history, get_session, CreateChatRunResponse,
+};
+use crate::models::{ApiResponse, MessageHistoryItem};
+use crate::services::session_documents::{self, SessionDocumentLayout};
+
+const STORE_FILE: &str = "context_snapshots.json";
+/// 每个群组保留的快照数
+const MAX_SNAPSHOTS_PER_GROUP: usize = 20;
+const PAGE_SIZE: i32 = 200;
+/// 服务端单条消息上限 16KB，留出前后说明文字的余量
+const MAX_SEED_CHARS: usize = 15_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextSnapshot {
+    pub id: String,
+    pub group_id: String,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    pub created_at_ms: i64,
+    /// 被清理的消息区间（groupSeq，含两端）；from 为空表示从群聊开头
+    #[serde(default)]
+    pub from_seq: Option<i64>,
+    #[serde(default)]
+    pub to_seq: Option<i64>,
+    #[serde(default)]
+    pub document_ids: Vec<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub restored_at_ms: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct SnapshotFile {
+    /// groupId -> snapshots（新的在前）
+    #[serde(default)]
+    groups: HashMap<String, Vec<ContextSnapshot>>,
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+pub(crate) fn store_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+fn load_store(dir: &Path) -> SnapshotFile {
+    // 容错：文件损坏时不阻塞清理上下文，按空处理并在下次写入时覆盖
+    fs::read_to_string(dir.join(STORE_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_store(dir: &Path, store: &SnapshotFile) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let content = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("Failed to serialize snapshots: {}", e))?;
+    fs::write(dir.join(STORE_FILE), content)
+        .map_err(|e| format!("Failed to write snapshots: {}", e))
+}
+
+fn find_snapshot(store: &SnapshotFile, snapshot_id: &str) -> Option<ContextSnapshot> {
+    store
+        .groups
+        .values()
+        .flatten()
+        .find(|s| s.id == snapshot_id)
+        .cloned()
+}
+
+/// 当前最新一条消息的 groupSeq
+async fn latest_seq(group_id: &str) -> Result<Option<i64>, String> {
+    let resp =
+        get_group_message_history(group_id.to_string(), Some(1), None, None, None, None, None)
+            .await?;
+    Ok(resp
+        .data
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|m| m.group_seq)
+        .max())
+}
+
+/// 清理前记录快照；区间起点为上一次快照的终点之后（上一次清理后才进入上下文的消息）
+pub(crate) async fn capture_snapshot(
+    dir: &Path,
+    group_id: &str,
+    session_id: Option<&str>,
+) -> Result<ContextSnapshot, String> {
+    let mut store = load_store(dir);
+    let previous_to = store
+        .groups
+        .get(group_id)
+        .and_then(|list| list.first())
+        .and_then(|s| s.to_seq);
+    let to_seq = latest_seq(group_id).await?;
+
+    let (document_ids, role) = match session_id {
+        Some(sid) => match get_session(sid.to_string()).await?.data {
+            Some(session) => (
+                session_documents::context_document_ids(
+                    sid,
+                    &session_documents::session_document_ids(&session),
+                )
+                .unwrap_or_else(|| session_documents::session_document_ids(&session)),
+                Some(session.current_role),
+            ),
+            None => (Vec::new(), None),
+        },
+        None => (Vec::new(), None),
+    };
+
+    let snapshot = ContextSnapshot {
+        id: Uuid::new_v4().to_string(),
+        group_id: group_id.to_string(),
+        session_id: session_id.map(|s| s.to_string()),
+        created_at_ms: now_ms(),
+        from_seq: previous_to.map(|s| s + 1),
+        to_seq,
+        document_ids,
+        role,
+        restored_at_ms: None,
+    };
+    let list = store.groups.entry(group_id.to_string()).or_default();
+    list.insert(0, snapshot.clone());
+    list.truncate(MAX_SNAPSHOTS_PER_GROUP);
+    save_store(dir, &store)?;
+    Ok(snapshot)
+}
+
+/// 拉取 [from_seq, to_seq] 区间内的消息（服务端 afterSeq 需大于 0，这里按 beforeSeq 从区间末尾向前翻页）
+async fn fetch_range(
+    group_id: &str,
+    from_seq: i64,
+    to_seq: i64,
+) -> Result<Vec<MessageHistoryItem>, String> {
+    let mut out: Vec<MessageHistoryItem> = Vec::new();
+    let mut before = to_seq + 1;
+    while before > from_seq {
+        let resp = get_group_message_history(
+            group_id.to_string(),
+            Some(PAGE_SIZE),
+            None,
+            None,
+            Some(before),
+            None,
+            None,
+        )
+        .await?;
+        let page = resp.data.unwrap_or_default();
+        let Some(min_seq) = page.iter().filter_map(|m| m.group_seq).min() else {
+            break;
+        };
+        out.extend(
+            page.into_iter()
+                .filter(|m| m.group_seq.is_some_and(|s| s >= from_seq && s <= to_seq)),
+        );
+        if min_seq >= before {
+            break;
+        }
+        before = min_seq;
+    }
+    out.sort_by_key(|m| m.group_seq);
+    out.dedup_by(|a, b| a.id == b.id);
+    Ok(out)
+}
+
+/// 把区间内的对话整理为一条上下文消息；超出长度时保留最近的部分
+pub(crate) fn build_seed_message(messages: &[MessageHistoryItem]) -> Option<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut used = 0usize;
+    let mut truncated = false;
+    for m in messages.iter().rev() {
+        let content = m.content.trim();
+        if content.is_empty() {
+            continue;
+        }
+        let sender = m.sender_name.as_deref().unwrap_or(m.role.as_str());
+        let line = format!("[{}] {}", sender, content);
+        let len = line.chars().count() + 1;
+        if used + len > MAX_SEED_CHARS {
+            truncated = true;
+            break;
+        }
+        used += len;
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    let mut seed = String::from("【恢复上下文】以下是此前清理掉的对话，后续回答请将其作为背景：\n");
+    if truncated {
+        seed.push_str("（内容较长，仅保留最近部分）\n");
+    }
+    seed.push_str(&lines.join("\n"));
+    Some(seed)
+}
+
+/// 按快照恢复会话文档的排序与纳入状态；快照中的文档都已不在会话中时保持不变
+async fn restore_document_layout(session_id: &str, document_ids: &[String]) -> Result<(), String> {
+    if document_ids.is_empty() {
+        return Ok(());
+    }
+    let Some(session) = get_session(session_id.to_string()).await?.data else {
+        return Ok(());
+    };
+    let ids = session_documents::session_document_ids(&session);
+    if !ids.iter().any(|id| document_ids.contains(id)) {
+        return Ok(());
+    }
+    session_documents::set_layout(
+        session_id,
+        SessionDocumentLayout {
+            order: document_ids
+                .iter()
+                .filter(|id| ids.contains(id))
+                .cloned()
+                .collect(),
+            excluded: ids
+                .iter()
+                .filter(|id| !document_ids.contains(id))
+                .cloned()
+                .collect(),
+        },
+    )
+}
+
+/// 恢复快照：from_seq 为空时恢复整个区间，否则"从第 X 条消息继续"；
+/// 消息写回成功后再恢复快照时的会话文档排序 / 纳入状态
+pub(crate) async fn restore_snapshot(
+    dir: &Path,
+    snapshot_id: &str,
+    session_id: &str,
+    from_seq: Option<i64>,
+) -> Result<ApiResponse<CreateChatRunResponse>, String> {
+    let mut store = load_store(dir);
+    let Some(snapshot) = find_snapshot(&store, snapshot_id) else {
+        return Ok(rejected("NOT_FOUND", "快照不存在"));
+    };
+    let Some(to_seq) = snapshot.to_seq else {
+        return Ok(rejected("INVALID_FORMAT", "快照中没有可恢复的消息"));
+    };
+    let start = from_seq
+        .or(snapshot.from_seq)
+        .unwrap_or(1)
+        .max(snapshot.from_seq.unwrap_or(1));
+    if start > to_seq {
+        return Ok(rejected("INVALID_FORMAT", "起始消息不在快照范围内"));
+    }
+    let messages = fetch_range(&snapshot.group_id, start, to_seq).await?;
+    let Some(seed) = build_seed_message(&messages) else {
+        return Ok(rejected("INVALID_FORMAT", "快照中没有可恢复的消息"));
+    };
+
+    let resp = create_chat_run(
+        session_id.to_string(),
+        seed,
+        snapshot.role.clone(),
+        None,
+        None,
+        Some(true),
+    )
+    .await?;
+    if resp.success {
+        restore_document_layout(session_id, &snapshot.document_ids).await?;
+        if let Some(s) = store
+            .groups
+            .get_mut(&snapshot.group_id)
+            .and_then(|list| list.iter_mut().find(|s| s.id == snapshot_id))
+        {
+            s.restored_at_ms = Some(now_ms());
+        }
+        save_store(dir, &store)?;
+    }
+    Ok(resp)
+}
+
+/// 列出某群组的上下文清理记录（新的在前）
+#[command]
+pub async fn list_context_snapshots(
+    app: AppHandle,
+    group_id: String,
+) -> Result<Vec<ContextSnapshot>, String> {
+    let store = load_store(&store_dir(&app)?);
+    Ok(store
+        .groups
+        .get(group_id.trim())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// 恢复被清理的上下文；from_seq 指定时从该条消息开始。
+/// 区间内的对话会整理为一条**群内可见**的消息（最长约 15KB，超出时只保留最近部分）
+/// 以 skipAiReply 发出，同时恢复快照时的会话文档排序与纳入状态
+#[command]
+pub async fn restore_group_context(
+    app: AppHandle,
+    snapshot_id: String,
+    session_id: String,
+    from_seq: Option<i64>,
+) -> Result<ApiResponse<CreateChatRunResponse>, String> {
+    restore_snapshot(
+        &store_dir(&app)?,
+        snapshot_id.trim(),
+        session_id.trim(),
+        from_seq,
+    )
+    .await
+}
+
+fn remove_snapshot(dir: &Path, snapshot_id: &str) -> Result<(), String> {
+    let mut store = load_store(dir);
+    for list in store.groups.values_mut() {
+        list.retain(|s| s.id != snapshot_id);
+    }
+    store.groups.retain(|_, list| !list.is_empty());
+    save_store(dir, &store)
+}
+
+/// 删除一条快照记录（不影响服务端）
+#[command]
+pub async fn delete_context_snapshot(app: AppHandle, snapshot_id: String) -> Result<(), String> {
+    remove_snapshot(&store_dir(&app)?, snapshot_id.trim())
+}
+
+/// 清理上下文并在清理前记录快照；快照失败只记日志，不阻塞清理；清理失败时丢弃快照
+pub(crate) async fn clear_with_snapshot(
+    dir: &Path,
+    group_id: &str,
+    session_id: Option<&str>,
+) -> Result<ApiResponse<serde_json::Value>, String> {
+    let snapshot = match capture_snapshot(dir, group_id, session_id).await {
+        Ok(snapshot) => Some(snapshot),
+        Err(e) => {
+            tracing::warn!("failed to snapshot group context: {}", e);
+            None
+        }
+    };
+    let result = clear_context(group_id).await;
+    let Some(snapshot) = snapshot else {
+        return result;
+    };
+    match result {
+        Ok(mut resp) if resp.success => {
+            // 返回快照 id，前端可直接提供"撤销清理"
+            resp.data = Some(serde_json::json!({ "snapshotId": snapshot.id }));
+            Ok(resp)
+        }
+        other => {
+            let _ = remove_snapshot(dir, &snapshot.id);
+            other
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{signed_in, MockResponse};
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn context_snapshot_restores_cleared_range() {
+        let (_guard, server) = signed_in().await;
+        let dir = std::env::temp_dir().join(format!("prd-snapshots-{}", uuid::Uuid::new_v4()));
+        let history = json!([
+            { "id": "m-1", "groupSeq": 1, "senderName": "Mock 用户", "role": "User",
+              "content": "登录页需要验证码吗？", "timestamp": "2026-01-01T00:00:00Z" },
+            { "id": "m-2", "groupSeq": 2, "role": "Assistant",
+              "content": "需要，见 3.2 节。", "timestamp": "2026-01-01T00:00:05Z" },
+            { "id": "m-3", "groupSeq": 3, "senderName": "Mock 用户", "role": "User",
+              "content": "验证码有效期多久？", "timestamp": "2026-01-01T00:00:10Z" }
+        ]);
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1/messages",
+            MockResponse::ok(json!([history[2].clone()])),
+        );
+        let session = json!({
+            "sessionId": "session-1", "groupId": "group-1", "documentId": "doc-1",
+            "documentIds": ["doc-1", "doc-2"], "currentRole": "PM", "mode": "QA"
+        });
+        server.script(
+            Method::GET,
+            "/api/v1/sessions/session-1",
+            MockResponse::ok(session.clone()),
+        );
+        session_documents::set_layout(
+            "session-1",
+            session_documents::SessionDocumentLayout {
+                order: vec!["doc-2".into(), "doc-1".into()],
+                excluded: vec!["doc-1".into()],
+            },
+        )
+        .expect("layout");
+
+        let resp = clear_with_snapshot(&dir, "group-1", Some("session-1"))
+            .await
+            .expect("clear");
+        assert!(resp.success);
+        session_documents::set_layout("session-1", Default::default()).expect("reset");
+        let snapshot_id = resp.data.expect("data")["snapshotId"]
+            .as_str()
+            .expect("snapshot id")
+            .to_string();
+        let snapshot = capture_snapshot(&dir, "group-2", None)
+            .await
+            .expect("capture");
+        assert_eq!(snapshot.to_seq, None);
+
+        // 从第 2 条继续：只带回 2..=3，并且不触发 AI 回复
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1/messages",
+            MockResponse::ok(history.clone()),
+        );
+        server.script(
+            Method::POST,
+            "/api/v1/sessions/*/messages/run",
+            MockResponse::ok(
+                json!({ "userMessageId": "m-4", "groupSeq": 4, "skippedAiReply": true }),
+            ),
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/sessions/session-1",
+            MockResponse::ok(session),
+        );
+        let resp = restore_snapshot(&dir, &snapshot_id, "session-1", Some(2))
+            .await
+            .expect("restore");
+        assert!(resp.success);
+        // 快照时只纳入了 doc-2：恢复后 doc-1 重新被移出
+        let layout = session_documents::get_layout("session-1");
+        assert_eq!(layout.order, ["doc-2"]);
+        assert_eq!(layout.excluded, ["doc-1"]);
+        session_documents::set_layout("session-1", Default::default()).expect("reset");
+        let runs = server.requests_to("/api/v1/sessions/*/messages/run");
+        assert_eq!(runs.len(), 1);
+        let body = runs[0].json();
+        assert_eq!(body["skipAiReply"], json!(true));
+        assert_eq!(body["role"], json!("PM"));
+        let seed = body["content"].as_str().expect("content");
+        assert!(seed.contains("需要，见 3.2 节。") && seed.contains("验证码有效期多久？"));
+        assert!(!seed.contains("登录页需要验证码吗？"));
+        assert!(server.requests_to("/api/v1/groups/group-1/messages")[1]
+            .query
+            .as_deref()
+            .is_some_and(|q| q.contains("beforeSeq=4")));
+
+        // 清理失败时不留下快照
+        server.script(
+            Method::POST,
+            "/api/v1/groups/group-1/context/clear",
+            MockResponse::error(403, "PERMISSION_DENIED", "无权限"),
+        );
+        let resp = clear_with_snapshot(&dir, "group-1", None)
+            .await
+            .expect("clear");
+        assert!(!resp.success);
+        let store: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.join("context_snapshots.json")).expect("store"),
+        )
+        .expect("json");
+        let kept = store["groups"]["group-1"].as_array().expect("group-1");
+        assert_eq!(kept.len(), 1);
+        assert!(kept[0]["restoredAtMs"].is_i64());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,90 @@
+use tauri::{command, AppHandle};
+
+use super::defect::{create_defect, delete_defect, submit_defect};
+use crate::models::{AddDefectAttachmentResponse, ApiResponse, DefectEnvelope};
+use crate::services::crash_reporter::{self, CrashReport};
+use crate::services::ApiClient;
+
+const DEFAULT_SEVERITY: &str = "critical";
+const TITLE_MAX_CHARS: usize = 60;
+
+/// 上次运行遗留的崩溃报告（前端启动 / 登录后调用，有则提示用户上报）
+#[command]
+pub async fn list_crash_reports(app: AppHandle) -> Result<Vec<CrashReport>, String> {
+    crash_reporter::list_reports(&app)
+}
+
+/// 把崩溃报告提交为缺陷：create → 上传完整报告（JSON 附件）→ submit
+/// - 附件上传或 submit 失败时删除刚创建的缺陷，本地报告保留以便重试
+/// - 成功后删除本地报告
+#[command]
+pub async fn file_crash_report(
+    app: AppHandle,
+    id: String,
+    assignee_user_id: String,
+    severity: Option<String>,
+) -> Result<ApiResponse<DefectEnvelope>, String> {
+    let report = crash_reporter::load_report(&app, &id)?;
+
+    let summary: String = report.message.chars().take(TITLE_MAX_CHARS).collect();
+    let mut content = format!(
+        "桌面端崩溃：{}\n\n版本：{} ({})\n时间：{}",
+        report.message, report.app_version, report.target, report.created_at
+    );
+    if let Some(location) = &report.location {
+        content.push_str(&format!("\n位置：{}", location));
+    }
+
+    let created = create_defect(
+        content,
+        severity
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_SEVERITY.to_string()),
+        Some(format!("[崩溃] {}", summary)),
+        assignee_user_id,
+        None,
+    )
+    .await?;
+    let defect_id = match &created.data {
+        Some(data) if created.success => data.defect.id.clone(),
+        _ => return Ok(created),
+    };
+
+    let bytes = serde_json::to_vec_pretty(&report)
+        .map_err(|e| format!("Failed to serialize crash report: {}", e))?;
+    let uploaded = ApiClient::new()
+        .post_file::<AddDefectAttachmentResponse>(
+            &format!("/api/defect-agent/defects/{}/attachments", defect_id),
+            bytes,
+            format!("crash-{}.json", report.id),
+            "application/json".to_string(),
+        )
+        .await;
+    if !matches!(&uploaded, Ok(r) if r.success) {
+        let _ = delete_defect(defect_id).await;
+        return Err(match uploaded {
+            Ok(r) => r
+                .error
+                .map(|e| e.message)
+                .unwrap_or_else(|| "Failed to upload crash report".to_string()),
+            Err(e) => e,
+        });
+    }
+
+    let submitted = submit_defect(defect_id.clone(), None).await;
+    match &submitted {
+        Ok(r) if r.success => {
+            let _ = crash_reporter::remove_report(&app, &report.id);
+        }
+        _ => {
+            let _ = delete_defect(defect_id).await;
+        }
+    }
+    submitted
+}
+
+/// 忽略崩溃报告（删除本地文件）
+#[command]
+pub async fn dismiss_crash_report(app: AppHandle, id: String) -> Result<(), String> {
+    crash_reporter::remove_report(&app, &id)
+}
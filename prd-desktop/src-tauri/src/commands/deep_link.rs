@@ -0,0 +1,15 @@
+use tauri::command;
+
+use crate::services::deep_link::{self, DeepLinkRoute};
+
+/// 取走冷启动时收到、前端尚未处理的深链路由
+#[command]
+pub async fn take_pending_deep_link() -> Result<Option<DeepLinkRoute>, String> {
+    Ok(deep_link::take_pending())
+}
+
+/// 解析 prdagent:// 链接（如用户粘贴的链接），非法链接返回错误原因
+#[command]
+pub async fn parse_deep_link(url: String) -> Result<DeepLinkRoute, String> {
+    deep_link::parse(&url)
+}
@@ -1,8 +1,92 @@
-use serde::Serialize;
-use tauri::command;
+use futures::StreamExt;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tokio_util::sync::CancellationToken;
 
-use crate::models::ApiResponse;
-use crate::services::ApiClient;
+use crate::models::{
+    AddDefectAttachmentResponse, ApiResponse, Defect, DefectAction, DefectDetailResponse,
+    DefectEnvelope, DefectItemsResponse, DefectListFilter, DefectListResponse, DefectMessage,
+    DefectMessagesResponse, DefectStats, DefectStatus, DefectTemplate, DefectUser,
+    SendDefectMessageResponse,
+};
+use crate::services::client_log::{self, ClientLogEntry};
+use crate::services::{api_client, notifier, ApiClient};
+
+use super::session::{
+    emit_auth_expired, open_event_stream, pump_event_stream, StreamCancelState, StreamSink,
+};
+
+/// 服务端单页上限（DefectAgentController.ListDefects 中 MaxPageSize）
+const MAX_PAGE_SIZE: i64 = 500;
+/// list_all_defects 翻页保护：500 × 200 = 10 万条，足够覆盖任何团队
+const MAX_PAGES: usize = 200;
+
+// ---------------------------------------------------------------------------
+// Local cache
+// ---------------------------------------------------------------------------
+
+/// 本地缺陷缓存：list_all_defects 全量翻页后写入，供前端离线筛选/排序
+#[derive(Default)]
+pub struct DefectCacheState {
+    defects: Mutex<HashMap<String, Defect>>,
+    synced_at_ms: Mutex<Option<i64>>,
+}
+
+impl DefectCacheState {
+    fn replace_all(&self, items: &[Defect]) {
+        let mut guard = self.defects.lock().unwrap();
+        guard.clear();
+        for d in items {
+            guard.insert(d.id.clone(), d.clone());
+        }
+        *self.synced_at_ms.lock().unwrap() = Some(now_ms());
+    }
+
+    fn upsert_many(&self, items: &[Defect]) {
+        let mut guard = self.defects.lock().unwrap();
+        for d in items {
+            guard.insert(d.id.clone(), d.clone());
+        }
+        *self.synced_at_ms.lock().unwrap() = Some(now_ms());
+    }
+
+    /// 外层 None 表示缓存中没有该缺陷
+    fn assignee_of(&self, id: &str) -> Option<Option<String>> {
+        self.defects
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|d| d.assignee_id.clone())
+    }
+
+    fn query(&self, filter: &DefectListFilter) -> Vec<Defect> {
+        let mut items: Vec<Defect> = self
+            .defects
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|d| filter.matches(d))
+            .cloned()
+            .collect();
+        filter.sort_defects(&mut items);
+        items
+    }
+
+    fn synced_at_ms(&self) -> Option<i64> {
+        *self.synced_at_ms.lock().unwrap()
+    }
+}
+
+fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
 
 // ---------------------------------------------------------------------------
 // Request bodies
@@ -53,28 +137,218 @@ struct PolishDefectRequest {
 #[serde(rename_all = "camelCase")]
 struct EmptyBody {}
 
+/// 某个状态下可执行的流转动作
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectTransition {
+    pub action: DefectAction,
+    pub target_status: DefectStatus,
+    pub requires_text: bool,
+    pub requires_assignee: bool,
+}
+
+/// 本地缓存查询结果
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedDefects {
+    pub items: Vec<Defect>,
+    pub total: usize,
+    pub synced_at_ms: Option<i64>,
+}
+
+fn defect_action_path(id: &str, action: DefectAction) -> String {
+    format!("/api/defect-agent/defects/{}/{}", id, action.as_str())
+}
+
+fn defect_messages_path(id: &str, after_seq: Option<i64>) -> String {
+    match after_seq {
+        Some(seq) => format!("/api/defect-agent/defects/{}/messages?afterSeq={}", id, seq),
+        None => format!("/api/defect-agent/defects/{}/messages", id),
+    }
+}
+
+/// 只透传服务端支持的条件（DefectAgentController.ListDefects）；其余条件由调用方在本地处理
+fn build_list_path(filter: &DefectListFilter, offset: i64, limit: i64) -> String {
+    let mut url = Url::parse("http://localhost/api/defect-agent/defects").expect("static url");
+    {
+        let mut q = url.query_pairs_mut();
+        q.append_pair("limit", &limit.to_string());
+        q.append_pair("offset", &offset.to_string());
+        if let Some(scope) = filter.scope() {
+            q.append_pair("filter", scope);
+        }
+        if let Some(status) = &filter.status {
+            q.append_pair("status", status.as_str());
+        }
+        if let Some(severity) = &filter.severity {
+            q.append_pair("severity", severity.as_str());
+        }
+        if let Some(assignee) = filter.assignee_id() {
+            q.append_pair("assigneeId", assignee);
+        }
+    }
+    format!("{}?{}", url.path(), url.query().unwrap_or_default())
+}
+
+/// 拉取服务端一页（按 createdAt 倒序），nextCursor 与 total 均以服务端结果为准
+async fn fetch_defect_page(
+    client: &ApiClient,
+    filter: &DefectListFilter,
+    offset: i64,
+    limit: i64,
+) -> Result<ApiResponse<DefectListResponse>, String> {
+    let mut resp: ApiResponse<DefectListResponse> =
+        client.get(&build_list_path(filter, offset, limit)).await?;
+    if let Some(page) = resp.data.as_mut() {
+        let fetched = page.items.len() as i64;
+        let next = offset + fetched;
+        let reached_end = fetched < limit || (page.total > 0 && next >= page.total);
+        page.next_cursor = if reached_end {
+            None
+        } else {
+            Some(next.to_string())
+        };
+    }
+    Ok(resp)
+}
+
+/// 在本地过滤排序后的全量结果上切出一页
+fn slice_page(all: DefectListResponse, offset: i64, limit: i64) -> DefectListResponse {
+    let total = all.items.len() as i64;
+    let end = (offset + limit).min(total);
+    let items = all
+        .items
+        .into_iter()
+        .skip(offset as usize)
+        .take((end - offset).max(0) as usize)
+        .collect();
+    DefectListResponse {
+        items,
+        total,
+        limit: Some(limit),
+        offset: Some(offset),
+        next_cursor: (end < total).then(|| end.to_string()),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Commands
 // ---------------------------------------------------------------------------
 
-/// 获取缺陷列表
-/// limit 显式传 500：后端历史默认 pageSize=20，若不显式加大会导致"用户明明有更多缺陷却看不见"
+/// 获取缺陷列表（单页）
+/// - 未传 limit 时仍按 500 拉取：后端历史默认 pageSize=20，若不显式加大会导致"用户明明有更多缺陷却看不见"
+/// - 含服务端不支持的条件（reporter、日期、关键字、非默认排序）时先拉全量再在本地分页，
+///   total 为本地过滤后的条数
+/// - 返回 nextCursor 时说明还有下一页，前端应继续翻页或改用 list_all_defects
 #[command]
-pub async fn list_defects() -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn list_defects(
+    filter: Option<DefectListFilter>,
+) -> Result<ApiResponse<DefectListResponse>, String> {
+    let filter = filter.unwrap_or_default();
+    filter.validate()?;
+    let limit = filter
+        .limit
+        .unwrap_or(MAX_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
     let client = ApiClient::new();
-    client.get("/api/defect-agent/defects?limit=500").await
+    if !filter.needs_local_query() {
+        return fetch_defect_page(&client, &filter, filter.offset(), limit).await;
+    }
+    let resp = fetch_all_defects(&client, &filter).await?;
+    Ok(ApiResponse {
+        data: resp.data.map(|all| slice_page(all, filter.offset(), limit)),
+        ..resp
+    })
+}
+
+/// 翻页拉取全部匹配的缺陷（忽略 filter.cursor / filter.limit），跨页去重后在本地过滤并排序
+pub(crate) async fn fetch_all_defects(
+    client: &ApiClient,
+    filter: &DefectListFilter,
+) -> Result<ApiResponse<DefectListResponse>, String> {
+    filter.validate()?;
+    let mut items: Vec<Defect> = Vec::new();
+    let mut offset = 0;
+
+    for _ in 0..MAX_PAGES {
+        let resp = fetch_defect_page(client, filter, offset, MAX_PAGE_SIZE).await?;
+        if !resp.success {
+            return Ok(resp);
+        }
+        let Some(page) = resp.data else {
+            break;
+        };
+        items.extend(page.items);
+        match page.next_cursor.and_then(|c| c.parse::<i64>().ok()) {
+            Some(next) => offset = next,
+            None => break,
+        }
+    }
+
+    // 跨页去重（翻页期间有新缺陷插入会导致 offset 错位出现重复）
+    let mut seen = std::collections::HashSet::new();
+    items.retain(|d| seen.insert(d.id.clone()) && filter.matches(d));
+    filter.sort_defects(&mut items);
+
+    Ok(ApiResponse {
+        success: true,
+        data: Some(DefectListResponse {
+            total: items.len() as i64,
+            limit: None,
+            offset: Some(0),
+            next_cursor: None,
+            items,
+        }),
+        error: None,
+    })
+}
+
+/// 翻页拉取全部匹配的缺陷并写入本地缓存（忽略 filter.cursor / filter.limit）
+#[command]
+pub async fn list_all_defects(
+    cache: State<'_, DefectCacheState>,
+    filter: Option<DefectListFilter>,
+) -> Result<ApiResponse<DefectListResponse>, String> {
+    let filter = filter.unwrap_or_default();
+    let resp = fetch_all_defects(&ApiClient::new(), &filter).await?;
+
+    if let Some(page) = resp.data.as_ref() {
+        if filter.is_unfiltered() {
+            cache.replace_all(&page.items);
+        } else {
+            cache.upsert_many(&page.items);
+        }
+    }
+
+    Ok(resp)
+}
+
+/// 查询本地缓存（不发请求），需先调用过 list_all_defects
+#[command]
+pub async fn get_cached_defects(
+    cache: State<'_, DefectCacheState>,
+    filter: Option<DefectListFilter>,
+) -> Result<CachedDefects, String> {
+    let filter = filter.unwrap_or_default();
+    let items = cache.query(&filter);
+    Ok(CachedDefects {
+        total: items.len(),
+        synced_at_ms: cache.synced_at_ms(),
+        items,
+    })
 }
 
 /// 获取缺陷管理用户列表（用于选择提交对象）
 #[command]
-pub async fn list_defect_users() -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn list_defect_users() -> Result<ApiResponse<DefectItemsResponse<DefectUser>>, String> {
     let client = ApiClient::new();
     client.get("/api/defect-agent/users").await
 }
 
 /// 获取缺陷模板列表
 #[command]
-pub async fn list_defect_templates() -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn list_defect_templates(
+) -> Result<ApiResponse<DefectItemsResponse<DefectTemplate>>, String> {
     let client = ApiClient::new();
     client.get("/api/defect-agent/templates").await
 }
@@ -87,7 +361,7 @@ pub async fn create_defect(
     title: Option<String>,
     assignee_user_id: String,
     template_id: Option<String>,
-) -> Result<ApiResponse<serde_json::Value>, String> {
+) -> Result<ApiResponse<DefectEnvelope>, String> {
     let client = ApiClient::new();
     let request = CreateDefectRequest {
         content,
@@ -100,18 +374,25 @@ pub async fn create_defect(
 }
 
 /// 提交缺陷（触发 Agent 处理流程）
+/// attach_client_logs=true 时先把桌面端最近的客户端日志作为附件上传（上传失败不阻塞提交）
 #[command]
-pub async fn submit_defect(id: String) -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn submit_defect(
+    id: String,
+    attach_client_logs: Option<bool>,
+) -> Result<ApiResponse<DefectEnvelope>, String> {
     let client = ApiClient::new();
+    if attach_client_logs.unwrap_or(false) {
+        let _ = upload_client_logs(&client, &id).await;
+    }
     let body = EmptyBody {};
     client
-        .post(&format!("/api/defect-agent/defects/{}/submit", id), &body)
+        .post(&defect_action_path(&id, DefectAction::Submit), &body)
         .await
 }
 
 /// 获取单个缺陷详情
 #[command]
-pub async fn get_defect(id: String) -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn get_defect(id: String) -> Result<ApiResponse<DefectDetailResponse>, String> {
     let client = ApiClient::new();
     client
         .get(&format!("/api/defect-agent/defects/{}", id))
@@ -123,13 +404,9 @@ pub async fn get_defect(id: String) -> Result<ApiResponse<serde_json::Value>, St
 pub async fn get_defect_messages(
     id: String,
     after_seq: Option<i64>,
-) -> Result<ApiResponse<serde_json::Value>, String> {
+) -> Result<ApiResponse<DefectMessagesResponse>, String> {
     let client = ApiClient::new();
-    let path = match after_seq {
-        Some(seq) => format!("/api/defect-agent/defects/{}/messages?afterSeq={}", id, seq),
-        None => format!("/api/defect-agent/defects/{}/messages", id),
-    };
-    client.get(&path).await
+    client.get(&defect_messages_path(&id, after_seq)).await
 }
 
 /// 发送缺陷消息
@@ -137,7 +414,7 @@ pub async fn get_defect_messages(
 pub async fn send_defect_message(
     id: String,
     content: String,
-) -> Result<ApiResponse<serde_json::Value>, String> {
+) -> Result<ApiResponse<SendDefectMessageResponse>, String> {
     let client = ApiClient::new();
     let request = SendDefectMessageRequest { content };
     client
@@ -150,11 +427,11 @@ pub async fn send_defect_message(
 
 /// 处理缺陷（标记为处理中）
 #[command]
-pub async fn process_defect(id: String) -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn process_defect(id: String) -> Result<ApiResponse<DefectEnvelope>, String> {
     let client = ApiClient::new();
     let body = EmptyBody {};
     client
-        .post(&format!("/api/defect-agent/defects/{}/process", id), &body)
+        .post(&defect_action_path(&id, DefectAction::Process), &body)
         .await
 }
 
@@ -163,14 +440,11 @@ pub async fn process_defect(id: String) -> Result<ApiResponse<serde_json::Value>
 pub async fn resolve_defect(
     id: String,
     resolution: String,
-) -> Result<ApiResponse<serde_json::Value>, String> {
+) -> Result<ApiResponse<DefectEnvelope>, String> {
     let client = ApiClient::new();
     let request = ResolveDefectRequest { resolution };
     client
-        .post(
-            &format!("/api/defect-agent/defects/{}/resolve", id),
-            &request,
-        )
+        .post(&defect_action_path(&id, DefectAction::Resolve), &request)
         .await
 }
 
@@ -179,24 +453,21 @@ pub async fn resolve_defect(
 pub async fn reject_defect(
     id: String,
     reason: String,
-) -> Result<ApiResponse<serde_json::Value>, String> {
+) -> Result<ApiResponse<DefectEnvelope>, String> {
     let client = ApiClient::new();
     let request = RejectDefectRequest { reason };
     client
-        .post(
-            &format!("/api/defect-agent/defects/{}/reject", id),
-            &request,
-        )
+        .post(&defect_action_path(&id, DefectAction::Reject), &request)
         .await
 }
 
 /// 关闭缺陷（标记为已完成）
 #[command]
-pub async fn close_defect(id: String) -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn close_defect(id: String) -> Result<ApiResponse<DefectEnvelope>, String> {
     let client = ApiClient::new();
     let body = EmptyBody {};
     client
-        .post(&format!("/api/defect-agent/defects/{}/close", id), &body)
+        .post(&defect_action_path(&id, DefectAction::Close), &body)
         .await
 }
 
@@ -211,14 +482,11 @@ pub async fn delete_defect(id: String) -> Result<ApiResponse<serde_json::Value>,
 
 /// 验收通过
 #[command]
-pub async fn verify_pass_defect(id: String) -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn verify_pass_defect(id: String) -> Result<ApiResponse<DefectEnvelope>, String> {
     let client = ApiClient::new();
     let body = EmptyBody {};
     client
-        .post(
-            &format!("/api/defect-agent/defects/{}/verify-pass", id),
-            &body,
-        )
+        .post(&defect_action_path(&id, DefectAction::VerifyPass), &body)
         .await
 }
 
@@ -227,20 +495,17 @@ pub async fn verify_pass_defect(id: String) -> Result<ApiResponse<serde_json::Va
 pub async fn verify_fail_defect(
     id: String,
     reason: String,
-) -> Result<ApiResponse<serde_json::Value>, String> {
+) -> Result<ApiResponse<DefectEnvelope>, String> {
     let client = ApiClient::new();
     let request = VerifyFailRequest { reason };
     client
-        .post(
-            &format!("/api/defect-agent/defects/{}/verify-fail", id),
-            &request,
-        )
+        .post(&defect_action_path(&id, DefectAction::VerifyFail), &request)
         .await
 }
 
 /// 获取缺陷统计信息
 #[command]
-pub async fn get_defect_stats() -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn get_defect_stats() -> Result<ApiResponse<DefectStats>, String> {
     let client = ApiClient::new();
     client.get("/api/defect-agent/stats").await
 }
@@ -268,6 +533,29 @@ pub async fn preview_defect_logs() -> Result<ApiResponse<serde_json::Value>, Str
     client.get("/api/defect-agent/logs/preview").await
 }
 
+/// 预览桌面端客户端日志（最近的请求、流事件与错误；请求头已脱敏）
+#[command]
+pub async fn preview_client_logs(limit: Option<usize>) -> Result<Vec<ClientLogEntry>, String> {
+    Ok(client_log::snapshot(limit))
+}
+
+async fn upload_client_logs(
+    client: &ApiClient,
+    id: &str,
+) -> Result<ApiResponse<AddDefectAttachmentResponse>, String> {
+    let entries = client_log::snapshot(None);
+    let bytes = serde_json::to_vec_pretty(&entries)
+        .map_err(|e| format!("Failed to serialize client logs: {}", e))?;
+    client
+        .post_file(
+            &format!("/api/defect-agent/defects/{}/attachments", id),
+            bytes,
+            format!("client-logs-{}.json", now_ms()),
+            "application/json".to_string(),
+        )
+        .await
+}
+
 /// 上传缺陷附件（base64 编码的文件）
 #[command]
 pub async fn add_defect_attachment(
@@ -275,7 +563,7 @@ pub async fn add_defect_attachment(
     file_base64: String,
     file_name: String,
     mime_type: String,
-) -> Result<ApiResponse<serde_json::Value>, String> {
+) -> Result<ApiResponse<AddDefectAttachmentResponse>, String> {
     use base64::Engine;
     let file_bytes = base64::engine::general_purpose::STANDARD
         .decode(&file_base64)
@@ -291,3 +579,595 @@ pub async fn add_defect_attachment(
         )
         .await
 }
+
+/// 查询某状态下合法的流转动作（前端据此禁用不可用按钮，权限仍以后端校验为准）
+#[command]
+pub async fn get_defect_transitions(status: String) -> Result<Vec<DefectTransition>, String> {
+    let status = DefectStatus::from(status);
+    Ok(status
+        .allowed_actions()
+        .into_iter()
+        .map(|action| DefectTransition {
+            action,
+            target_status: action.target_status(),
+            requires_text: action.requires_text(),
+            requires_assignee: action.requires_assignee(),
+        })
+        .collect())
+}
+
+// ---------------------------------------------------------------------------
+// Bulk operations
+// ---------------------------------------------------------------------------
+
+const DEFECT_BULK_PROGRESS_EVENT: &str = "defect-bulk-progress";
+/// 并发上限：避免一次性打满服务端（分拣时常常一次选几十条）
+const BULK_CONCURRENCY: usize = 4;
+/// 瞬时错误（网络 / 限流 / 5xx）的最大重试次数
+const BULK_MAX_RETRIES: u32 = 2;
+const BULK_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// 批量动作的附加参数：text 对应 resolution / reason，assigneeId 用于 assign
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkDefectPayload {
+    pub text: Option<String>,
+    pub assignee_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AssignDefectRequest {
+    assignee_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkDefectItemResult {
+    pub id: String,
+    pub success: bool,
+    /// 本地缓存显示当前状态不允许该动作，未发请求
+    pub skipped: bool,
+    pub attempts: u32,
+    pub defect: Option<Defect>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkDefectActionResult {
+    pub action: DefectAction,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// 与入参 ids 顺序一致
+    pub items: Vec<BulkDefectItemResult>,
+}
+
+/// 执行单个流转动作（按动作拼请求体）
+async fn run_defect_action(
+    client: &ApiClient,
+    id: &str,
+    action: DefectAction,
+    payload: &BulkDefectPayload,
+) -> Result<ApiResponse<DefectEnvelope>, String> {
+    let path = defect_action_path(id, action);
+    let text = payload.text.clone().unwrap_or_default();
+    match action {
+        DefectAction::Resolve => {
+            client
+                .post(&path, &ResolveDefectRequest { resolution: text })
+                .await
+        }
+        DefectAction::Reject => {
+            client
+                .post(&path, &RejectDefectRequest { reason: text })
+                .await
+        }
+        DefectAction::VerifyFail => {
+            client
+                .post(&path, &VerifyFailRequest { reason: text })
+                .await
+        }
+        DefectAction::Assign => {
+            let request = AssignDefectRequest {
+                assignee_id: payload.assignee_id.clone().unwrap_or_default(),
+            };
+            client.post(&path, &request).await
+        }
+        _ => client.post(&path, &EmptyBody {}).await,
+    }
+}
+
+/// 网络错误、非 JSON 响应（网关 502/504 页面）与限流 / 服务端内部错误视为可重试
+fn is_transient(result: &Result<ApiResponse<DefectEnvelope>, String>) -> bool {
+    match result {
+        Err(_) => true,
+        Ok(r) if r.success => false,
+        Ok(r) => matches!(
+            r.error.as_ref().map(|e| e.code.as_str()),
+            Some("RATE_LIMITED") | Some("INTERNAL_ERROR")
+        ),
+    }
+}
+
+enum Recheck {
+    Retry,
+    /// 已生效（视为成功）或状态已变为不可执行（不再重试）
+    Done(Box<Result<ApiResponse<DefectEnvelope>, String>>),
+    /// 状态读取失败，无法确认是否已生效，保留原错误
+    GiveUp,
+}
+
+/// 动作是否已在该缺陷上生效
+fn action_applied(defect: &Defect, action: DefectAction, payload: &BulkDefectPayload) -> bool {
+    match action {
+        DefectAction::Assign => {
+            defect.status == DefectStatus::Assigned
+                && defect.assignee_id.as_deref() == payload.assignee_id.as_deref().map(str::trim)
+        }
+        _ => defect.status == action.target_status(),
+    }
+}
+
+async fn recheck_before_retry(
+    client: &ApiClient,
+    id: &str,
+    action: DefectAction,
+    payload: &BulkDefectPayload,
+) -> Recheck {
+    let resp = client
+        .get::<DefectDetailResponse>(&format!("/api/defect-agent/defects/{}", id))
+        .await;
+    let Some(defect) = resp.ok().and_then(|r| r.data).map(|d| d.defect) else {
+        return Recheck::GiveUp;
+    };
+    if action_applied(&defect, action, payload) {
+        return Recheck::Done(Box::new(Ok(ApiResponse {
+            success: true,
+            data: Some(DefectEnvelope { defect }),
+            error: None,
+        })));
+    }
+    if !action.is_allowed_from(&defect.status) {
+        return Recheck::Done(Box::new(Err(format!(
+            "当前状态 {} 不支持 {}",
+            defect.status.as_str(),
+            action.as_str()
+        ))));
+    }
+    Recheck::Retry
+}
+
+async fn run_with_retry(
+    client: &ApiClient,
+    id: &str,
+    action: DefectAction,
+    payload: &BulkDefectPayload,
+) -> BulkDefectItemResult {
+    let mut attempts = 0;
+    let result = loop {
+        attempts += 1;
+        let result = run_defect_action(client, id, action, payload).await;
+        if attempts > BULK_MAX_RETRIES || !is_transient(&result) {
+            break result;
+        }
+        tokio::time::sleep(BULK_RETRY_BASE_DELAY * 2u32.pow(attempts - 1)).await;
+        // 超时或网关 5xx 时请求可能已在服务端生效：重试前先确认缺陷当前状态
+        match recheck_before_retry(client, id, action, payload).await {
+            Recheck::Retry => continue,
+            Recheck::Done(done) => break *done,
+            Recheck::GiveUp => break result,
+        }
+    };
+
+    let (success, defect, error) = match result {
+        Ok(r) if r.success => (true, r.data.map(|d| d.defect), None),
+        Ok(r) => (
+            false,
+            None,
+            Some(
+                r.error
+                    .map(|e| e.message)
+                    .unwrap_or_else(|| "Unknown error".to_string()),
+            ),
+        ),
+        Err(e) => (false, None, Some(e)),
+    };
+    BulkDefectItemResult {
+        id: id.to_string(),
+        success,
+        skipped: false,
+        attempts,
+        defect,
+        error,
+    }
+}
+
+/// 批量执行流转动作（关闭 / 指派 / 处理…）
+/// - 有限并发 + 瞬时错误指数退避重试（重试前重新读取状态，已生效则不再重复提交）；单条失败不影响其余
+/// - 每完成一条发一次 defect-bulk-progress，前端据此画进度条
+/// - 返回逐条结果表；成功的缺陷会同步进本地缓存
+#[command]
+pub async fn bulk_defect_action(
+    app: AppHandle,
+    ids: Vec<String>,
+    action: DefectAction,
+    payload: Option<BulkDefectPayload>,
+) -> Result<BulkDefectActionResult, String> {
+    let payload = payload.unwrap_or_default();
+    if action.requires_text() && payload.text.as_deref().unwrap_or("").trim().is_empty() {
+        return Err(format!("{} 需要填写说明", action.as_str()));
+    }
+    if action.requires_assignee()
+        && payload
+            .assignee_id
+            .as_deref()
+            .unwrap_or("")
+            .trim()
+            .is_empty()
+    {
+        return Err("请选择处理人".to_string());
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    let ids: Vec<String> = ids
+        .into_iter()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty() && seen.insert(id.clone()))
+        .collect();
+    let total = ids.len();
+
+    let cache = app.try_state::<DefectCacheState>();
+    let client = ApiClient::new();
+    let mut results: HashMap<String, BulkDefectItemResult> = HashMap::new();
+    let (mut succeeded, mut failed, mut skipped) = (0, 0, 0);
+
+    let mut pending = futures::stream::iter(ids.iter().cloned())
+        .map(|id| {
+            let cached_status = cache
+                .as_ref()
+                .and_then(|c| c.defects.lock().unwrap().get(&id).map(|d| d.status.clone()));
+            let client = &client;
+            let payload = &payload;
+            async move {
+                match cached_status {
+                    Some(status) if !action.is_allowed_from(&status) => BulkDefectItemResult {
+                        error: Some(format!(
+                            "当前状态 {} 不支持 {}",
+                            status.as_str(),
+                            action.as_str()
+                        )),
+                        id,
+                        success: false,
+                        skipped: true,
+                        attempts: 0,
+                        defect: None,
+                    },
+                    _ => run_with_retry(client, &id, action, payload).await,
+                }
+            }
+        })
+        .buffer_unordered(BULK_CONCURRENCY);
+
+    while let Some(item) = pending.next().await {
+        if item.skipped {
+            skipped += 1;
+        } else if item.success {
+            succeeded += 1;
+        } else {
+            failed += 1;
+        }
+        if let (Some(cache), Some(defect)) = (cache.as_ref(), item.defect.as_ref()) {
+            cache.upsert_many(std::slice::from_ref(defect));
+        }
+        let _ = app.emit(
+            DEFECT_BULK_PROGRESS_EVENT,
+            serde_json::json!({
+                "action": action,
+                "id": item.id,
+                "success": item.success,
+                "completed": succeeded + failed + skipped,
+                "total": total,
+                "succeeded": succeeded,
+                "failed": failed,
+                "skipped": skipped,
+            }),
+        );
+        results.insert(item.id.clone(), item);
+    }
+
+    Ok(BulkDefectActionResult {
+        action,
+        total,
+        succeeded,
+        failed,
+        skipped,
+        items: ids.iter().filter_map(|id| results.remove(id)).collect(),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Realtime subscription
+// ---------------------------------------------------------------------------
+
+const DEFECT_UPDATED_EVENT: &str = "defect-updated";
+const DEFECT_MESSAGE_EVENT: &str = "defect-message";
+/// 服务端无 SSE 时的轮询间隔
+const DEFECT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MY_DEFECTS_POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// 单缺陷轮询时，每隔多少轮额外拉一次详情检测状态变化（新消息到达时也会立即拉）
+const DEFECT_DETAIL_EVERY_TICKS: u32 = 6;
+/// 缺陷流断开后的重连退避区间
+const STREAM_RECONNECT_MIN: Duration = Duration::from_secs(1);
+const STREAM_RECONNECT_MAX: Duration = Duration::from_secs(60);
+/// 连续多少次连不上后降级为轮询
+const STREAM_MAX_FAILURES: u32 = 5;
+
+fn emit_defect_updated(app: &AppHandle, defect: &Defect) {
+    let mut previous_assignee = None;
+    if let Some(cache) = app.try_state::<DefectCacheState>() {
+        previous_assignee = cache.assignee_of(&defect.id);
+        cache.upsert_many(std::slice::from_ref(defect));
+    }
+    notifier::on_defect_updated(app, previous_assignee, defect);
+    let _ = app.emit(
+        DEFECT_UPDATED_EVENT,
+        serde_json::json!({ "type": "updated", "defect": defect }),
+    );
+}
+
+fn emit_defect_message(app: &AppHandle, defect_id: &str, message: &DefectMessage) {
+    let _ = app.emit(
+        DEFECT_MESSAGE_EVENT,
+        serde_json::json!({ "type": "message", "defectId": defect_id, "message": message }),
+    );
+}
+
+/// 服务端推送的 data 帧：{ defect?, message? }，两者都有时各发一次
+fn dispatch_defect_frame(app: &AppHandle, value: &serde_json::Value, last_seq: &mut i64) {
+    if let Some(message) = value
+        .get("message")
+        .and_then(|m| serde_json::from_value::<DefectMessage>(m.clone()).ok())
+    {
+        *last_seq = (*last_seq).max(message.seq);
+        emit_defect_message(app, &message.defect_id, &message);
+    }
+    if let Some(defect) = value
+        .get("defect")
+        .and_then(|d| serde_json::from_value::<Defect>(d.clone()).ok())
+    {
+        emit_defect_updated(app, &defect);
+    }
+}
+
+/// 缺陷流的 sink：转发 defect / message 帧并记录已收到的最大 seq（重连时作为 afterSeq）
+struct DefectSink<'a> {
+    app: &'a AppHandle,
+    last_seq: i64,
+}
+
+impl StreamSink for DefectSink<'_> {
+    fn phase(&mut self, _phase: &str) {}
+
+    fn keepalive(&mut self) {}
+
+    fn event(&mut self, event: serde_json::Value) {
+        dispatch_defect_frame(self.app, &event, &mut self.last_seq);
+    }
+
+    // 流中断会自动重连，不打扰前端
+    fn error(&mut self, message: String) {
+        tracing::warn!("defect stream interrupted: {}", message);
+    }
+
+    fn auth_expired(&mut self) {
+        emit_auth_expired(self.app);
+    }
+
+    fn cancelled(&mut self) {}
+}
+
+/// 跟随缺陷 SSE 流：断开后按指数退避重连（带上已收到的 afterSeq）
+/// 返回 Some(last_seq) 表示应降级为轮询（服务端无该流，或连续多次连不上）；取消或登录失效时返回 None
+async fn follow_defect_stream(
+    app: &AppHandle,
+    url_for: impl Fn(i64) -> String,
+    token: &CancellationToken,
+    last_seq: i64,
+) -> Option<i64> {
+    let client = api_client::build_streaming_client(&api_client::get_api_base_url());
+    let mut sink = DefectSink { app, last_seq };
+    let mut backoff = STREAM_RECONNECT_MIN;
+    let mut failures = 0;
+
+    while !token.is_cancelled() {
+        let url = url_for(sink.last_seq);
+        let opened = open_event_stream(
+            || client.get(&url).header("Accept", "text/event-stream"),
+            &mut sink,
+        )
+        .await;
+        match opened {
+            Ok(response) if response.status().is_success() => {
+                failures = 0;
+                backoff = STREAM_RECONNECT_MIN;
+                pump_event_stream(response, token, &mut sink).await;
+            }
+            Ok(response)
+                if matches!(
+                    response.status(),
+                    StatusCode::NOT_FOUND
+                        | StatusCode::METHOD_NOT_ALLOWED
+                        | StatusCode::NOT_IMPLEMENTED
+                ) =>
+            {
+                return Some(sink.last_seq);
+            }
+            // refresh 失败时 open_event_stream 已通知登录失效
+            Ok(response) if response.status() == StatusCode::UNAUTHORIZED => return None,
+            Ok(response) => {
+                failures += 1;
+                sink.error(format!("HTTP {}", response.status()));
+            }
+            Err(e) => {
+                failures += 1;
+                sink.error(e);
+            }
+        }
+        if failures >= STREAM_MAX_FAILURES {
+            return Some(sink.last_seq);
+        }
+        if !sleep_or_cancel(token, backoff).await {
+            break;
+        }
+        backoff = (backoff * 2).min(STREAM_RECONNECT_MAX);
+    }
+    None
+}
+
+/// 可取消的等待；返回 false 表示已取消
+async fn sleep_or_cancel(token: &CancellationToken, interval: Duration) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(interval) => true,
+        _ = token.cancelled() => false,
+    }
+}
+
+/// 单缺陷轮询降级：afterSeq 增量拉消息，定期拉详情比对 updatedAt
+async fn poll_defect(app: &AppHandle, id: &str, token: &CancellationToken, mut last_seq: i64) {
+    let client = ApiClient::new();
+    let mut last_updated_at: Option<String> = None;
+    let mut tick: u32 = 0;
+
+    loop {
+        let mut got_messages = false;
+        if let Ok(resp) = client
+            .get::<DefectMessagesResponse>(&defect_messages_path(id, Some(last_seq)))
+            .await
+        {
+            for message in resp.data.map(|d| d.messages).unwrap_or_default() {
+                if message.seq <= last_seq {
+                    continue;
+                }
+                last_seq = message.seq;
+                got_messages = true;
+                emit_defect_message(app, id, &message);
+            }
+        }
+
+        if got_messages || tick.is_multiple_of(DEFECT_DETAIL_EVERY_TICKS) {
+            if let Ok(resp) = client
+                .get::<DefectDetailResponse>(&format!("/api/defect-agent/defects/{}", id))
+                .await
+            {
+                if let Some(data) = resp.data {
+                    let changed =
+                        last_updated_at.as_deref() != Some(data.defect.updated_at.as_str());
+                    // 第一次拉取只建立基线，不发事件
+                    if changed && last_updated_at.is_some() {
+                        emit_defect_updated(app, &data.defect);
+                    }
+                    last_updated_at = Some(data.defect.updated_at);
+                }
+            }
+        }
+
+        tick = tick.wrapping_add(1);
+        if !sleep_or_cancel(token, DEFECT_POLL_INTERVAL).await {
+            break;
+        }
+    }
+}
+
+/// “我的缺陷”轮询降级：按第一页（最新 500 条）比对 updatedAt
+async fn poll_my_defects(app: &AppHandle, token: &CancellationToken) {
+    let client = ApiClient::new();
+    let filter = DefectListFilter::default();
+    let mut known: HashMap<String, String> = HashMap::new();
+    let mut baseline_ready = false;
+
+    loop {
+        if let Ok(resp) = fetch_defect_page(&client, &filter, 0, MAX_PAGE_SIZE).await {
+            if let Some(page) = resp.data {
+                for defect in page.items {
+                    if known.get(&defect.id) == Some(&defect.updated_at) {
+                        continue;
+                    }
+                    known.insert(defect.id.clone(), defect.updated_at.clone());
+                    if baseline_ready {
+                        emit_defect_updated(app, &defect);
+                    }
+                }
+                baseline_ready = true;
+            }
+        }
+
+        if !sleep_or_cancel(token, MY_DEFECTS_POLL_INTERVAL).await {
+            break;
+        }
+    }
+}
+
+/// 订阅单个缺陷的实时变化
+/// - 事件：defect-message（新消息）/ defect-updated（状态等字段变化）
+/// - 流断开后按 afterSeq 退避重连；服务端无 SSE 端点或连续连不上时降级为 afterSeq 轮询
+/// - 取消：cancel_stream(kind = "defect")；重复订阅会自动取消上一个
+#[command]
+pub async fn subscribe_defect(
+    app: AppHandle,
+    cancel: State<'_, StreamCancelState>,
+    id: String,
+    after_seq: Option<i64>,
+) -> Result<(), String> {
+    let did = id.trim().to_string();
+    if did.is_empty() {
+        return Ok(());
+    }
+
+    let last_seq = after_seq.unwrap_or(0).max(0);
+    let base_url = api_client::get_api_base_url();
+    let token = cancel.new_defect_token();
+
+    tauri::async_runtime::spawn(async move {
+        let url_for = |seq: i64| {
+            format!(
+                "{}/api/defect-agent/defects/{}/stream?afterSeq={}",
+                base_url, did, seq
+            )
+        };
+        if let Some(last_seq) = follow_defect_stream(&app, url_for, &token, last_seq).await {
+            poll_defect(&app, &did, &token, last_seq).await;
+        }
+    });
+
+    Ok(())
+}
+
+/// 订阅“与我相关”的缺陷变化（我提交的 + 派给我的）
+/// - 事件：defect-updated / defect-message
+/// - 流断开后退避重连；服务端无 SSE 端点或连续连不上时降级为列表轮询
+/// - 取消：cancel_stream(kind = "my-defects")
+#[command]
+pub async fn subscribe_my_defects(
+    app: AppHandle,
+    cancel: State<'_, StreamCancelState>,
+) -> Result<(), String> {
+    let url = format!(
+        "{}/api/defect-agent/defects/stream",
+        api_client::get_api_base_url()
+    );
+    let token = cancel.new_my_defects_token();
+
+    tauri::async_runtime::spawn(async move {
+        if follow_defect_stream(&app, |_| url.clone(), &token, 0)
+            .await
+            .is_some()
+        {
+            poll_my_defects(&app, &token).await;
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,521 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, Manager};
+use uuid::Uuid;
+
+use super::defect::{create_defect, delete_defect, submit_defect};
+use crate::models::{AddDefectAttachmentResponse, ApiResponse, Defect};
+use crate::services::ApiClient;
+
+/// 本地缺陷草稿：create → 附件 → submit 多步流程中途崩溃也不会丢内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectDraft {
+    pub id: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub severity: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub assignee_user_id: Option<String>,
+    #[serde(default)]
+    pub template_id: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<DefectDraftAttachment>,
+    /// 已在服务端创建但尚未提交成功的缺陷；再次提交时复用，避免重复创建
+    /// 草稿内容修改后失效（见 `apply_input`）
+    #[serde(default)]
+    pub defect_id: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    pub created_at_ms: i64,
+    pub updated_at_ms: i64,
+}
+
+/// 待上传附件（文件内容落盘在草稿目录，不放进 JSON）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectDraftAttachment {
+    pub id: String,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: u64,
+    /// 已上传到 defect_id 对应的缺陷
+    #[serde(default)]
+    pub uploaded: bool,
+}
+
+/// 自动保存入参：id 为空表示新建草稿
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectDraftInput {
+    pub id: Option<String>,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub severity: String,
+    pub title: Option<String>,
+    pub assignee_user_id: Option<String>,
+    pub template_id: Option<String>,
+}
+
+/// submit_defect_draft 结果
+/// - success=true：缺陷已提交，本地草稿已删除
+/// - success=false：stage 指出失败步骤；rolledBack 表示服务端半成品已删除，草稿保持原样可重试
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitDefectDraftResult {
+    pub success: bool,
+    pub defect: Option<Defect>,
+    pub stage: Option<String>,
+    pub error: Option<String>,
+    pub rolled_back: bool,
+    pub failed_attachment_ids: Vec<String>,
+    pub draft: Option<DefectDraft>,
+}
+
+fn now_ms() -> i64 {
+    let dur = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_else(|_| std::time::Duration::from_millis(0));
+    dur.as_millis() as i64
+}
+
+fn get_drafts_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?
+        .join("defect_drafts");
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create drafts dir: {}", e))?;
+    }
+
+    Ok(dir)
+}
+
+/// 草稿 id 会拼进路径，只接受 uuid，防止 ../ 之类的路径穿越
+fn validate_id(id: &str) -> Result<String, String> {
+    Uuid::parse_str(id.trim())
+        .map(|u| u.to_string())
+        .map_err(|_| format!("Invalid draft id: {}", id))
+}
+
+fn draft_json_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+fn draft_files_dir(dir: &Path, id: &str) -> PathBuf {
+    dir.join(id)
+}
+
+fn load_draft(dir: &Path, id: &str) -> Result<DefectDraft, String> {
+    let id = validate_id(id)?;
+    let path = draft_json_path(dir, &id);
+    if !path.exists() {
+        return Err(format!("Draft not found: {}", id));
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read draft file: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse draft file: {}", e))
+}
+
+/// 先写临时文件再 rename：autosave 写到一半崩溃不会留下半截 JSON
+fn save_draft(dir: &Path, draft: &DefectDraft) -> Result<(), String> {
+    let path = draft_json_path(dir, &draft.id);
+    let tmp = dir.join(format!("{}.json.tmp", draft.id));
+    let content = serde_json::to_string_pretty(draft)
+        .map_err(|e| format!("Failed to serialize draft: {}", e))?;
+    fs::write(&tmp, content).map_err(|e| format!("Failed to write draft file: {}", e))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("Failed to write draft file: {}", e))
+}
+
+fn remove_draft(dir: &Path, id: &str) -> Result<(), String> {
+    let _ = fs::remove_file(draft_json_path(dir, id));
+    let files = draft_files_dir(dir, id);
+    if files.exists() {
+        let _ = fs::remove_dir_all(files);
+    }
+    Ok(())
+}
+
+fn api_error_message<T>(resp: &ApiResponse<T>) -> String {
+    resp.error
+        .as_ref()
+        .map(|e| e.message.clone())
+        .unwrap_or_else(|| "Unknown error".to_string())
+}
+
+impl DefectDraft {
+    /// 写入编辑内容；创建缺陷时提交的字段有变化时，返回已失效的服务端缺陷 id
+    /// （其内容已与草稿不一致，下次提交需重新创建）
+    fn apply_input(&mut self, input: DefectDraftInput) -> Option<String> {
+        let changed = self.content != input.content
+            || self.severity != input.severity
+            || self.title != input.title
+            || self.assignee_user_id != input.assignee_user_id
+            || self.template_id != input.template_id;
+        self.content = input.content;
+        self.severity = input.severity;
+        self.title = input.title;
+        self.assignee_user_id = input.assignee_user_id;
+        self.template_id = input.template_id;
+        if !changed {
+            return None;
+        }
+        for a in self.attachments.iter_mut() {
+            a.uploaded = false;
+        }
+        self.defect_id.take()
+    }
+}
+
+/// 保存草稿（save_defect_draft 与测试共用）；内容变化使残留的服务端缺陷失效时尽力删除它
+pub(crate) async fn save_draft_input(
+    dir: &Path,
+    input: DefectDraftInput,
+) -> Result<DefectDraft, String> {
+    let now = now_ms();
+    let mut saved = match input.id.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(id) => load_draft(dir, id)?,
+        None => DefectDraft {
+            id: Uuid::new_v4().to_string(),
+            content: String::new(),
+            severity: String::new(),
+            title: None,
+            assignee_user_id: None,
+            template_id: None,
+            attachments: Vec::new(),
+            defect_id: None,
+            last_error: None,
+            created_at_ms: now,
+            updated_at_ms: now,
+        },
+    };
+
+    let stale = saved.apply_input(input);
+    saved.updated_at_ms = now;
+    save_draft(dir, &saved)?;
+    if let Some(stale) = stale {
+        let _ = delete_defect(stale).await;
+    }
+    Ok(saved)
+}
+
+/// 自动保存草稿（前端输入防抖后调用）
+#[command]
+pub async fn save_defect_draft(
+    app: AppHandle,
+    draft: DefectDraftInput,
+) -> Result<DefectDraft, String> {
+    save_draft_input(&get_drafts_dir(&app)?, draft).await
+}
+
+/// 列出本地草稿（最近编辑的在前）
+#[command]
+pub async fn list_defect_drafts(app: AppHandle) -> Result<Vec<DefectDraft>, String> {
+    let dir = get_drafts_dir(&app)?;
+    let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read drafts dir: {}", e))?;
+
+    let mut drafts: Vec<DefectDraft> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|x| x.to_str()) == Some("json"))
+        .filter_map(|p| fs::read_to_string(p).ok())
+        // 容错：单个草稿损坏不影响其他草稿
+        .filter_map(|s| serde_json::from_str::<DefectDraft>(&s).ok())
+        .collect();
+
+    drafts.sort_by_key(|d| std::cmp::Reverse(d.updated_at_ms));
+    Ok(drafts)
+}
+
+/// 恢复草稿（重新打开编辑）
+#[command]
+pub async fn resume_defect_draft(app: AppHandle, id: String) -> Result<DefectDraft, String> {
+    load_draft(&get_drafts_dir(&app)?, &id)
+}
+
+/// 删除草稿及其待上传附件
+#[command]
+pub async fn delete_defect_draft(app: AppHandle, id: String) -> Result<(), String> {
+    let id = validate_id(&id)?;
+    remove_draft(&get_drafts_dir(&app)?, &id)
+}
+
+/// 向草稿添加待上传附件（base64，与 add_defect_attachment 入参一致）
+#[command]
+pub async fn add_defect_draft_attachment(
+    app: AppHandle,
+    draft_id: String,
+    file_base64: String,
+    file_name: String,
+    mime_type: String,
+) -> Result<DefectDraft, String> {
+    use base64::Engine;
+    let file_bytes = base64::engine::general_purpose::STANDARD
+        .decode(&file_base64)
+        .map_err(|e| format!("Failed to decode file: {}", e))?;
+
+    let dir = get_drafts_dir(&app)?;
+    let mut draft = load_draft(&dir, &draft_id)?;
+    let files_dir = draft_files_dir(&dir, &draft.id);
+    fs::create_dir_all(&files_dir)
+        .map_err(|e| format!("Failed to create draft attachments dir: {}", e))?;
+
+    let attachment = DefectDraftAttachment {
+        id: Uuid::new_v4().to_string(),
+        file_name,
+        mime_type,
+        size: file_bytes.len() as u64,
+        uploaded: false,
+    };
+    fs::write(files_dir.join(&attachment.id), &file_bytes)
+        .map_err(|e| format!("Failed to write draft attachment: {}", e))?;
+
+    draft.attachments.push(attachment);
+    draft.updated_at_ms = now_ms();
+    save_draft(&dir, &draft)?;
+    Ok(draft)
+}
+
+/// 从草稿移除待上传附件
+#[command]
+pub async fn remove_defect_draft_attachment(
+    app: AppHandle,
+    draft_id: String,
+    attachment_id: String,
+) -> Result<DefectDraft, String> {
+    let dir = get_drafts_dir(&app)?;
+    let mut draft = load_draft(&dir, &draft_id)?;
+    let files_dir = draft_files_dir(&dir, &draft.id);
+
+    draft.attachments.retain(|a| {
+        if a.id == attachment_id {
+            let _ = fs::remove_file(files_dir.join(&a.id));
+            false
+        } else {
+            true
+        }
+    });
+    draft.updated_at_ms = now_ms();
+    save_draft(&dir, &draft)?;
+    Ok(draft)
+}
+
+/// 提交草稿：create → 逐个上传附件 → submit
+/// - 任一步失败：删除本次创建的服务端缺陷（回滚），草稿原样保留
+/// - 回滚也失败：把 defectId 与已上传标记写回草稿，下次提交从断点继续而不是重复创建
+#[command]
+pub async fn submit_defect_draft(
+    app: AppHandle,
+    id: String,
+    attach_client_logs: Option<bool>,
+) -> Result<SubmitDefectDraftResult, String> {
+    submit_draft(&get_drafts_dir(&app)?, &id, attach_client_logs).await
+}
+
+/// submit_defect_draft 的实现（与测试共用）
+pub(crate) async fn submit_draft(
+    dir: &Path,
+    id: &str,
+    attach_client_logs: Option<bool>,
+) -> Result<SubmitDefectDraftResult, String> {
+    let mut draft = load_draft(dir, id)?;
+
+    let assignee = draft
+        .assignee_user_id
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| "请选择处理人".to_string())?;
+    if draft.content.trim().is_empty() {
+        return Err("缺陷描述不能为空".to_string());
+    }
+    if draft.severity.trim().is_empty() {
+        return Err("请选择严重程度".to_string());
+    }
+
+    // 1) create（或复用上次残留的缺陷）
+    let defect_id = match draft.defect_id.clone() {
+        Some(existing) => existing,
+        None => {
+            let resp = create_defect(
+                draft.content.clone(),
+                draft.severity.clone(),
+                draft.title.clone(),
+                assignee,
+                draft.template_id.clone(),
+            )
+            .await;
+            match resp {
+                Ok(ApiResponse {
+                    success: true,
+                    data: Some(data),
+                    ..
+                }) => {
+                    // 立即落盘：后续步骤中途崩溃时，下次提交复用该缺陷而不是重复创建
+                    draft.defect_id = Some(data.defect.id.clone());
+                    save_draft(dir, &draft)?;
+                    data.defect.id
+                }
+                Ok(r) => {
+                    let error = api_error_message(&r);
+                    return finish_failed(dir, draft, "create", error, false, Vec::new());
+                }
+                Err(e) => return finish_failed(dir, draft, "create", e, false, Vec::new()),
+            }
+        }
+    };
+
+    // 2) attachments
+    let files_dir = draft_files_dir(dir, &draft.id);
+    let client = ApiClient::new();
+    let mut uploaded_now: Vec<String> = Vec::new();
+    let mut failed_ids: Vec<String> = Vec::new();
+    let mut first_error: Option<String> = None;
+
+    for attachment in draft.attachments.iter().filter(|a| !a.uploaded) {
+        let result = match fs::read(files_dir.join(&attachment.id)) {
+            Ok(bytes) => client
+                .post_file::<AddDefectAttachmentResponse>(
+                    &format!("/api/defect-agent/defects/{}/attachments", defect_id),
+                    bytes,
+                    attachment.file_name.clone(),
+                    attachment.mime_type.clone(),
+                )
+                .await
+                .and_then(|r| {
+                    if r.success {
+                        Ok(())
+                    } else {
+                        Err(api_error_message(&r))
+                    }
+                }),
+            Err(e) => Err(format!("Failed to read draft attachment: {}", e)),
+        };
+        match result {
+            Ok(()) => uploaded_now.push(attachment.id.clone()),
+            Err(e) => {
+                failed_ids.push(attachment.id.clone());
+                first_error.get_or_insert(format!("{}: {}", attachment.file_name, e));
+            }
+        }
+    }
+
+    // 3) submit（附件全部成功才提交）
+    let (stage, error) = match first_error {
+        Some(e) => ("attachments", e),
+        None => match submit_defect(defect_id.clone(), attach_client_logs).await {
+            Ok(r) if r.success => {
+                remove_draft(dir, &draft.id)?;
+                return Ok(SubmitDefectDraftResult {
+                    success: true,
+                    defect: r.data.map(|d| d.defect),
+                    stage: None,
+                    error: None,
+                    rolled_back: false,
+                    failed_attachment_ids: Vec::new(),
+                    draft: None,
+                });
+            }
+            Ok(r) => ("submit", api_error_message(&r)),
+            Err(e) => ("submit", e),
+        },
+    };
+
+    // 回滚：删除服务端半成品，草稿回到“从未提交”的状态
+    let rolled_back = matches!(delete_defect(defect_id.clone()).await, Ok(r) if r.success);
+    if rolled_back {
+        draft.defect_id = None;
+        for a in draft.attachments.iter_mut() {
+            a.uploaded = false;
+        }
+    } else {
+        draft.defect_id = Some(defect_id);
+        for a in draft.attachments.iter_mut() {
+            if uploaded_now.contains(&a.id) {
+                a.uploaded = true;
+            }
+        }
+    }
+    finish_failed(dir, draft, stage, error, rolled_back, failed_ids)
+}
+
+fn finish_failed(
+    dir: &Path,
+    mut draft: DefectDraft,
+    stage: &str,
+    error: String,
+    rolled_back: bool,
+    failed_attachment_ids: Vec<String>,
+) -> Result<SubmitDefectDraftResult, String> {
+    draft.last_error = Some(format!("[{}] {}", stage, error));
+    draft.updated_at_ms = now_ms();
+    save_draft(dir, &draft)?;
+    Ok(SubmitDefectDraftResult {
+        success: false,
+        defect: None,
+        stage: Some(stage.to_string()),
+        error: Some(error),
+        rolled_back,
+        failed_attachment_ids,
+        draft: Some(draft),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(content: &str) -> DefectDraftInput {
+        DefectDraftInput {
+            id: None,
+            content: content.to_string(),
+            severity: "major".to_string(),
+            title: Some("登录失败".to_string()),
+            assignee_user_id: Some("u-1".to_string()),
+            template_id: None,
+        }
+    }
+
+    #[test]
+    fn editing_submitted_fields_invalidates_server_defect() {
+        let mut draft = DefectDraft {
+            id: Uuid::new_v4().to_string(),
+            content: String::new(),
+            severity: String::new(),
+            title: None,
+            assignee_user_id: None,
+            template_id: None,
+            attachments: vec![DefectDraftAttachment {
+                id: "a-1".to_string(),
+                file_name: "shot.png".to_string(),
+                mime_type: "image/png".to_string(),
+                size: 1,
+                uploaded: false,
+            }],
+            defect_id: None,
+            last_error: None,
+            created_at_ms: 0,
+            updated_at_ms: 0,
+        };
+        assert_eq!(draft.apply_input(input("点击无响应")), None);
+
+        draft.defect_id = Some("defect-1".to_string());
+        draft.attachments[0].uploaded = true;
+        assert_eq!(draft.apply_input(input("点击无响应")), None);
+        assert_eq!(draft.defect_id.as_deref(), Some("defect-1"));
+        assert!(draft.attachments[0].uploaded);
+
+        assert_eq!(
+            draft.apply_input(input("点击后白屏")),
+            Some("defect-1".to_string())
+        );
+        assert_eq!(draft.defect_id, None);
+        assert!(!draft.attachments[0].uploaded);
+    }
+}
@@ -0,0 +1,528 @@
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use tauri::{command, AppHandle};
+use tauri_plugin_dialog::DialogExt;
+
+use super::defect::{fetch_all_defects, get_defect_stats};
+use crate::models::{Defect, DefectListFilter, DefectMessage, DefectMessagesResponse, DefectStats};
+use crate::services::ApiClient;
+
+/// 拉取讨论消息的并发上限
+const MESSAGE_FETCH_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefectExportFormat {
+    Csv,
+    Xlsx,
+    Markdown,
+}
+
+impl DefectExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+            Self::Markdown => "md",
+        }
+    }
+
+    fn filter_name(&self) -> &'static str {
+        match self {
+            Self::Csv => "CSV",
+            Self::Xlsx => "Excel",
+            Self::Markdown => "Markdown",
+        }
+    }
+}
+
+/// 可导出的列（key 即前端传入的 columns 取值）
+const COLUMNS: &[(&str, &str)] = &[
+    ("defectNo", "编号"),
+    ("title", "标题"),
+    ("status", "状态"),
+    ("severity", "严重程度"),
+    ("priority", "优先级"),
+    ("reporter", "提交人"),
+    ("assignee", "处理人"),
+    ("createdAt", "创建时间"),
+    ("submittedAt", "提交时间"),
+    ("resolvedAt", "解决时间"),
+    ("verifiedAt", "验收时间"),
+    ("closedAt", "关闭时间"),
+    ("resolveHours", "解决耗时(h)"),
+    ("verifyHours", "验收耗时(h)"),
+    ("totalHours", "总耗时(h)"),
+    ("resolution", "解决说明"),
+    ("rejectReason", "驳回原因"),
+    ("messageCount", "讨论数"),
+    ("lastMessageAt", "最后讨论时间"),
+];
+
+const DEFAULT_COLUMNS: &[&str] = &[
+    "defectNo",
+    "title",
+    "status",
+    "severity",
+    "reporter",
+    "assignee",
+    "createdAt",
+    "resolvedAt",
+    "verifiedAt",
+    "resolveHours",
+    "verifyHours",
+];
+
+/// 需要逐个拉取讨论消息的列
+const MESSAGE_COLUMNS: &[&str] = &["messageCount", "lastMessageAt"];
+
+enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+impl Cell {
+    fn text(value: Option<&str>) -> Self {
+        match value.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(s) => Self::Text(s.to_string()),
+            None => Self::Empty,
+        }
+    }
+
+    fn display(&self) -> String {
+        match self {
+            Self::Text(s) => s.clone(),
+            Self::Number(n) => format!("{:.1}", n),
+            Self::Empty => String::new(),
+        }
+    }
+}
+
+/// 后端时间既有带 Z 的 RFC3339，也有不带时区的 UTC 字符串
+fn parse_ts(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|d| d.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|n| n.and_utc())
+        })
+}
+
+fn hours_between(from: Option<&str>, to: Option<&str>) -> Option<f64> {
+    let from = parse_ts(from?)?;
+    let to = parse_ts(to?)?;
+    let minutes = (to - from).num_minutes();
+    (minutes >= 0).then(|| minutes as f64 / 60.0)
+}
+
+/// 导出用的本地时间展示
+fn format_ts(value: Option<&str>) -> Option<String> {
+    parse_ts(value?).map(|d| d.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+}
+
+/// created → resolved
+fn resolve_hours(d: &Defect) -> Option<f64> {
+    hours_between(Some(&d.created_at), d.resolved_at.as_deref())
+}
+
+/// resolved → verified
+fn verify_hours(d: &Defect) -> Option<f64> {
+    hours_between(d.resolved_at.as_deref(), d.verified_at.as_deref())
+}
+
+/// created → verified / closed（取先到的终点）
+fn total_hours(d: &Defect) -> Option<f64> {
+    let end = d.verified_at.as_deref().or(d.closed_at.as_deref());
+    hours_between(Some(&d.created_at), end)
+}
+
+struct ExportRow<'a> {
+    defect: &'a Defect,
+    messages: Option<&'a Vec<DefectMessage>>,
+}
+
+impl ExportRow<'_> {
+    fn cell(&self, key: &str) -> Cell {
+        let d = self.defect;
+        let hours = |h: Option<f64>| h.map(Cell::Number).unwrap_or(Cell::Empty);
+        let ts = |v: Option<&str>| Cell::text(format_ts(v).as_deref());
+        match key {
+            "defectNo" => Cell::text(Some(&d.defect_no)),
+            "title" => Cell::text(d.title.as_deref().or(Some(&d.raw_content))),
+            "status" => Cell::text(Some(d.status.as_str())),
+            "severity" => Cell::text(d.severity.as_ref().map(|s| s.as_str())),
+            "priority" => Cell::text(d.priority.as_deref()),
+            "reporter" => Cell::text(d.reporter_name.as_deref().or(Some(&d.reporter_id))),
+            "assignee" => Cell::text(d.assignee_name.as_deref().or(d.assignee_id.as_deref())),
+            "createdAt" => ts(Some(&d.created_at)),
+            "submittedAt" => ts(d.submitted_at.as_deref()),
+            "resolvedAt" => ts(d.resolved_at.as_deref()),
+            "verifiedAt" => ts(d.verified_at.as_deref()),
+            "closedAt" => ts(d.closed_at.as_deref()),
+            "resolveHours" => hours(resolve_hours(d)),
+            "verifyHours" => hours(verify_hours(d)),
+            "totalHours" => hours(total_hours(d)),
+            "resolution" => Cell::text(d.resolution.as_deref()),
+            "rejectReason" => Cell::text(d.reject_reason.as_deref()),
+            "messageCount" => self
+                .messages
+                .map(|m| Cell::Number(m.len() as f64))
+                .unwrap_or(Cell::Empty),
+            "lastMessageAt" => ts(self
+                .messages
+                .and_then(|m| m.iter().max_by_key(|x| x.seq))
+                .map(|m| m.created_at.as_str())),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+fn column_title(key: &str) -> &str {
+    COLUMNS
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, title)| *title)
+        .unwrap_or(key)
+}
+
+fn build_csv(columns: &[&str], rows: &[ExportRow]) -> Result<Vec<u8>, String> {
+    // UTF-8 BOM：否则 Excel 直接打开中文会乱码
+    let mut writer = csv::Writer::from_writer(b"\xEF\xBB\xBF".to_vec());
+    writer
+        .write_record(columns.iter().map(|k| column_title(k)))
+        .map_err(|e| format!("Failed to write CSV: {}", e))?;
+    for row in rows {
+        writer
+            .write_record(columns.iter().map(|k| row.cell(k).display()))
+            .map_err(|e| format!("Failed to write CSV: {}", e))?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| format!("Failed to write CSV: {}", e))
+}
+
+fn build_xlsx(
+    columns: &[&str],
+    rows: &[ExportRow],
+    stats: Option<&DefectStats>,
+) -> Result<Vec<u8>, String> {
+    use rust_xlsxwriter::{Format, Workbook};
+
+    let xlsx_err = |e: rust_xlsxwriter::XlsxError| format!("Failed to write XLSX: {}", e);
+    let header = Format::new().set_bold();
+    let mut workbook = Workbook::new();
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("缺陷").map_err(xlsx_err)?;
+    for (col, key) in columns.iter().enumerate() {
+        sheet
+            .write_string_with_format(0, col as u16, column_title(key), &header)
+            .map_err(xlsx_err)?;
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let r = i as u32 + 1;
+        for (col, key) in columns.iter().enumerate() {
+            match row.cell(key) {
+                Cell::Text(s) => sheet.write_string(r, col as u16, s),
+                Cell::Number(n) => sheet.write_number(r, col as u16, (n * 10.0).round() / 10.0),
+                Cell::Empty => continue,
+            }
+            .map_err(xlsx_err)?;
+        }
+    }
+    sheet.autofit();
+
+    let summary = workbook.add_worksheet();
+    summary.set_name("统计").map_err(xlsx_err)?;
+    let mut r = 0u32;
+    let mut write_counts = |title: &str, counts: &BTreeMap<String, i64>| {
+        summary.write_string_with_format(r, 0, title, &header)?;
+        r += 1;
+        for (k, v) in counts {
+            summary.write_string(r, 0, k)?;
+            summary.write_number(r, 1, *v as f64)?;
+            r += 1;
+        }
+        r += 1;
+        Ok::<(), rust_xlsxwriter::XlsxError>(())
+    };
+    if let Some(stats) = stats {
+        write_counts("服务端状态统计", &to_sorted(&stats.status_counts)).map_err(xlsx_err)?;
+        write_counts("服务端严重程度统计", &to_sorted(&stats.severity_counts)).map_err(xlsx_err)?;
+    }
+    write_counts("本次导出（按严重程度）", &count_by(rows, severity_key)).map_err(xlsx_err)?;
+    write_counts("本次导出（按处理人）", &count_by(rows, assignee_key)).map_err(xlsx_err)?;
+    summary.autofit();
+
+    workbook.save_to_buffer().map_err(xlsx_err)
+}
+
+fn to_sorted(map: &HashMap<String, i64>) -> BTreeMap<String, i64> {
+    map.iter().map(|(k, v)| (k.clone(), *v)).collect()
+}
+
+fn severity_key(d: &Defect) -> String {
+    d.severity
+        .as_ref()
+        .map(|s| s.as_str().to_string())
+        .unwrap_or_else(|| "未设置".to_string())
+}
+
+fn assignee_key(d: &Defect) -> String {
+    d.assignee_name
+        .clone()
+        .or_else(|| d.assignee_id.clone())
+        .unwrap_or_else(|| "未指派".to_string())
+}
+
+fn count_by(rows: &[ExportRow], key: fn(&Defect) -> String) -> BTreeMap<String, i64> {
+    let mut counts = BTreeMap::new();
+    for row in rows {
+        *counts.entry(key(row.defect)).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, n) = values.fold((0.0, 0usize), |(s, n), v| (s + v, n + 1));
+    (n > 0).then(|| sum / n as f64)
+}
+
+fn md_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn fmt_hours(h: Option<f64>) -> String {
+    h.map(|v| format!("{:.1}", v))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn build_markdown(columns: &[&str], rows: &[ExportRow], stats: Option<&DefectStats>) -> String {
+    let mut out = String::new();
+    out.push_str("# 缺陷报告\n\n");
+    out.push_str(&format!(
+        "导出时间：{}　本次导出：{} 条\n\n",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        rows.len()
+    ));
+
+    if let Some(stats) = stats {
+        out.push_str("## 服务端统计\n\n");
+        out.push_str(&format!("- 缺陷总数：{}\n", stats.total));
+        for (title, counts) in [
+            ("状态", &stats.status_counts),
+            ("严重程度", &stats.severity_counts),
+        ] {
+            let parts: Vec<String> = to_sorted(counts)
+                .into_iter()
+                .map(|(k, v)| format!("{} {}", k, v))
+                .collect();
+            out.push_str(&format!("- {}：{}\n", title, parts.join("，")));
+        }
+        out.push('\n');
+    }
+
+    // 按处理人：数量与平均耗时
+    out.push_str("## 按处理人\n\n");
+    out.push_str("| 处理人 | 数量 | 已解决 | 平均解决耗时(h) | 平均验收耗时(h) |\n");
+    out.push_str("| --- | ---: | ---: | ---: | ---: |\n");
+    let mut by_assignee: BTreeMap<String, Vec<&Defect>> = BTreeMap::new();
+    for row in rows {
+        by_assignee
+            .entry(assignee_key(row.defect))
+            .or_default()
+            .push(row.defect);
+    }
+    for (assignee, defects) in &by_assignee {
+        let resolved = defects.iter().filter(|d| d.resolved_at.is_some()).count();
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            md_cell(assignee),
+            defects.len(),
+            resolved,
+            fmt_hours(average(defects.iter().filter_map(|d| resolve_hours(d)))),
+            fmt_hours(average(defects.iter().filter_map(|d| verify_hours(d)))),
+        ));
+    }
+    out.push('\n');
+
+    // 按严重程度分组列出明细（严重的在前）
+    out.push_str("## 按严重程度\n");
+    let mut by_severity: Vec<(u8, String, Vec<&ExportRow>)> = Vec::new();
+    for row in rows {
+        let key = severity_key(row.defect);
+        match by_severity.iter_mut().find(|(_, k, _)| *k == key) {
+            Some((_, _, list)) => list.push(row),
+            None => {
+                let rank = row
+                    .defect
+                    .severity
+                    .as_ref()
+                    .map(|s| s.rank())
+                    .unwrap_or(u8::MAX);
+                by_severity.push((rank, key, vec![row]));
+            }
+        }
+    }
+    by_severity.sort_by_key(|(rank, _, _)| *rank);
+
+    for (_, severity, group) in by_severity {
+        out.push_str(&format!("\n### {}（{}）\n\n", severity, group.len()));
+        let titles: Vec<&str> = columns.iter().map(|k| column_title(k)).collect();
+        out.push_str(&format!("| {} |\n", titles.join(" | ")));
+        out.push_str(&format!("|{}\n", " --- |".repeat(columns.len())));
+        for row in group {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|k| md_cell(&row.cell(k).display()))
+                .collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+    }
+
+    out
+}
+
+async fn fetch_messages(
+    client: &ApiClient,
+    defects: &[Defect],
+) -> HashMap<String, Vec<DefectMessage>> {
+    let ids: Vec<String> = defects.iter().map(|d| d.id.clone()).collect();
+    futures::stream::iter(ids)
+        .map(|id| async move {
+            let resp = client
+                .get::<DefectMessagesResponse>(&format!(
+                    "/api/defect-agent/defects/{}/messages",
+                    id
+                ))
+                .await;
+            let messages = resp
+                .ok()
+                .and_then(|r| r.data)
+                .map(|d| d.messages)
+                .unwrap_or_default();
+            (id, messages)
+        })
+        .buffer_unordered(MESSAGE_FETCH_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// 导出缺陷报告（CSV / XLSX / Markdown），通过系统保存对话框选择路径
+/// - columns 为空时使用默认列；未知列忽略
+/// - 返回保存路径；用户取消时返回 None
+#[command]
+pub async fn export_defects(
+    app: AppHandle,
+    filter: Option<DefectListFilter>,
+    format: DefectExportFormat,
+    columns: Option<Vec<String>>,
+) -> Result<Option<String>, String> {
+    let requested: Vec<String> = columns.unwrap_or_default();
+    let columns: Vec<&str> = if requested.is_empty() {
+        DEFAULT_COLUMNS.to_vec()
+    } else {
+        COLUMNS
+            .iter()
+            .map(|(k, _)| *k)
+            .filter(|k| requested.iter().any(|r| r == k))
+            .collect()
+    };
+    if columns.is_empty() {
+        return Err("没有可导出的列".to_string());
+    }
+
+    let client = ApiClient::new();
+    let filter = filter.unwrap_or_default();
+    let resp = fetch_all_defects(&client, &filter).await?;
+    if !resp.success {
+        return Err(resp
+            .error
+            .map(|e| e.message)
+            .unwrap_or_else(|| "获取缺陷列表失败".to_string()));
+    }
+    let defects = resp.data.map(|d| d.items).unwrap_or_default();
+
+    let messages = if columns.iter().any(|c| MESSAGE_COLUMNS.contains(c)) {
+        fetch_messages(&client, &defects).await
+    } else {
+        HashMap::new()
+    };
+    let stats = get_defect_stats().await.ok().and_then(|r| r.data);
+
+    let rows: Vec<ExportRow> = defects
+        .iter()
+        .map(|defect| ExportRow {
+            defect,
+            messages: messages.get(&defect.id),
+        })
+        .collect();
+
+    let bytes = match format {
+        DefectExportFormat::Csv => build_csv(&columns, &rows)?,
+        DefectExportFormat::Xlsx => build_xlsx(&columns, &rows, stats.as_ref())?,
+        DefectExportFormat::Markdown => {
+            build_markdown(&columns, &rows, stats.as_ref()).into_bytes()
+        }
+    };
+
+    let default_name = format!(
+        "defects-{}.{}",
+        Local::now().format("%Y%m%d"),
+        format.extension()
+    );
+    save_with_dialog(
+        &app,
+        &default_name,
+        format.filter_name(),
+        format.extension(),
+        &bytes,
+    )
+}
+
+/// 弹出系统保存对话框并写入文件（各导出命令共用）；用户取消时返回 None
+pub(crate) fn save_with_dialog(
+    app: &AppHandle,
+    default_name: &str,
+    filter_name: &str,
+    extension: &str,
+    bytes: &[u8],
+) -> Result<Option<String>, String> {
+    use std::sync::mpsc;
+    use tauri_plugin_dialog::FilePath;
+
+    let (tx, rx) = mpsc::channel();
+
+    app.dialog()
+        .file()
+        .set_file_name(default_name)
+        .add_filter(filter_name, &[extension])
+        .save_file(move |path| {
+            tx.send(path).ok();
+        });
+
+    let path = rx.recv().map_err(|e| format!("Dialog error: {}", e))?;
+
+    match path {
+        Some(file_path) => {
+            let path_buf = match file_path {
+                FilePath::Path(p) => p,
+                FilePath::Url(u) => u
+                    .to_file_path()
+                    .map_err(|_| "Invalid file URL".to_string())?,
+            };
+            std::fs::write(&path_buf, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+            Ok(Some(path_buf.to_string_lossy().to_string()))
+        }
+        None => Ok(None), // User cancelled
+    }
+}
@@ -1,4 +1,8 @@
 use tauri::Manager;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::services::network_inspector::{self, NetworkLogEntry, NetworkLogId};
+use crate::services::ApiClient;
 
 /// 打开开发者工具
 #[tauri::command]
@@ -10,3 +14,60 @@ pub async fn open_devtools(app: tauri::AppHandle) -> Result<(), String> {
         Err("无法获取主窗口".to_string())
     }
 }
+
+fn ensure_inspector_enabled() -> Result<(), String> {
+    if network_inspector::is_enabled() {
+        Ok(())
+    } else {
+        Err("请先在设置中开启开发者模式".to_string())
+    }
+}
+
+/// 获取网络检查器已采集的请求（按时间升序）；新请求另通过 network-log 事件推送
+#[tauri::command]
+pub async fn get_network_log(limit: Option<usize>) -> Result<Vec<NetworkLogEntry>, String> {
+    ensure_inspector_enabled()?;
+    Ok(network_inspector::snapshot(limit))
+}
+
+/// 清空网络检查器记录
+#[tauri::command]
+pub async fn clear_network_log() -> Result<(), String> {
+    network_inspector::clear();
+    Ok(())
+}
+
+/// 重放一条已采集的请求，返回新请求的记录（鉴权头使用当前登录态）
+#[tauri::command]
+pub async fn replay_request(id: u64) -> Result<NetworkLogEntry, String> {
+    ensure_inspector_enabled()?;
+    let (_, spec) = network_inspector::get(id).ok_or_else(|| "请求记录不存在".to_string())?;
+    let spec = spec.ok_or_else(|| "该请求不支持重放（流式请求或 multipart 上传）".to_string())?;
+
+    let response = ApiClient::new()
+        .send_raw(
+            spec.method,
+            &spec.url,
+            spec.content_type.as_deref(),
+            spec.body,
+        )
+        .await?;
+    response
+        .extensions()
+        .get::<NetworkLogId>()
+        .and_then(|NetworkLogId(new_id)| network_inspector::get(*new_id))
+        .map(|(entry, _)| entry)
+        .ok_or_else(|| "重放请求未被记录".to_string())
+}
+
+/// 生成等价的 curl 命令并写入剪贴板（Authorization 以 $PRD_AGENT_TOKEN 占位；不可重放的请求首行带提示）
+#[tauri::command]
+pub async fn copy_request_as_curl(app: tauri::AppHandle, id: u64) -> Result<String, String> {
+    ensure_inspector_enabled()?;
+    let (entry, replay) = network_inspector::get(id).ok_or_else(|| "请求记录不存在".to_string())?;
+    let curl = network_inspector::to_curl(&entry, replay.as_ref());
+    app.clipboard()
+        .write_text(curl.clone())
+        .map_err(|e| format!("Failed to write clipboard: {}", e))?;
+    Ok(curl)
+}
@@ -1,8 +1,12 @@
 use serde::Serialize;
-use tauri::command;
+use tauri::{command, AppHandle, Emitter};
 
-use crate::models::{ApiResponse, GroupInfo, GroupMemberInfo, OpenGroupSessionResponse};
-use crate::services::ApiClient;
+use crate::models::{
+    ApiError, ApiResponse, GroupInfo, GroupInvitePreview, GroupMemberInfo, GroupMemberTag,
+    OpenGroupSessionResponse,
+};
+use crate::services::deep_link::{self, DeepLinkRoute};
+use crate::services::{api_client, qr_code, ApiClient};
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -56,6 +60,56 @@ pub async fn join_group(
     client.post("/groups/join", &request).await
 }
 
+/// 邀请码先按 deep link 规则校验，避免把任意字符串拼进 URL
+fn normalize_invite_code(invite_code: &str) -> Result<String, String> {
+    // 直接校验原始输入：拼成 URL 再解析会先折叠 ../ 等路径段
+    let code = invite_code.trim();
+    if deep_link::valid_id(code) {
+        Ok(code.to_string())
+    } else {
+        Err("邀请码格式不正确".to_string())
+    }
+}
+
+/// 加入前预览：群名、PRD 标题、成员数
+#[command]
+pub async fn preview_invite(
+    invite_code: String,
+) -> Result<ApiResponse<GroupInvitePreview>, String> {
+    let code = normalize_invite_code(&invite_code)?;
+    let client = ApiClient::new();
+    client.get(&format!("/groups/invite/{}", code)).await
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupInviteLink {
+    pub invite_code: String,
+    /// prdagent://join/{inviteCode}
+    pub invite_link: String,
+    pub qr_svg: String,
+    pub qr_png_base64: String,
+}
+
+/// 生成可分享的邀请链接及二维码（SVG + PNG）
+#[command]
+pub async fn generate_invite_link(invite_code: String) -> Result<GroupInviteLink, String> {
+    use base64::Engine;
+    let invite_code = normalize_invite_code(&invite_code)?;
+    let invite_link = DeepLinkRoute::Join {
+        invite_code: invite_code.clone(),
+    }
+    .to_url();
+    let qr_svg = qr_code::render_svg(&invite_link, 240)?;
+    let png = qr_code::render_png(&invite_link, 8)?;
+    Ok(GroupInviteLink {
+        invite_code,
+        invite_link,
+        qr_svg,
+        qr_png_base64: base64::engine::general_purpose::STANDARD.encode(png),
+    })
+}
+
 #[command]
 pub async fn get_groups() -> Result<ApiResponse<Vec<GroupInfo>>, String> {
     let client = ApiClient::new();
@@ -125,8 +179,91 @@ struct AddGroupMemberRequest {
     member_role: String,
 }
 
+/// 成员变更事件：打开中的成员列表据此刷新
+pub const GROUP_MEMBERS_CHANGED_EVENT: &str = "group-members-changed";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupMembersChanged {
+    group_id: String,
+    /// added / removed / roleChanged / tagsUpdated / ownerTransferred
+    change: &'static str,
+    user_id: Option<String>,
+}
+
+fn notify_members_changed<T>(
+    app: &AppHandle,
+    result: &Result<ApiResponse<T>, String>,
+    group_id: &str,
+    change: &'static str,
+    user_id: Option<String>,
+) {
+    if matches!(result, Ok(resp) if resp.success) {
+        let _ = app.emit(
+            GROUP_MEMBERS_CHANGED_EVENT,
+            GroupMembersChanged {
+                group_id: group_id.to_string(),
+                change,
+                user_id,
+            },
+        );
+    }
+}
+
+pub(super) fn rejected<T>(code: &str, message: &str) -> ApiResponse<T> {
+    ApiResponse {
+        success: false,
+        data: None,
+        error: Some(ApiError {
+            code: code.to_string(),
+            message: message.to_string(),
+        }),
+    }
+}
+
+/// 校验当前用户是群主、目标是群成员；通过时返回目标成员
+async fn check_owner_action(
+    group_id: &str,
+    target_user_id: &str,
+) -> Result<Result<GroupMemberInfo, ApiError>, String> {
+    let deny = |code: &str, message: &str| {
+        Ok(Err(ApiError {
+            code: code.to_string(),
+            message: message.to_string(),
+        }))
+    };
+    if group_id.is_empty() || target_user_id.is_empty() {
+        return deny("INVALID_FORMAT", "groupId / userId 不能为空");
+    }
+    let Some(me) = api_client::get_auth_user_id() else {
+        return deny("UNAUTHORIZED", "未登录");
+    };
+    let resp = get_group_members(group_id.to_string()).await?;
+    let members = match resp {
+        ApiResponse {
+            success: true,
+            data: Some(members),
+            ..
+        } => members,
+        ApiResponse { error, .. } => {
+            return Ok(Err(error.unwrap_or(ApiError {
+                code: "UNKNOWN".to_string(),
+                message: "获取群成员失败".to_string(),
+            })))
+        }
+    };
+    if !members.iter().any(|m| m.user_id == me && m.is_owner) {
+        return deny("PERMISSION_DENIED", "仅群主可管理成员");
+    }
+    match members.into_iter().find(|m| m.user_id == target_user_id) {
+        Some(target) => Ok(Ok(target)),
+        None => deny("NOT_FOUND", "该用户不是群成员"),
+    }
+}
+
 #[command]
 pub async fn add_group_member(
+    app: AppHandle,
     group_id: String,
     username: String,
     member_role: String,
@@ -136,11 +273,194 @@ pub async fn add_group_member(
         username,
         member_role,
     };
-    client
+    let result: Result<ApiResponse<GroupMemberInfo>, String> = client
         .post(&format!("/groups/{}/members", group_id), &request)
+        .await;
+    let user_id = result
+        .as_ref()
+        .ok()
+        .and_then(|r| r.data.as_ref())
+        .map(|m| m.user_id.clone());
+    notify_members_changed(&app, &result, &group_id, "added", user_id);
+    result
+}
+
+/// 移除成员（仅群主；不能移除群主自己，群主离开前需先转让）
+pub(crate) async fn remove_member(
+    group_id: &str,
+    user_id: &str,
+) -> Result<ApiResponse<serde_json::Value>, String> {
+    let target = match check_owner_action(group_id, user_id).await? {
+        Ok(target) => target,
+        Err(e) => return Ok(rejected(&e.code, &e.message)),
+    };
+    if target.is_owner {
+        return Ok(rejected("PERMISSION_DENIED", "不能移除群主"));
+    }
+    let client = ApiClient::new();
+    client
+        .delete(&format!("/groups/{}/members/{}", group_id, user_id))
+        .await
+}
+
+#[command]
+pub async fn remove_group_member(
+    app: AppHandle,
+    group_id: String,
+    user_id: String,
+) -> Result<ApiResponse<serde_json::Value>, String> {
+    let (gid, uid) = (group_id.trim(), user_id.trim());
+    let result = remove_member(gid, uid).await;
+    notify_members_changed(&app, &result, gid, "removed", Some(uid.to_string()));
+    result
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateMemberRoleRequest {
+    member_role: String,
+}
+
+/// 修改成员角色（仅群主）
+pub(crate) async fn update_member_role(
+    group_id: &str,
+    user_id: &str,
+    member_role: &str,
+) -> Result<ApiResponse<GroupMemberInfo>, String> {
+    let member_role = member_role.trim().to_ascii_uppercase();
+    if member_role.is_empty()
+        || !member_role
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Ok(rejected("INVALID_FORMAT", "角色不合法"));
+    }
+    if let Err(e) = check_owner_action(group_id, user_id).await? {
+        return Ok(rejected(&e.code, &e.message));
+    }
+    let client = ApiClient::new();
+    client
+        .put(
+            &format!("/groups/{}/members/{}/role", group_id, user_id),
+            &UpdateMemberRoleRequest { member_role },
+        )
+        .await
+}
+
+#[command]
+pub async fn update_group_member_role(
+    app: AppHandle,
+    group_id: String,
+    user_id: String,
+    member_role: String,
+) -> Result<ApiResponse<GroupMemberInfo>, String> {
+    let (gid, uid) = (group_id.trim(), user_id.trim());
+    let result = update_member_role(gid, uid, &member_role).await;
+    notify_members_changed(&app, &result, gid, "roleChanged", Some(uid.to_string()));
+    result
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateMemberTagsRequest {
+    tags: Vec<GroupMemberTag>,
+}
+
+const MAX_MEMBER_TAGS: usize = 10;
+
+/// 编辑成员标签（仅群主）；按 role 去重，空标签丢弃
+pub(crate) async fn update_member_tags(
+    group_id: &str,
+    user_id: &str,
+    tags: Vec<GroupMemberTag>,
+) -> Result<ApiResponse<GroupMemberInfo>, String> {
+    let mut cleaned: Vec<GroupMemberTag> = Vec::new();
+    for tag in tags {
+        let name = tag.name.trim().to_string();
+        let role = tag.role.trim().to_ascii_lowercase();
+        if name.is_empty() || role.is_empty() || cleaned.iter().any(|t| t.role == role) {
+            continue;
+        }
+        cleaned.push(GroupMemberTag { name, role });
+    }
+    if cleaned.len() > MAX_MEMBER_TAGS {
+        return Ok(rejected(
+            "INVALID_FORMAT",
+            &format!("标签最多 {} 个", MAX_MEMBER_TAGS),
+        ));
+    }
+    if let Err(e) = check_owner_action(group_id, user_id).await? {
+        return Ok(rejected(&e.code, &e.message));
+    }
+    let client = ApiClient::new();
+    client
+        .put(
+            &format!("/groups/{}/members/{}/tags", group_id, user_id),
+            &UpdateMemberTagsRequest { tags: cleaned },
+        )
+        .await
+}
+
+#[command]
+pub async fn update_group_member_tags(
+    app: AppHandle,
+    group_id: String,
+    user_id: String,
+    tags: Vec<GroupMemberTag>,
+) -> Result<ApiResponse<GroupMemberInfo>, String> {
+    let (gid, uid) = (group_id.trim(), user_id.trim());
+    let result = update_member_tags(gid, uid, tags).await;
+    notify_members_changed(&app, &result, gid, "tagsUpdated", Some(uid.to_string()));
+    result
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransferOwnershipRequest {
+    new_owner_user_id: String,
+}
+
+/// 转让群主（仅群主；目标须为其他成员）
+pub(crate) async fn transfer_ownership(
+    group_id: &str,
+    new_owner_user_id: &str,
+) -> Result<ApiResponse<GroupInfo>, String> {
+    let target = match check_owner_action(group_id, new_owner_user_id).await? {
+        Ok(target) => target,
+        Err(e) => return Ok(rejected(&e.code, &e.message)),
+    };
+    if target.is_owner {
+        return Ok(rejected("INVALID_FORMAT", "该成员已是群主"));
+    }
+    let client = ApiClient::new();
+    client
+        .post(
+            &format!("/groups/{}/owner/transfer", group_id),
+            &TransferOwnershipRequest {
+                new_owner_user_id: target.user_id,
+            },
+        )
         .await
 }
 
+#[command]
+pub async fn transfer_group_ownership(
+    app: AppHandle,
+    group_id: String,
+    new_owner_user_id: String,
+) -> Result<ApiResponse<GroupInfo>, String> {
+    let (gid, uid) = (group_id.trim(), new_owner_user_id.trim());
+    let result = transfer_ownership(gid, uid).await;
+    notify_members_changed(
+        &app,
+        &result,
+        gid,
+        "ownerTransferred",
+        Some(uid.to_string()),
+    );
+    result
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct EmptyBody {}
@@ -148,24 +468,154 @@ struct EmptyBody {}
 /// 清理群组上下文（服务端 LLM 上下文缓存）
 /// - 不删除消息历史
 /// - 仅影响后续提问时的上下文拼接
+/// - 清理前在本机记录快照，可用 restore_group_context 恢复
 #[command]
 pub async fn clear_group_context(
+    app: AppHandle,
     group_id: String,
+    session_id: Option<String>,
 ) -> Result<ApiResponse<serde_json::Value>, String> {
     let gid = group_id.trim().to_string();
     if gid.is_empty() {
-        return Ok(ApiResponse::<serde_json::Value> {
-            success: false,
-            data: None,
-            error: Some(crate::models::ApiError {
-                code: "INVALID_FORMAT".to_string(),
-                message: "groupId 不能为空".to_string(),
-            }),
-        });
+        return Ok(rejected("INVALID_FORMAT", "groupId 不能为空"));
     }
+    let sid = session_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    let dir = super::context_snapshot::store_dir(&app)?;
+    super::context_snapshot::clear_with_snapshot(&dir, &gid, sid).await
+}
+
+pub(crate) async fn clear_context(
+    group_id: &str,
+) -> Result<ApiResponse<serde_json::Value>, String> {
     let client = ApiClient::new();
     let request = EmptyBody {};
     client
-        .post(&format!("/groups/{}/context/clear", gid), &request)
+        .post(&format!("/groups/{}/context/clear", group_id), &request)
         .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{signed_in, MockResponse, MOCK_USER_ID};
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn invite_preview_and_share_link() {
+        let (_guard, _server) = signed_in().await;
+
+        let preview = preview_invite("MOCK01".into()).await.expect("preview");
+        let preview = preview.data.expect("preview data");
+        assert_eq!(preview.group_name, "Mock 群组");
+        assert_eq!(preview.member_count, 1);
+        assert!(preview.already_member);
+
+        let invalid = preview_invite("NOPE42".into()).await.expect("response");
+        assert!(!invalid.success);
+        assert_eq!(invalid.error.expect("error").code, "INVALID_INVITE_LINK");
+        assert!(preview_invite("../admin".into()).await.is_err());
+
+        let link = generate_invite_link(" MOCK01 ".into()).await.expect("link");
+        assert_eq!(link.invite_link, "prdagent://join/MOCK01");
+        assert!(link.qr_svg.contains("<svg"));
+        assert!(!link.qr_png_base64.is_empty());
+    }
+
+    #[tokio::test]
+    async fn member_management_requires_owner() {
+        let (_guard, server) = signed_in().await;
+        let member = |user_id: &str, is_owner: bool| {
+            json!({
+                "userId": user_id,
+                "username": user_id,
+                "displayName": user_id,
+                "memberRole": "DEV",
+                "tags": [],
+                "joinedAt": "2026-01-01T00:00:00Z",
+                "isOwner": is_owner
+            })
+        };
+        let as_owner = json!([member(MOCK_USER_ID, true), member("user-2", false)]);
+        let as_member = json!([member("user-2", true), member(MOCK_USER_ID, false)]);
+
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1/members",
+            MockResponse::ok(as_owner.clone()),
+        );
+        server.script(
+            Method::DELETE,
+            "/api/v1/groups/group-1/members/user-2",
+            MockResponse::ok(json!({})),
+        );
+        let removed = remove_member("group-1", "user-2").await.expect("remove");
+        assert!(removed.success);
+
+        // 群主不能移除自己 / 不能把群主转让给自己
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1/members",
+            MockResponse::ok(as_owner.clone()),
+        );
+        let self_removed = remove_member("group-1", MOCK_USER_ID)
+            .await
+            .expect("response");
+        assert_eq!(self_removed.error.expect("error").code, "PERMISSION_DENIED");
+
+        // 非群主：所有管理操作在本地拒绝，不会发出写请求
+        for _ in 0..3 {
+            server.script(
+                Method::GET,
+                "/api/v1/groups/group-1/members",
+                MockResponse::ok(as_member.clone()),
+            );
+        }
+        let role = update_member_role("group-1", "user-2", "qa")
+            .await
+            .expect("response");
+        assert_eq!(role.error.expect("error").code, "PERMISSION_DENIED");
+        let tags = update_member_tags("group-1", "user-2", Vec::new())
+            .await
+            .expect("response");
+        assert!(!tags.success);
+        let transfer = transfer_ownership("group-1", "user-2")
+            .await
+            .expect("response");
+        assert!(!transfer.success);
+        assert!(server
+            .requests_to("/api/v1/groups/group-1/members/user-2/*")
+            .is_empty());
+        assert!(server
+            .requests_to("/api/v1/groups/group-1/owner/transfer")
+            .is_empty());
+
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1/members",
+            MockResponse::ok(as_owner),
+        );
+        server.script(
+            Method::POST,
+            "/api/v1/groups/group-1/owner/transfer",
+            MockResponse::ok(json!({
+                "groupId": "group-1",
+                "groupName": "Mock 群组",
+                "prdTitle": null,
+                "inviteCode": "MOCK01",
+                "memberCount": 2
+            })),
+        );
+        let transferred = transfer_ownership("group-1", "user-2")
+            .await
+            .expect("transfer");
+        assert!(transferred.success);
+        assert_eq!(
+            server.requests_to("/api/v1/groups/group-1/owner/transfer")[0].json()["newOwnerUserId"],
+            json!("user-2")
+        );
+    }
+}
@@ -0,0 +1,613 @@
+//! 群组归档：把消息历史、PRD 正文、评论、成员与附件打包为单个 zip，解散群组后仍可离线只读浏览。
+//!
+//! 归档结构：
+//! - manifest.json     格式版本、导出时间、各部分数量
+//! - group.json / members.json / messages.json / comments.json / prd.json / attachments.json
+//! - prd.md / conversation.md   便于直接阅读的 Markdown
+//! - assets/<attachmentId>-<fileName>
+
+use chrono::Local;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{Cursor, Read, Write};
+use tauri::{command, AppHandle, Emitter};
+use zip::write::SimpleFileOptions;
+
+use super::defect_export::save_with_dialog;
+use super::document::get_document_content;
+use super::group::get_group_members;
+use super::prd_comments::get_prd_comments;
+use super::session::get_group_message_history;
+use crate::models::{
+    ApiResponse, DocumentContentInfo, GroupInfo, GroupMemberInfo, MessageHistoryItem,
+    PrdCommentInfo,
+};
+use crate::services::ApiClient;
+
+pub const ARCHIVE_FORMAT: &str = "prd-agent-group-archive";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// 归档进度事件：stage = group / members / messages / prd / comments / attachments / writing
+pub const ARCHIVE_PROGRESS_EVENT: &str = "group-archive-progress";
+
+const MESSAGE_PAGE_SIZE: i32 = 200;
+const MAX_COMMENTS: i32 = 5000;
+/// 单个附件上限，超过的只记录在 attachments.json 中
+const MAX_ATTACHMENT_BYTES: usize = 20 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub group_id: String,
+    pub group_name: String,
+    pub message_count: usize,
+    pub member_count: usize,
+    pub comment_count: usize,
+    pub attachment_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedAttachment {
+    pub attachment_id: String,
+    #[serde(default)]
+    pub file_name: Option<String>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// 归档内路径；下载失败时为空
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// 打开归档后返回给前端的只读视图（附件内容按需用 read_archive_asset 读取）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupArchive {
+    pub manifest: ArchiveManifest,
+    pub group: GroupInfo,
+    pub members: Vec<GroupMemberInfo>,
+    pub messages: Vec<MessageHistoryItem>,
+    pub prd: Option<DocumentContentInfo>,
+    pub comments: Vec<PrdCommentInfo>,
+    pub attachments: Vec<ArchivedAttachment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AttachmentInfo {
+    url: String,
+    file_name: Option<String>,
+    mime_type: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveProgress<'a> {
+    group_id: &'a str,
+    stage: &'a str,
+    done: usize,
+}
+
+fn api_data<T>(resp: ApiResponse<T>, what: &str) -> Result<T, String> {
+    match resp {
+        ApiResponse {
+            success: true,
+            data: Some(data),
+            ..
+        } => Ok(data),
+        ApiResponse { error, .. } => Err(error
+            .map(|e| e.message)
+            .unwrap_or_else(|| format!("获取{}失败", what))),
+    }
+}
+
+/// 拉取全部消息：服务端 afterSeq 需大于 0，因此从最新一页开始按 beforeSeq 向前翻页
+async fn fetch_all_messages(
+    group_id: &str,
+    progress: &impl Fn(&str, usize),
+) -> Result<Vec<MessageHistoryItem>, String> {
+    let mut all: Vec<MessageHistoryItem> = Vec::new();
+    let mut before_seq: Option<i64> = None;
+    loop {
+        let page = api_data(
+            get_group_message_history(
+                group_id.to_string(),
+                Some(MESSAGE_PAGE_SIZE),
+                None,
+                None,
+                before_seq,
+                None,
+                None,
+            )
+            .await?,
+            "消息历史",
+        )?;
+        let min_seq = page.iter().filter_map(|m| m.group_seq).min();
+        all.extend(page);
+        progress("messages", all.len());
+        match min_seq {
+            Some(seq) if seq > 1 && before_seq.is_none_or(|b| seq < b) => before_seq = Some(seq),
+            _ => break,
+        }
+    }
+    all.sort_by_key(|m| m.group_seq.unwrap_or(i64::MAX));
+    all.dedup_by(|a, b| a.id == b.id);
+    Ok(all)
+}
+
+/// 附件名只保留安全字符，避免归档内出现路径分隔符
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let cleaned = cleaned.trim_matches('.').to_string();
+    if cleaned.is_empty() {
+        "file".to_string()
+    } else {
+        cleaned
+    }
+}
+
+async fn download_attachment(
+    client: &ApiClient,
+    attachment_id: &str,
+) -> (ArchivedAttachment, Option<Vec<u8>>) {
+    let mut entry = ArchivedAttachment {
+        attachment_id: attachment_id.to_string(),
+        file_name: None,
+        mime_type: None,
+        path: None,
+        error: None,
+    };
+    let info = match client
+        .get::<AttachmentInfo>(&format!("/attachments/{}", attachment_id))
+        .await
+        .and_then(|r| api_data(r, "附件信息"))
+    {
+        Ok(info) => info,
+        Err(e) => {
+            entry.error = Some(e);
+            return (entry, None);
+        }
+    };
+    entry.file_name = info.file_name.clone();
+    entry.mime_type = info.mime_type.clone();
+
+    // 同源 / 相对地址经 ApiClient 带鉴权下载；对象存储等外部地址不带鉴权头
+    let bytes = match client.download(&info.url).await {
+        Ok(resp) if resp.status().is_success() => read_bounded(resp, MAX_ATTACHMENT_BYTES).await,
+        Ok(resp) => Err(format!("HTTP {}", resp.status().as_u16())),
+        Err(e) => Err(e),
+    };
+    match bytes {
+        Ok(None) => {
+            entry.error = Some("附件超过 20MB，未打包".to_string());
+            (entry, None)
+        }
+        Ok(Some(bytes)) => {
+            let name = sanitize_file_name(info.file_name.as_deref().unwrap_or("file"));
+            entry.path = Some(format!("assets/{}-{}", attachment_id, name));
+            (entry, Some(bytes))
+        }
+        Err(e) => {
+            entry.error = Some(format!("下载附件失败: {}", e));
+            (entry, None)
+        }
+    }
+}
+
+/// 边读边计数，超过 max 即停止（Content-Length 已超限时不读取）；超限返回 None
+async fn read_bounded(
+    mut response: reqwest::Response,
+    max: usize,
+) -> Result<Option<Vec<u8>>, String> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > max as u64)
+    {
+        return Ok(None);
+    }
+    let mut buf = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        if buf.len() + chunk.len() > max {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(Some(buf))
+}
+
+fn render_prd_markdown(prd: &DocumentContentInfo) -> String {
+    if prd.content.trim_start().starts_with('#') {
+        prd.content.clone()
+    } else {
+        format!("# {}\n\n{}", prd.title, prd.content)
+    }
+}
+
+fn render_conversation_markdown(
+    group: &GroupInfo,
+    messages: &[MessageHistoryItem],
+    attachments: &[ArchivedAttachment],
+) -> String {
+    let mut md = format!("# {} · 群聊记录\n\n", group.group_name);
+    for m in messages {
+        let sender = m.sender_name.clone().unwrap_or_else(|| m.role.clone());
+        let role = m.sender_role.as_deref().unwrap_or(&m.role);
+        md.push_str(&format!("### {} ({}) · {}\n\n", sender, role, m.timestamp));
+        md.push_str(m.content.trim());
+        md.push_str("\n\n");
+        for id in m.attachment_ids.iter().flatten() {
+            match attachments.iter().find(|a| &a.attachment_id == id) {
+                Some(ArchivedAttachment {
+                    path: Some(path),
+                    file_name,
+                    ..
+                }) => md.push_str(&format!(
+                    "- 附件：[{}]({})\n",
+                    file_name.as_deref().unwrap_or(id),
+                    path
+                )),
+                _ => md.push_str(&format!("- 附件：{}（未打包）\n", id)),
+            }
+        }
+    }
+    md
+}
+
+fn zip_json<T: Serialize>(
+    zip: &mut zip::ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    value: &T,
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    let bytes =
+        serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    zip_bytes(zip, name, &bytes, options)
+}
+
+fn zip_bytes(
+    zip: &mut zip::ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    bytes: &[u8],
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("Failed to write zip: {}", e))?;
+    zip.write_all(bytes)
+        .map_err(|e| format!("Failed to write zip: {}", e))
+}
+
+/// 拉取群组全部内容并打包为 zip 字节
+pub(crate) async fn build_archive(
+    group_id: &str,
+    progress: impl Fn(&str, usize),
+) -> Result<Vec<u8>, String> {
+    let client = ApiClient::new();
+
+    progress("group", 0);
+    let group: GroupInfo = api_data(
+        client.get(&format!("/groups/{}", group_id)).await?,
+        "群组信息",
+    )?;
+    progress("members", 0);
+    let members = api_data(get_group_members(group_id.to_string()).await?, "群成员")?;
+    let messages = fetch_all_messages(group_id, &progress).await?;
+
+    let (prd, comments) = match group.prd_document_id.as_deref() {
+        Some(doc_id) if !doc_id.is_empty() => {
+            progress("prd", 0);
+            let prd = api_data(
+                get_document_content(doc_id.to_string(), group_id.to_string()).await?,
+                "PRD 正文",
+            )?;
+            progress("comments", 0);
+            let comments = api_data(
+                get_prd_comments(
+                    doc_id.to_string(),
+                    group_id.to_string(),
+                    None,
+                    Some(MAX_COMMENTS),
+                )
+                .await?,
+                "PRD 评论",
+            )?;
+            (Some(prd), comments)
+        }
+        _ => (None, Vec::new()),
+    };
+
+    let mut attachment_ids: Vec<String> = messages
+        .iter()
+        .flat_map(|m| m.attachment_ids.iter().flatten().cloned())
+        .collect();
+    attachment_ids.sort();
+    attachment_ids.dedup();
+    let mut attachments = Vec::new();
+    let mut assets = Vec::new();
+    for id in &attachment_ids {
+        let (entry, bytes) = download_attachment(&client, id).await;
+        if let (Some(path), Some(bytes)) = (entry.path.clone(), bytes) {
+            assets.push((path, bytes));
+        }
+        attachments.push(entry);
+        progress("attachments", attachments.len());
+    }
+
+    progress("writing", 0);
+    let manifest = ArchiveManifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: Local::now().to_rfc3339(),
+        group_id: group.group_id.clone(),
+        group_name: group.group_name.clone(),
+        message_count: messages.len(),
+        member_count: members.len(),
+        comment_count: comments.len(),
+        attachment_count: assets.len(),
+    };
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    zip_json(&mut zip, "manifest.json", &manifest, options)?;
+    zip_json(&mut zip, "group.json", &group, options)?;
+    zip_json(&mut zip, "members.json", &members, options)?;
+    zip_json(&mut zip, "messages.json", &messages, options)?;
+    zip_json(&mut zip, "comments.json", &comments, options)?;
+    zip_json(&mut zip, "prd.json", &prd, options)?;
+    zip_json(&mut zip, "attachments.json", &attachments, options)?;
+    if let Some(prd) = &prd {
+        zip_bytes(
+            &mut zip,
+            "prd.md",
+            render_prd_markdown(prd).as_bytes(),
+            options,
+        )?;
+    }
+    let conversation = render_conversation_markdown(&group, &messages, &attachments);
+    zip_bytes(
+        &mut zip,
+        "conversation.md",
+        conversation.as_bytes(),
+        options,
+    )?;
+    for (path, bytes) in &assets {
+        zip_bytes(&mut zip, path, bytes, options)?;
+    }
+    Ok(zip
+        .finish()
+        .map_err(|e| format!("Failed to write zip: {}", e))?
+        .into_inner())
+}
+
+fn read_entry(
+    archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>,
+    name: &str,
+) -> Result<Vec<u8>, String> {
+    let mut file = archive
+        .by_name(name)
+        .map_err(|_| format!("归档缺少 {}", name))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| format!("读取归档失败: {}", e))?;
+    Ok(bytes)
+}
+
+fn read_json<T: DeserializeOwned>(
+    archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>,
+    name: &str,
+) -> Result<T, String> {
+    serde_json::from_slice(&read_entry(archive, name)?)
+        .map_err(|e| format!("归档内容损坏（{}）: {}", name, e))
+}
+
+fn open_zip(bytes: Vec<u8>) -> Result<zip::ZipArchive<Cursor<Vec<u8>>>, String> {
+    zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("不是有效的归档文件: {}", e))
+}
+
+/// 解析归档 zip（只读）
+pub(crate) fn read_archive(bytes: Vec<u8>) -> Result<GroupArchive, String> {
+    let mut archive = open_zip(bytes)?;
+    let manifest: ArchiveManifest = read_json(&mut archive, "manifest.json")?;
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err("不是 PRD Agent 群组归档".to_string());
+    }
+    if manifest.version > ARCHIVE_VERSION {
+        return Err("归档版本过新，请升级客户端后再打开".to_string());
+    }
+    Ok(GroupArchive {
+        group: read_json(&mut archive, "group.json")?,
+        members: read_json(&mut archive, "members.json")?,
+        messages: read_json(&mut archive, "messages.json")?,
+        prd: read_json(&mut archive, "prd.json")?,
+        comments: read_json(&mut archive, "comments.json")?,
+        attachments: read_json(&mut archive, "attachments.json")?,
+        manifest,
+    })
+}
+
+/// 把群组完整归档为 zip（消息、PRD、评论、成员、附件），通过系统保存对话框选择路径
+/// - 过程中发 group-archive-progress 事件
+/// - 返回保存路径；用户取消时返回 None
+#[command]
+pub async fn archive_group_snapshot(
+    app: AppHandle,
+    group_id: String,
+) -> Result<Option<String>, String> {
+    let gid = group_id.trim().to_string();
+    if gid.is_empty() {
+        return Err("groupId 不能为空".to_string());
+    }
+    let bytes = build_archive(&gid, |stage, done| {
+        let _ = app.emit(
+            ARCHIVE_PROGRESS_EVENT,
+            ArchiveProgress {
+                group_id: &gid,
+                stage,
+                done,
+            },
+        );
+    })
+    .await?;
+
+    let default_name = format!(
+        "group-{}-{}.prdarchive.zip",
+        sanitize_file_name(&gid),
+        Local::now().format("%Y%m%d-%H%M%S")
+    );
+    save_with_dialog(&app, &default_name, "Zip", "zip", &bytes)
+}
+
+/// 打开本地归档文件，只读浏览
+#[command]
+pub async fn open_archive(path: String) -> Result<GroupArchive, String> {
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("读取归档失败: {}", e))?;
+    read_archive(bytes)
+}
+
+/// 读取归档内的附件（base64），仅允许 attachments.json 中登记过的路径
+#[command]
+pub async fn read_archive_asset(path: String, asset_path: String) -> Result<String, String> {
+    use base64::Engine;
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("读取归档失败: {}", e))?;
+    let mut archive = open_zip(bytes)?;
+    let attachments: Vec<ArchivedAttachment> = read_json(&mut archive, "attachments.json")?;
+    if !attachments
+        .iter()
+        .any(|a| a.path.as_deref() == Some(asset_path.as_str()))
+    {
+        return Err("附件不存在".to_string());
+    }
+    let data = read_entry(&mut archive, &asset_path)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{signed_in, MockResponse, MOCK_USER_ID};
+    use reqwest::Method;
+    use serde_json::json;
+
+    fn response(body: &'static [u8]) -> reqwest::Response {
+        reqwest::Response::from(http::Response::new(reqwest::Body::from(body)))
+    }
+
+    #[tokio::test]
+    async fn read_bounded_stops_at_the_limit() {
+        assert_eq!(
+            read_bounded(response(b"0123456789"), 10).await,
+            Ok(Some(b"0123456789".to_vec()))
+        );
+        assert_eq!(read_bounded(response(b"0123456789"), 9).await, Ok(None));
+        assert_eq!(read_bounded(response(b""), 0).await, Ok(Some(Vec::new())));
+    }
+
+    #[tokio::test]
+    async fn group_archive_round_trips_history_prd_and_assets() {
+        let (_guard, server) = signed_in().await;
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1",
+            MockResponse::ok(json!({
+                "groupId": "group-1",
+                "groupName": "Mock 群组",
+                "prdDocumentId": "doc-1",
+                "prdTitle": "Mock PRD",
+                "inviteCode": "MOCK01",
+                "memberCount": 1
+            })),
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1/messages",
+            MockResponse::ok(json!([
+                { "id": "m-1", "groupSeq": 1, "senderName": "Mock 用户", "role": "User",
+                  "content": "登录页需要验证码吗？", "timestamp": "2026-01-01T00:00:00Z",
+                  "attachmentIds": ["att-1", "att-2"] },
+                { "id": "m-2", "groupSeq": 2, "role": "Assistant",
+                  "content": "需要，见 3.2 节。", "timestamp": "2026-01-01T00:00:05Z" }
+            ])),
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/documents/doc-1/content",
+            MockResponse::ok(
+                json!({ "id": "doc-1", "title": "Mock PRD", "content": "# Mock PRD\n\n正文" }),
+            ),
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/prd-comments",
+            MockResponse::ok(json!([{
+                "id": "c-1", "documentId": "doc-1", "headingId": "h-1",
+                "headingTitleSnapshot": "3.2 验证码", "authorUserId": MOCK_USER_ID,
+                "authorDisplayName": "Mock 用户", "content": "确认过了", "createdAt": "2026-01-01T00:00:00Z"
+            }])),
+        );
+        // att-1 为相对地址（同源，带鉴权）；att-2 换用 localhost 访问同一服务，模拟外部来源（不带鉴权）
+        server.script(
+            Method::GET,
+            "/api/v1/attachments/att-1",
+            MockResponse::ok(json!({
+                "attachmentId": "att-1",
+                "url": "/files/att-1.png",
+                "fileName": "截图 1/../a.png",
+                "mimeType": "image/png"
+            })),
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/attachments/att-2",
+            MockResponse::ok(json!({
+                "attachmentId": "att-2",
+                "url": server.base_url().replace("127.0.0.1", "localhost") + "/files/att-2.png",
+                "fileName": "b.png",
+                "mimeType": "image/png"
+            })),
+        );
+        for path in ["/files/att-1.png", "/files/att-2.png"] {
+            server.script(
+                Method::GET,
+                path,
+                MockResponse::Json {
+                    status: 200,
+                    body: json!("png-bytes"),
+                },
+            );
+        }
+
+        let bytes = build_archive("group-1", |_, _| {}).await.expect("archive");
+        assert!(server.requests_to("/files/att-1.png")[0]
+            .header("authorization")
+            .is_some());
+        assert!(server.requests_to("/files/att-2.png")[0]
+            .header("authorization")
+            .is_none());
+
+        let archive = read_archive(bytes).expect("read archive");
+        assert_eq!(archive.manifest.message_count, 2);
+        assert_eq!(archive.manifest.attachment_count, 2);
+        assert_eq!(archive.members.len(), 1);
+        assert_eq!(archive.comments[0].content, "确认过了");
+        assert_eq!(archive.prd.expect("prd").title, "Mock PRD");
+        let asset = archive.attachments[0].path.clone().expect("asset path");
+        assert!(asset.starts_with("assets/att-1-") && !asset["assets/".len()..].contains('/'));
+
+        assert!(read_archive(b"not a zip".to_vec()).is_err());
+    }
+}
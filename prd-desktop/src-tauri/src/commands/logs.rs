@@ -0,0 +1,129 @@
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+use tauri::{command, AppHandle, Manager};
+
+use super::defect_export::save_with_dialog;
+use crate::services::{client_log, logging};
+
+const DEFAULT_TAIL_LINES: usize = 200;
+const MAX_TAIL_LINES: usize = 5000;
+
+/// 日志文件信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFileInfo {
+    pub name: String,
+    pub size: u64,
+    pub modified_ms: i64,
+}
+
+fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    match logging::log_dir() {
+        Some(dir) => Ok(dir),
+        None => app
+            .path()
+            .app_log_dir()
+            .map_err(|e| format!("Failed to get log dir: {}", e)),
+    }
+}
+
+/// 列出日志目录下本应用的滚动日志（按修改时间倒序）
+fn list_log_files(app: &AppHandle) -> Result<Vec<LogFileInfo>, String> {
+    let dir = log_dir(app)?;
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let suffix = format!(".{}", logging::LOG_FILE_SUFFIX);
+    let mut files: Vec<LogFileInfo> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(logging::LOG_FILE_PREFIX) || !name.ends_with(&suffix) {
+                return None;
+            }
+            let meta = entry.metadata().ok().filter(|m| m.is_file())?;
+            let modified_ms = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0);
+            Some(LogFileInfo {
+                name,
+                size: meta.len(),
+                modified_ms,
+            })
+        })
+        .collect();
+    files.sort_by_key(|f| std::cmp::Reverse(f.modified_ms));
+    Ok(files)
+}
+
+/// 获取日志文件列表
+#[command]
+pub async fn get_log_files(app: AppHandle) -> Result<Vec<LogFileInfo>, String> {
+    list_log_files(&app)
+}
+
+/// 读取日志文件末尾若干行
+/// - name 为空时读取最新的文件；只允许读取 get_log_files 列出的文件
+/// - lines 默认 200，最多 5000
+#[command]
+pub async fn read_log_tail(
+    app: AppHandle,
+    name: Option<String>,
+    lines: Option<usize>,
+) -> Result<String, String> {
+    let files = list_log_files(&app)?;
+    let file = match name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => files
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| format!("Log file not found: {}", name))?,
+        None => match files.first() {
+            Some(f) => f,
+            None => return Ok(String::new()),
+        },
+    };
+    let lines = lines.unwrap_or(DEFAULT_TAIL_LINES).clamp(1, MAX_TAIL_LINES);
+    logging::read_tail(&log_dir(&app)?.join(&file.name), lines)
+}
+
+/// 打包日志给技术支持：全部日志文件 + 客户端请求环形缓冲（client-logs.json）
+/// - 返回保存路径；用户取消时返回 None
+#[command]
+pub async fn export_logs_zip(app: AppHandle) -> Result<Option<String>, String> {
+    use zip::write::SimpleFileOptions;
+
+    let dir = log_dir(&app)?;
+    let files = list_log_files(&app)?;
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for file in &files {
+        let bytes = std::fs::read(dir.join(&file.name))
+            .map_err(|e| format!("Failed to read log file: {}", e))?;
+        zip.start_file(file.name.as_str(), options)
+            .map_err(|e| format!("Failed to write zip: {}", e))?;
+        zip.write_all(&bytes)
+            .map_err(|e| format!("Failed to write zip: {}", e))?;
+    }
+    let client_logs = serde_json::to_vec_pretty(&client_log::snapshot(None))
+        .map_err(|e| format!("Failed to serialize client logs: {}", e))?;
+    zip.start_file("client-logs.json", options)
+        .map_err(|e| format!("Failed to write zip: {}", e))?;
+    zip.write_all(&client_logs)
+        .map_err(|e| format!("Failed to write zip: {}", e))?;
+    let bytes = zip
+        .finish()
+        .map_err(|e| format!("Failed to write zip: {}", e))?
+        .into_inner();
+
+    let default_name = format!(
+        "prd-agent-logs-{}.zip",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    save_with_dialog(&app, &default_name, "Zip", "zip", &bytes)
+}
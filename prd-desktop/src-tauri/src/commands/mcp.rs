@@ -0,0 +1,50 @@
+use tauri::{command, AppHandle};
+
+use super::config::{load_config_from_file, save_config_to_file};
+use crate::services::mcp_server::{self, McpSettings, McpStatus};
+
+/// 获取 MCP 设置（含 HTTP 传输的 token，供用户填入 IDE 配置）
+#[command]
+pub async fn get_mcp_settings() -> Result<McpSettings, String> {
+    Ok(mcp_server::get_settings())
+}
+
+/// 写入 config.json 并立即生效；还没有 token 时生成一个
+fn persist(app: &AppHandle, mut settings: McpSettings) -> Result<McpStatus, String> {
+    if settings.token.is_empty() {
+        settings.token = Some(mcp_server::get_settings().token)
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(mcp_server::new_token);
+    }
+    let mut cfg = load_config_from_file(app)?;
+    cfg.mcp = Some(settings.clone());
+    save_config_to_file(app, &cfg)?;
+    mcp_server::set_settings(settings);
+    Ok(mcp_server::status())
+}
+
+/// 保存 MCP 设置（立即启动 / 停止 HTTP 服务并写入 config.json）；token 留空时保持不变
+#[command]
+pub async fn save_mcp_settings(app: AppHandle, settings: McpSettings) -> Result<McpStatus, String> {
+    if settings.port < 1024 {
+        return Err("端口需在 1024-65535 之间".to_string());
+    }
+    persist(&app, settings)
+}
+
+/// 重新生成 HTTP 传输的 token（旧 token 立即失效）
+#[command]
+pub async fn rotate_mcp_token(app: AppHandle) -> Result<McpSettings, String> {
+    let settings = McpSettings {
+        token: mcp_server::new_token(),
+        ..mcp_server::get_settings()
+    };
+    persist(&app, settings)?;
+    Ok(mcp_server::get_settings())
+}
+
+/// 获取 MCP 服务运行状态（监听地址、最近的启动错误、各工具是否放行）
+#[command]
+pub async fn get_mcp_status() -> Result<McpStatus, String> {
+    Ok(mcp_server::status())
+}
@@ -0,0 +1,265 @@
+use tauri::command;
+
+use super::group::rejected;
+use super::session::get_group_message_history;
+use crate::models::{ApiResponse, MessageHistoryItem};
+use crate::services::message_graph::{self, MessageThread, ResendVariants};
+use crate::services::ApiClient;
+
+/// 单次请求最多补拉的祖先消息数
+const MAX_ANCESTOR_FETCHES: usize = 20;
+/// 向后补拉回复 / 重发时最多翻的页数
+const MAX_FOLLOWING_PAGES: usize = 5;
+const PAGE_SIZE: i32 = 200;
+
+/// 拉取单条消息并写入缓存；请求失败（含 404 空响应）按“取不到”处理，不中断线程构建
+async fn fetch_message(group_id: &str, message_id: &str) -> bool {
+    let client = ApiClient::new();
+    let resp: Result<ApiResponse<MessageHistoryItem>, String> = client
+        .get(&format!("/groups/{}/messages/{}", group_id, message_id))
+        .await;
+    match resp {
+        Ok(ApiResponse {
+            success: true,
+            data: Some(m),
+            ..
+        }) => {
+            message_graph::ingest(group_id, &[m]);
+            true
+        }
+        Ok(_) => false,
+        Err(e) => {
+            tracing::warn!("failed to fetch message {}: {}", message_id, e);
+            false
+        }
+    }
+}
+
+/// 补拉缺失的自身与祖先消息；取不到时停止（结果中带 missingAncestorId）
+async fn fill_ancestors(group_id: &str, message_id: &str) {
+    for _ in 0..MAX_ANCESTOR_FETCHES {
+        let Some(missing) = message_graph::with_graph(group_id, |g| g.missing_for(message_id))
+        else {
+            break;
+        };
+        if !fetch_message(group_id, &missing).await {
+            break;
+        }
+    }
+}
+
+/// 补拉线程根之后的消息（回复与重发都晚于被回复 / 被重发的消息）
+async fn fill_following(group_id: &str, message_id: &str) -> Result<(), String> {
+    let Some(mut after) = message_graph::with_graph(group_id, |g| g.earliest_seq(message_id))
+    else {
+        return Ok(());
+    };
+    for _ in 0..MAX_FOLLOWING_PAGES {
+        let page = get_group_message_history(
+            group_id.to_string(),
+            Some(PAGE_SIZE),
+            None,
+            Some(after),
+            None,
+            None,
+            None,
+        )
+        .await?
+        .data
+        .unwrap_or_default();
+        let Some(max_seq) = page.iter().filter_map(|m| m.group_seq).max() else {
+            break;
+        };
+        if page.len() < PAGE_SIZE as usize || max_seq <= after {
+            break;
+        }
+        after = max_seq;
+    }
+    Ok(())
+}
+
+/// 未指定群组时从本机缓存中查找
+fn resolve_group(message_id: &str, group_id: Option<String>) -> Option<String> {
+    group_id
+        .map(|g| g.trim().to_string())
+        .filter(|g| !g.is_empty())
+        .or_else(|| message_graph::find_group(message_id))
+}
+
+async fn load_graph(group_id: &str, message_id: &str) -> Result<(), String> {
+    fill_ancestors(group_id, message_id).await;
+    fill_following(group_id, message_id).await
+}
+
+/// 消息所在的回复线程（祖先链 + 回复树），缺失的祖先按需补拉
+#[command]
+pub async fn get_thread(
+    message_id: String,
+    group_id: Option<String>,
+) -> Result<ApiResponse<MessageThread>, String> {
+    let message_id = message_id.trim().to_string();
+    let Some(group_id) = resolve_group(&message_id, group_id) else {
+        return Ok(rejected("INVALID_FORMAT", "未找到消息所在的群组"));
+    };
+    load_graph(&group_id, &message_id).await?;
+    Ok(
+        match message_graph::with_graph(&group_id, |g| g.thread(&message_id)) {
+            Some(thread) => ApiResponse {
+                success: true,
+                data: Some(thread),
+                error: None,
+            },
+            None => rejected("NOT_FOUND", "消息不存在"),
+        },
+    )
+}
+
+/// 某条 AI 回答的全部重新生成版本（按重发先后，最后一个为当前版本）
+#[command]
+pub async fn get_resend_variants(
+    message_id: String,
+    group_id: Option<String>,
+) -> Result<ApiResponse<ResendVariants>, String> {
+    let message_id = message_id.trim().to_string();
+    let Some(group_id) = resolve_group(&message_id, group_id) else {
+        return Ok(rejected("INVALID_FORMAT", "未找到消息所在的群组"));
+    };
+    load_graph(&group_id, &message_id).await?;
+    Ok(
+        match message_graph::with_graph(&group_id, |g| g.resend_variants(&message_id)) {
+            Some(variants) => ApiResponse {
+                success: true,
+                data: Some(variants),
+                error: None,
+            },
+            None => rejected("NOT_FOUND", "消息不存在"),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{signed_in, MockResponse};
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn message_graph_builds_threads_and_resend_variants() {
+        let (_guard, server) = signed_in().await;
+        let msg = |id: &str, seq: i64, role: &str, extra: serde_json::Value| {
+            let mut m = json!({
+                "id": id, "groupId": "group-thread", "groupSeq": seq, "role": role,
+                "content": format!("{} 内容", id), "timestamp": format!("2026-01-01T00:00:{:02}Z", seq)
+            });
+            m.as_object_mut()
+                .expect("object")
+                .extend(extra.as_object().cloned().unwrap_or_default());
+            m
+        };
+        let u1 = msg("u-1", 2, "User", json!({}));
+        let a1 = msg("a-1", 3, "Assistant", json!({ "replyToMessageId": "u-1" }));
+        let u2 = msg("u-2", 5, "User", json!({ "resendOfMessageId": "u-1" }));
+        let a2 = msg("a-2", 6, "Assistant", json!({ "replyToMessageId": "u-2" }));
+        let r1 = msg("r-1", 7, "User", json!({ "replyToMessageId": "a-2" }));
+
+        // 旧回答只在消息流中出现过；重发后服务端历史不再返回旧轮次
+        message_graph::ingest_event(&json!({ "type": "message", "message": a1.clone() }));
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-thread/messages",
+            MockResponse::ok(json!([u2.clone(), a2.clone(), r1.clone()])),
+        );
+        get_group_message_history("group-thread".into(), None, None, None, None, None, None)
+            .await
+            .expect("history");
+
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-thread/messages/u-1",
+            MockResponse::ok(u1.clone()),
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-thread/messages",
+            MockResponse::ok(json!([u2, a2, r1])),
+        );
+        let variants = get_resend_variants("a-2".into(), None)
+            .await
+            .expect("variants")
+            .data
+            .expect("data");
+        assert_eq!(variants.missing_ancestor_id, None);
+        let prompts: Vec<_> = variants
+            .variants
+            .iter()
+            .map(|v| (v.prompt.id.as_str(), v.answers[0].id.as_str(), v.current))
+            .collect();
+        assert_eq!(prompts, [("u-1", "a-1", false), ("u-2", "a-2", true)]);
+        let pages = server.requests_to("/api/v1/groups/group-thread/messages");
+        assert!(pages[1]
+            .query
+            .as_deref()
+            .is_some_and(|q| q.contains("afterSeq=2")));
+
+        let thread = get_thread("r-1".into(), Some("group-thread".into()))
+            .await
+            .expect("thread")
+            .data
+            .expect("data");
+        let ancestors: Vec<_> = thread.ancestors.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ancestors, ["u-2", "a-2"]);
+        assert_eq!(thread.root.replies[0].replies[0].message.id, "r-1");
+
+        // 祖先取不到时返回断点而不是失败
+        message_graph::ingest(
+            "group-thread",
+            &[serde_json::from_value(msg(
+                "a-9",
+                9,
+                "Assistant",
+                json!({ "replyToMessageId": "u-gone" }),
+            ))
+            .expect("message")],
+        );
+        let thread = get_thread("a-9".into(), None)
+            .await
+            .expect("thread")
+            .data
+            .expect("data");
+        assert_eq!(thread.missing_ancestor_id.as_deref(), Some("u-gone"));
+
+        // 单条消息接口返回空 body 的 404（ApiClient 报错）时同样返回部分线程
+        message_graph::ingest(
+            "group-thread",
+            &[serde_json::from_value(msg(
+                "a-10",
+                10,
+                "Assistant",
+                json!({ "replyToMessageId": "u-empty" }),
+            ))
+            .expect("message")],
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-thread/messages/u-empty",
+            MockResponse::Json {
+                status: 404,
+                body: serde_json::Value::Null,
+            },
+        );
+        let thread = get_thread("a-10".into(), Some("group-thread".into()))
+            .await
+            .expect("thread")
+            .data
+            .expect("data");
+        assert_eq!(thread.missing_ancestor_id.as_deref(), Some("u-empty"));
+        assert_eq!(thread.root.message.id, "a-10");
+        assert_eq!(
+            server
+                .requests_to("/api/v1/groups/group-thread/messages/u-empty")
+                .len(),
+            1
+        );
+    }
+}
@@ -2,15 +2,30 @@ pub mod assets;
 pub mod attachment;
 pub mod auth;
 pub mod branding;
+pub mod bridge;
 pub mod client_config;
 pub mod config;
+pub mod context_snapshot;
+pub mod crash;
+pub mod deep_link;
 pub mod defect;
+pub mod defect_draft;
+pub mod defect_export;
 pub mod devtools;
 pub mod document;
 pub mod group;
+pub mod group_archive;
 pub mod intent;
+pub mod logs;
+pub mod mcp;
+pub mod message_graph;
+pub mod notification;
 pub mod prd_comments;
+pub mod presence;
 pub mod preview_ask_history;
+pub mod role_views;
 pub mod session;
+pub mod session_documents;
 pub mod skill;
 pub mod updater;
+pub mod usage;
@@ -0,0 +1,44 @@
+use tauri::{command, AppHandle};
+
+use super::config::{load_config_from_file, save_config_to_file};
+use crate::services::notifier::{self, NotificationSettings};
+
+/// 获取通知设置
+#[command]
+pub async fn get_notification_settings() -> Result<NotificationSettings, String> {
+    Ok(notifier::get_settings())
+}
+
+/// 保存通知设置（立即生效并写入 config.json）
+#[command]
+pub async fn save_notification_settings(
+    app: AppHandle,
+    settings: NotificationSettings,
+) -> Result<(), String> {
+    let mut cfg = load_config_from_file(&app)?;
+    cfg.notifications = Some(settings.clone());
+    save_config_to_file(&app, &cfg)?;
+    notifier::set_settings(settings);
+    Ok(())
+}
+
+/// 静音 / 取消静音某个群组
+#[command]
+pub async fn set_group_muted(
+    app: AppHandle,
+    group_id: String,
+    muted: bool,
+) -> Result<NotificationSettings, String> {
+    let gid = group_id.trim().to_string();
+    if gid.is_empty() {
+        return Err("groupId 不能为空".to_string());
+    }
+
+    let mut settings = notifier::get_settings();
+    settings.muted_group_ids.retain(|g| g != &gid);
+    if muted {
+        settings.muted_group_ids.push(gid);
+    }
+    save_notification_settings(app, settings.clone()).await?;
+    Ok(settings)
+}
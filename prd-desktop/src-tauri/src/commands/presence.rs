@@ -0,0 +1,221 @@
+use serde::Serialize;
+use tauri::{command, AppHandle, Emitter};
+
+use super::group::get_group_members;
+use crate::models::{ApiError, ApiResponse};
+use crate::services::presence::{
+    self, GroupPresenceMember, PresenceChanged, PresenceEntry, PRESENCE_CHANGED_EVENT,
+    PRESENCE_POLL_INTERVAL,
+};
+use crate::services::{api_client, ApiClient};
+
+/// 拉取群成员并合并在线状态；任一接口失败都原样返回错误（不把未知状态当作离线）
+pub(crate) async fn load_group_presence(
+    group_id: &str,
+) -> Result<ApiResponse<Vec<GroupPresenceMember>>, String> {
+    let client = ApiClient::new();
+    let presence_path = format!("/desktop/presence/groups/{}", group_id);
+    let (members, entries) = futures::join!(
+        get_group_members(group_id.to_string()),
+        client.get::<Vec<PresenceEntry>>(&presence_path)
+    );
+    let members = match members? {
+        ApiResponse {
+            success: true,
+            data: Some(members),
+            ..
+        } => members,
+        ApiResponse { error, .. } => {
+            return Ok(ApiResponse {
+                success: false,
+                data: None,
+                error,
+            })
+        }
+    };
+    let entries = match entries? {
+        ApiResponse {
+            success: true,
+            data,
+            ..
+        } => data.unwrap_or_default(),
+        ApiResponse { error, .. } => {
+            return Ok(ApiResponse {
+                success: false,
+                data: None,
+                error,
+            })
+        }
+    };
+    let self_user_id = api_client::get_auth_user_id();
+    Ok(ApiResponse {
+        success: true,
+        data: Some(presence::merge(members, &entries, self_user_id.as_deref())),
+        error: None,
+    })
+}
+
+/// 获取群成员在线 / 输入状态
+#[command]
+pub async fn get_group_presence(
+    group_id: String,
+) -> Result<ApiResponse<Vec<GroupPresenceMember>>, String> {
+    let gid = group_id.trim();
+    if gid.is_empty() {
+        return Ok(ApiResponse {
+            success: false,
+            data: None,
+            error: Some(ApiError {
+                code: "INVALID_FORMAT".to_string(),
+                message: "groupId 不能为空".to_string(),
+            }),
+        });
+    }
+    load_group_presence(gid).await
+}
+
+/// 订阅群成员在线状态：定时轮询，状态变化时发 presence-changed（同一时间只订阅一个群）
+#[command]
+pub async fn subscribe_group_presence(app: AppHandle, group_id: String) -> Result<(), String> {
+    let gid = group_id.trim().to_string();
+    if gid.is_empty() {
+        return Err("groupId 不能为空".to_string());
+    }
+    let token = presence::new_watch_token();
+    tauri::async_runtime::spawn(async move {
+        let mut previous: Option<Vec<GroupPresenceMember>> = None;
+        let mut ticker = tokio::time::interval(PRESENCE_POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = ticker.tick() => {}
+            }
+            let Ok(ApiResponse {
+                data: Some(current),
+                ..
+            }) = load_group_presence(&gid).await
+            else {
+                continue;
+            };
+            let changed = match &previous {
+                Some(prev) => presence::diff(prev, &current),
+                None => current.clone(),
+            };
+            if !changed.is_empty() && !token.is_cancelled() {
+                let _ = app.emit(
+                    PRESENCE_CHANGED_EVENT,
+                    PresenceChanged {
+                        group_id: gid.clone(),
+                        members: changed,
+                    },
+                );
+            }
+            previous = Some(current);
+        }
+    });
+    Ok(())
+}
+
+/// 取消在线状态订阅（离开群组页面时调用）
+#[command]
+pub async fn unsubscribe_group_presence() -> Result<(), String> {
+    presence::stop_watch();
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TypingRequest {
+    group_id: String,
+}
+
+/// 发送"正在输入"信号；3 秒内重复调用会被节流，返回 false 表示本次未发送
+#[command]
+pub async fn send_typing(group_id: String) -> Result<bool, String> {
+    let gid = group_id.trim().to_string();
+    if gid.is_empty() {
+        return Err("groupId 不能为空".to_string());
+    }
+    presence::touch();
+    if !presence::should_send_typing(&gid) {
+        return Ok(false);
+    }
+    let client = ApiClient::new();
+    let resp: ApiResponse<serde_json::Value> = client
+        .post("/desktop/presence/typing", &TypingRequest { group_id: gid })
+        .await?;
+    Ok(resp.success)
+}
+
+/// 前端在键盘 / 鼠标活动时调用（自行节流），用于空闲判断
+#[command]
+pub async fn report_user_activity() -> Result<(), String> {
+    presence::touch();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{signed_in, MockResponse, MOCK_USER_ID};
+    use crate::services::presence;
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn presence_merges_members_and_throttles_typing() {
+        let (_guard, server) = signed_in().await;
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1/members",
+            MockResponse::ok(json!([
+                { "userId": MOCK_USER_ID, "username": "mock", "displayName": "Mock 用户",
+                  "memberRole": "PM", "joinedAt": "2026-01-01T00:00:00Z", "isOwner": true },
+                { "userId": "user-2", "username": "dev", "displayName": "Dev",
+                  "memberRole": "DEV", "joinedAt": "2026-01-01T00:00:00Z", "isOwner": false },
+                { "userId": "user-3", "username": "qa", "displayName": "QA",
+                  "memberRole": "QA", "joinedAt": "2026-01-01T00:00:00Z", "isOwner": false }
+            ])),
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/desktop/presence/groups/group-1",
+            MockResponse::ok(json!([
+                { "userId": "user-2", "online": true, "typing": true },
+                { "userId": "user-3", "online": false, "typing": true, "lastSeenAt": "2026-01-01T00:00:00Z" }
+            ])),
+        );
+        presence::touch();
+        let members = load_group_presence("group-1")
+            .await
+            .expect("presence")
+            .data
+            .expect("members");
+        let by_id = |id: &str| members.iter().find(|m| m.user_id == id).expect("member");
+        assert!(by_id(MOCK_USER_ID).online);
+        assert!(by_id("user-2").online && by_id("user-2").typing);
+        // 离线成员的残留 typing 不展示
+        assert!(!by_id("user-3").online && !by_id("user-3").typing);
+
+        let mut changed = members.clone();
+        changed[1].typing = false;
+        assert_eq!(presence::diff(&members, &changed), vec![changed[1].clone()]);
+
+        // 在线状态接口失败时报错，而不是把所有人显示为离线
+        server.script(
+            Method::GET,
+            "/api/v1/desktop/presence/groups/group-1",
+            MockResponse::error(403, "PERMISSION_DENIED", "您不是该群组成员"),
+        );
+        let failed = load_group_presence("group-1").await.expect("presence");
+        assert!(!failed.success);
+        assert_eq!(failed.error.expect("error").code, "PERMISSION_DENIED");
+
+        assert!(send_typing("group-typing".into()).await.expect("typing"));
+        assert!(!send_typing("group-typing".into()).await.expect("throttled"));
+        assert_eq!(
+            server.requests_to("/api/v1/desktop/presence/typing").len(),
+            1
+        );
+    }
+}
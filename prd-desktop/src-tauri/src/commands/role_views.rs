@@ -0,0 +1,144 @@
+use tauri::command;
+
+use super::session::{get_session, switch_role};
+use crate::models::{ApiResponse, SwitchRoleResponse};
+use crate::services::role_views;
+
+/// 该群组上次使用的角色（本机记录）
+#[command]
+pub async fn get_last_group_role(group_id: String) -> Result<Option<String>, String> {
+    Ok(role_views::last_role(group_id.trim()))
+}
+
+/// 重新打开群组时恢复上次的角色；没有记录或已一致时不发起切换
+#[command]
+pub async fn restore_group_role(
+    group_id: String,
+    session_id: String,
+) -> Result<ApiResponse<SwitchRoleResponse>, String> {
+    let group_id = group_id.trim().to_string();
+    let session_id = session_id.trim().to_string();
+    let session = get_session(session_id.clone()).await?;
+    let Some(current) = session.data.as_ref().map(|s| s.current_role.clone()) else {
+        return Ok(ApiResponse {
+            success: session.success,
+            data: None,
+            error: session.error,
+        });
+    };
+    match role_views::last_role(&group_id) {
+        Some(role) if !role.eq_ignore_ascii_case(&current) => {
+            switch_role(session_id, role, Some(group_id)).await
+        }
+        _ => Ok(ApiResponse {
+            success: true,
+            data: Some(SwitchRoleResponse {
+                session_id,
+                current_role: current,
+            }),
+            error: None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::session::get_group_message_history;
+    use crate::mock_server::{signed_in, MockResponse};
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn role_views_filter_and_restore() {
+        let (_guard, server) = signed_in().await;
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-1/messages",
+            MockResponse::ok(json!([
+                { "id": "m-1", "groupSeq": 1, "senderRole": "PM", "role": "User",
+                  "content": "登录要验证码吗？", "timestamp": "2026-01-01T00:00:00Z" },
+                { "id": "m-2", "groupSeq": 2, "viewRole": "DEV", "role": "Assistant",
+                  "content": "接口需要校验验证码。", "timestamp": "2026-01-01T00:00:05Z" },
+                { "id": "m-3", "groupSeq": 3, "viewRole": "QA", "role": "Assistant",
+                  "content": "补充验证码过期用例。", "timestamp": "2026-01-01T00:00:06Z" }
+            ])),
+        );
+        let resp = get_group_message_history(
+            "group-1".into(),
+            None,
+            None,
+            None,
+            None,
+            Some(" dev ".into()),
+            None,
+        )
+        .await
+        .expect("history");
+        let ids: Vec<_> = resp.data.expect("data").into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, ["m-2"]);
+        // 不足一页即历史已取完，不再翻页；角色条件不发给服务端
+        let pages = server.requests_to("/api/v1/groups/group-1/messages");
+        assert_eq!(pages.len(), 1);
+        assert!(!pages[0]
+            .query
+            .as_deref()
+            .is_some_and(|q| q.contains("viewRole")));
+
+        // 过滤后不足 limit 条时按 beforeSeq 继续向前翻页
+        let answer = |seq: i64, view_role: &str| {
+            json!({ "id": format!("m-{}", seq), "groupSeq": seq, "viewRole": view_role,
+                    "role": "Assistant", "content": "", "timestamp": "2026-01-01T00:00:00Z" })
+        };
+        let newest: Vec<_> = (201..=400)
+            .map(|seq| answer(seq, if seq == 300 { "DEV" } else { "PM" }))
+            .collect();
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-paged/messages",
+            MockResponse::ok(json!(newest)),
+        );
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-paged/messages",
+            MockResponse::ok(json!([answer(1, "DEV"), answer(2, "DEV"), answer(3, "QA")])),
+        );
+        let resp = get_group_message_history(
+            "group-paged".into(),
+            Some(2),
+            None,
+            None,
+            None,
+            Some("DEV".into()),
+            None,
+        )
+        .await
+        .expect("history");
+        let ids: Vec<_> = resp.data.expect("data").into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, ["m-2", "m-300"]);
+        let pages = server.requests_to("/api/v1/groups/group-paged/messages");
+        assert_eq!(pages.len(), 2);
+        assert!(pages[1]
+            .query
+            .as_deref()
+            .is_some_and(|q| q.contains("beforeSeq=201")));
+
+        // 切换角色后记住，重新打开群组时恢复
+        let resp = switch_role("session-1".into(), "DEV".into(), Some("group-roles".into()))
+            .await
+            .expect("switch");
+        assert!(resp.success);
+        assert_eq!(role_views::last_role("group-roles").as_deref(), Some("DEV"));
+        let restored = restore_group_role("group-roles".into(), "session-1".into())
+            .await
+            .expect("restore");
+        assert_eq!(restored.data.expect("data").current_role, "DEV");
+        assert_eq!(server.requests_to("/api/v1/sessions/*/role").len(), 2);
+
+        let untouched = restore_group_role("group-unknown".into(), "session-1".into())
+            .await
+            .expect("restore");
+        assert_eq!(untouched.data.expect("data").current_role, "PM");
+        assert_eq!(server.requests_to("/api/v1/sessions/*/role").len(), 2);
+    }
+}
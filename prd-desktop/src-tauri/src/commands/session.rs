@@ -6,13 +6,18 @@ use tauri::{command, AppHandle, Emitter, State};
 use tokio_util::sync::CancellationToken;
 
 use crate::models::{ApiResponse, MessageHistoryItem, SessionInfo, SwitchRoleResponse};
-use crate::services::{api_client, ApiClient};
+use crate::services::message_graph;
+use crate::services::role_views::{self, RoleFilter};
+use crate::services::usage_ledger::{self, UsageContext};
+use crate::services::{api_client, client_log, notifier, ApiClient};
 
 #[derive(Default)]
 pub struct StreamCancelState {
     message: Mutex<CancellationToken>,
     preview: Mutex<CancellationToken>,
     group: Mutex<CancellationToken>,
+    defect: Mutex<CancellationToken>,
+    my_defects: Mutex<CancellationToken>,
 }
 
 impl StreamCancelState {
@@ -34,10 +39,24 @@ impl StreamCancelState {
         *guard = CancellationToken::new();
         guard.clone()
     }
+    pub(crate) fn new_defect_token(&self) -> CancellationToken {
+        let mut guard = self.defect.lock().unwrap();
+        guard.cancel();
+        *guard = CancellationToken::new();
+        guard.clone()
+    }
+    pub(crate) fn new_my_defects_token(&self) -> CancellationToken {
+        let mut guard = self.my_defects.lock().unwrap();
+        guard.cancel();
+        *guard = CancellationToken::new();
+        guard.clone()
+    }
     pub fn cancel_all(&self) {
         self.message.lock().unwrap().cancel();
         self.preview.lock().unwrap().cancel();
         self.group.lock().unwrap().cancel();
+        self.defect.lock().unwrap().cancel();
+        self.my_defects.lock().unwrap().cancel();
     }
     fn cancel_message(&self) {
         self.message.lock().unwrap().cancel();
@@ -48,6 +67,12 @@ impl StreamCancelState {
     fn cancel_group(&self) {
         self.group.lock().unwrap().cancel();
     }
+    fn cancel_defect(&self) {
+        self.defect.lock().unwrap().cancel();
+    }
+    fn cancel_my_defects(&self) {
+        self.my_defects.lock().unwrap().cancel();
+    }
 }
 
 // debug instrumentation cleanup:
@@ -79,11 +104,21 @@ pub async fn cancel_stream(
             cancel.cancel_group();
             Ok(())
         }
+        "defect" => {
+            cancel.cancel_defect();
+            Ok(())
+        }
+        "my-defects" => {
+            cancel.cancel_my_defects();
+            Ok(())
+        }
         _ => Ok(()),
     }
 }
 
-fn emit_stream_error(app: &AppHandle, channel: &str, message: String) {
+pub(crate) fn emit_stream_error(app: &AppHandle, channel: &str, message: String) {
+    client_log::record_error(channel, &message);
+    tracing::warn!(channel, "stream error: {}", message);
     // 前端只监听 message-chunk / preview-ask-chunk，不监听 "error" 事件名
     let _ = app.emit(
         channel,
@@ -94,7 +129,9 @@ fn emit_stream_error(app: &AppHandle, channel: &str, message: String) {
     );
 }
 
-fn emit_auth_expired(app: &AppHandle) {
+pub(crate) fn emit_auth_expired(app: &AppHandle) {
+    client_log::record_error("auth-expired", "UNAUTHORIZED");
+    tracing::warn!("auth expired");
     // 统一事件：前端收到后跳转登录（但保留本地上下文/消息）
     let _ = app.emit(
         "auth-expired",
@@ -103,6 +140,7 @@ fn emit_auth_expired(app: &AppHandle) {
 }
 
 fn emit_stream_phase(app: &AppHandle, channel: &str, phase: &str) {
+    client_log::record_stream(channel, phase);
     let _ = app.emit(
         channel,
         serde_json::json!({
@@ -112,14 +150,20 @@ fn emit_stream_phase(app: &AppHandle, channel: &str, phase: &str) {
     );
 }
 
-fn handle_sse_text(
-    app: &AppHandle,
-    channel: &str,
-    buf: &mut String,
-    incoming: &str,
-    saw_any_data: &mut bool,
-) {
+const GROUP_MESSAGE_CHANNEL: &str = "group-message";
+
+/// 单个 SSE 事件帧
+pub(crate) enum SseFrame {
+    /// keep-alive 注释行（如 ": keepalive"）
+    KeepAlive,
+    /// data 行拼接后的内容（已 trim，非空）
+    Data(String),
+}
+
+/// 把新到达的文本追加进缓冲区，并切出所有完整的 SSE 事件（以空行分隔）
+pub(crate) fn drain_sse_frames(buf: &mut String, incoming: &str) -> Vec<SseFrame> {
     buf.push_str(incoming);
+    let mut frames = Vec::new();
 
     // SSE event delimiter: blank line
     while let Some(idx) = buf.find("\n\n") {
@@ -133,8 +177,7 @@ fn handle_sse_text(
         });
 
         if is_keepalive {
-            // 发送 keepalive 心跳事件到前端（用于重置心跳计时器）
-            let _ = app.emit(channel, serde_json::json!({ "type": "keepalive" }));
+            frames.push(SseFrame::KeepAlive);
             continue;
         }
 
@@ -157,50 +200,293 @@ fn handle_sse_text(
             continue;
         }
 
+        frames.push(SseFrame::Data(data));
+    }
+
+    frames
+}
+
+/// SSE 流的消费方：GUI 转发为前端事件，CLI 输出到终端
+pub(crate) trait StreamSink {
+    /// requesting / connected / receiving
+    fn phase(&mut self, phase: &str);
+    fn keepalive(&mut self);
+    /// 一帧 data：JSON 原样交出，非 JSON 文本包装为 delta，`[DONE]` 转为 done
+    fn event(&mut self, event: serde_json::Value);
+    fn error(&mut self, message: String);
+    fn auth_expired(&mut self);
+    /// 被新请求顶替或被主动取消
+    fn cancelled(&mut self);
+}
+
+/// 把 SSE 事件转发给前端的 sink（所有 stream 命令共用）
+pub(crate) struct EmitSink<'a> {
+    app: &'a AppHandle,
+    channel: &'static str,
+    done_on_cancel: bool,
+    usage: Option<UsageContext>,
+}
+
+impl<'a> EmitSink<'a> {
+    pub(crate) fn new(app: &'a AppHandle, channel: &'static str) -> Self {
+        Self {
+            app,
+            channel,
+            done_on_cancel: false,
+            usage: None,
+        }
+    }
+
+    /// done 事件中的 token 用量记入台账时使用的会话 / 角色
+    fn with_usage(mut self, session_id: &str, role: Option<&str>) -> Self {
+        self.usage = Some(UsageContext {
+            session_id: Some(session_id.to_string()),
+            role: role.map(str::to_string),
+        });
+        self
+    }
+
+    /// 取消时补发 done，让前端结束“生成中”状态
+    fn done_on_cancel(mut self) -> Self {
+        self.done_on_cancel = true;
+        self
+    }
+}
+
+impl StreamSink for EmitSink<'_> {
+    fn phase(&mut self, phase: &str) {
+        emit_stream_phase(self.app, self.channel, phase);
+    }
+
+    fn keepalive(&mut self) {
+        // 发送 keepalive 心跳事件到前端（用于重置心跳计时器）
+        let _ = self
+            .app
+            .emit(self.channel, serde_json::json!({ "type": "keepalive" }));
+    }
+
+    fn event(&mut self, event: serde_json::Value) {
+        usage_ledger::record_stream_event(&event, self.usage.as_ref());
+        if self.channel == GROUP_MESSAGE_CHANNEL {
+            message_graph::ingest_event(&event);
+            notifier::on_group_event(self.app, &event);
+        }
+        let _ = self.app.emit(self.channel, event);
+    }
+
+    fn error(&mut self, message: String) {
+        emit_stream_error(self.app, self.channel, message);
+    }
+
+    fn auth_expired(&mut self) {
+        emit_auth_expired(self.app);
+    }
+
+    fn cancelled(&mut self) {
+        if self.done_on_cancel {
+            let _ = self
+                .app
+                .emit(self.channel, serde_json::json!({ "type": "done" }));
+        }
+    }
+}
+
+fn dispatch_sse_text(
+    sink: &mut impl StreamSink,
+    buf: &mut String,
+    incoming: &str,
+    saw_any_data: &mut bool,
+) {
+    for frame in drain_sse_frames(buf, incoming) {
+        let data = match frame {
+            SseFrame::KeepAlive => {
+                sink.keepalive();
+                continue;
+            }
+            SseFrame::Data(data) => data,
+        };
+
         if !*saw_any_data {
             *saw_any_data = true;
-            emit_stream_phase(app, channel, "receiving");
+            sink.phase("receiving");
         }
 
         if data == "[DONE]" {
-            let _ = app.emit(channel, serde_json::json!({ "type": "done" }));
+            sink.event(serde_json::json!({ "type": "done" }));
             continue;
         }
 
         // 默认期望 data 是 JSON（后端会发 {"type":"delta"...}），但这里要容错
         match serde_json::from_str::<serde_json::Value>(&data) {
-            Ok(event) => {
-                let _ = app.emit(channel, event);
+            Ok(event) => sink.event(event),
+            Err(_) => sink.event(serde_json::json!({
+                "type": "delta",
+                "content": data
+            })),
+        }
+    }
+}
+
+/// 发起 SSE 请求；access 过期时 refresh 后重试一次，refresh 失败则通知 sink 并返回原响应
+pub(crate) async fn open_event_stream<F>(
+    build: F,
+    sink: &mut impl StreamSink,
+) -> Result<reqwest::Response, String>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let authed = || {
+        let mut req = build();
+        if let Some(token) = api_client::get_auth_token() {
+            req = req.header("Authorization", format!("Bearer {}", token));
+        }
+        req
+    };
+
+    let response = api_client::send_streaming(authed())
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return Ok(response);
+    }
+
+    if ApiClient::new().refresh_auth().await.unwrap_or(false) {
+        return api_client::send_streaming(authed())
+            .await
+            .map_err(|e| format!("Request failed: {}", e));
+    }
+    sink.auth_expired();
+    Ok(response)
+}
+
+/// 读取 SSE 响应直到结束或 token 被取消；非 2xx 作为错误交给 sink
+pub(crate) async fn pump_event_stream(
+    response: reqwest::Response,
+    token: &CancellationToken,
+    sink: &mut impl StreamSink,
+) {
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        sink.error(format!("HTTP {}: {}", status, body));
+        return;
+    }
+
+    let mut stream = response.bytes_stream();
+    let mut sse_buf = String::new();
+    let mut saw_any_data = false;
+
+    loop {
+        tokio::select! {
+            chunk = stream.next() => {
+                match chunk {
+                    Some(Ok(bytes)) => {
+                        let text = String::from_utf8_lossy(&bytes);
+                        dispatch_sse_text(sink, &mut sse_buf, &text, &mut saw_any_data);
+                    }
+                    Some(Err(e)) => {
+                        sink.error(format!("Stream error: {}", e));
+                        break;
+                    }
+                    None => break,
+                }
             }
-            Err(_) => {
-                let _ = app.emit(
-                    channel,
-                    serde_json::json!({
-                        "type": "delta",
-                        "content": data
-                    }),
-                );
+            _ = token.cancelled() => {
+                sink.cancelled();
+                break;
             }
         }
     }
 }
 
+/// GET 一个 SSE 接口（订阅类，无 requesting/connected 阶段事件）
+async fn get_event_stream(
+    url: &str,
+    token: &CancellationToken,
+    sink: &mut impl StreamSink,
+) -> Result<(), String> {
+    let client = api_client::build_streaming_client(&api_client::get_api_base_url());
+    let response = open_event_stream(
+        || client.get(url).header("Accept", "text/event-stream"),
+        sink,
+    )
+    .await?;
+    pump_event_stream(response, token, sink).await;
+    Ok(())
+}
+
+/// POST 一个 SSE 接口：requesting → connected → 事件流
+async fn post_event_stream<B: Serialize>(
+    url: &str,
+    body: &B,
+    token: &CancellationToken,
+    sink: &mut impl StreamSink,
+) -> Result<(), String> {
+    let client = api_client::build_streaming_client(&api_client::get_api_base_url());
+    sink.phase("requesting");
+    let response = open_event_stream(
+        || {
+            client
+                .post(url)
+                .header("Accept", "text/event-stream")
+                .header("Content-Type", "application/json")
+                .json(body)
+        },
+        sink,
+    )
+    .await?;
+    sink.phase("connected");
+    pump_event_stream(response, token, sink).await;
+    Ok(())
+}
+
+/// 发送消息并以 SSE 接收回复（send_message 与 CLI 共用）
+pub(crate) async fn stream_message(
+    session_id: &str,
+    request: &SendMessageRequest,
+    token: &CancellationToken,
+    sink: &mut impl StreamSink,
+) -> Result<(), String> {
+    let url = format!(
+        "{}/api/v1/sessions/{}/messages",
+        api_client::get_api_base_url(),
+        session_id
+    );
+    post_event_stream(&url, request, token, sink).await
+}
+
+/// 订阅 chat run 的事件流（subscribe_chat_run 与 CLI 共用）
+pub(crate) async fn stream_chat_run(
+    run_id: &str,
+    after_seq: i64,
+    token: &CancellationToken,
+    sink: &mut impl StreamSink,
+) -> Result<(), String> {
+    let url = format!(
+        "{}/api/v1/chat-runs/{}/stream?afterSeq={}",
+        api_client::get_api_base_url(),
+        run_id,
+        after_seq.max(0)
+    );
+    get_event_stream(&url, token, sink).await
+}
+
 #[derive(Serialize)]
 struct SwitchRoleRequest {
     role: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Default)]
 #[serde(rename_all = "camelCase")]
-struct SendMessageRequest {
-    content: String,
-    role: Option<String>,
+pub(crate) struct SendMessageRequest {
+    pub(crate) content: String,
+    pub(crate) role: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    prompt_key: Option<String>,
+    pub(crate) prompt_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    attachment_ids: Option<Vec<String>>,
+    pub(crate) attachment_ids: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    skip_ai_reply: Option<bool>,
+    pub(crate) skip_ai_reply: Option<bool>,
 }
 
 #[derive(Serialize, serde::Deserialize)]
@@ -248,16 +534,16 @@ pub async fn get_message_history(
     client.get(&path).await
 }
 
-#[command]
-pub async fn get_group_message_history(
-    group_id: String,
-    limit: Option<i32>,
-    before: Option<String>,
+/// 按角色过滤时每页拉取的条数（服务端不支持角色过滤，只能本地筛）
+const ROLE_FILTER_PAGE_SIZE: i32 = 200;
+
+fn group_history_path(
+    group_id: &str,
+    limit: i32,
+    before: Option<&str>,
     after_seq: Option<i64>,
     before_seq: Option<i64>,
-) -> Result<ApiResponse<Vec<MessageHistoryItem>>, String> {
-    let client = ApiClient::new();
-    let limit = limit.unwrap_or(50).clamp(1, 200);
+) -> String {
     let mut path = format!("/groups/{}/messages?limit={}", group_id, limit);
     // 优先级：afterSeq > beforeSeq > before（timestamp）
     if let Some(a) = after_seq {
@@ -279,7 +565,92 @@ pub async fn get_group_message_history(
             path.push_str(&bb);
         }
     }
-    client.get(&path).await
+    path
+}
+
+async fn fetch_group_page(
+    client: &ApiClient,
+    group_id: &str,
+    path: &str,
+) -> Result<ApiResponse<Vec<MessageHistoryItem>>, String> {
+    let resp: ApiResponse<Vec<MessageHistoryItem>> = client.get(path).await?;
+    if let Some(items) = &resp.data {
+        message_graph::ingest(group_id, items);
+        usage_ledger::record_messages(group_id, items);
+    }
+    Ok(resp)
+}
+
+#[command]
+pub async fn get_group_message_history(
+    group_id: String,
+    limit: Option<i32>,
+    before: Option<String>,
+    after_seq: Option<i64>,
+    before_seq: Option<i64>,
+    view_role: Option<String>,
+    sender_role: Option<String>,
+) -> Result<ApiResponse<Vec<MessageHistoryItem>>, String> {
+    let client = ApiClient::new();
+    let filter = RoleFilter::new(view_role.as_deref(), sender_role.as_deref());
+    let limit = limit.unwrap_or(50).clamp(1, 200);
+    if filter.is_empty() {
+        let path = group_history_path(&group_id, limit, before.as_deref(), after_seq, before_seq);
+        return fetch_group_page(&client, &group_id, &path).await;
+    }
+
+    // 按 groupSeq 连续翻页，直到凑满 limit 条或历史取完：
+    // afterSeq 向后翻（保留最早的 limit 条），否则向前翻（保留最新的 limit 条）
+    let forward = after_seq.is_some_and(|a| a > 0);
+    let limit = limit as usize;
+    let (mut after_seq, mut before_seq, mut before) = (after_seq, before_seq, before);
+    let mut matched: Vec<MessageHistoryItem> = Vec::new();
+    loop {
+        let path = group_history_path(
+            &group_id,
+            ROLE_FILTER_PAGE_SIZE,
+            before.take().as_deref(),
+            after_seq,
+            before_seq,
+        );
+        let page = match fetch_group_page(&client, &group_id, &path).await? {
+            ApiResponse {
+                success: true,
+                data: Some(page),
+                ..
+            } => page,
+            failed => return Ok(failed),
+        };
+        let exhausted = page.len() < ROLE_FILTER_PAGE_SIZE as usize;
+        let seqs = page.iter().filter_map(|m| m.group_seq);
+        let cursor = if forward { seqs.max() } else { seqs.min() };
+        let page = filter.apply(page);
+        if forward {
+            matched.extend(page);
+        } else {
+            matched.splice(0..0, page);
+        }
+        match cursor {
+            Some(seq) if !exhausted && matched.len() < limit => {
+                if forward {
+                    after_seq = Some(seq);
+                } else {
+                    before_seq = Some(seq);
+                }
+            }
+            _ => break,
+        }
+    }
+    if forward {
+        matched.truncate(limit);
+    } else {
+        matched.drain(..matched.len().saturating_sub(limit));
+    }
+    Ok(ApiResponse {
+        success: true,
+        data: Some(matched),
+        error: None,
+    })
 }
 
 #[command]
@@ -294,87 +665,18 @@ pub async fn subscribe_group_messages(
         return Ok(());
     }
 
-    let base_url = api_client::get_api_base_url();
     let url = format!(
         "{}/api/v1/groups/{}/messages/stream?afterSeq={}",
-        base_url,
+        api_client::get_api_base_url(),
         gid,
         after_seq.unwrap_or(0).max(0)
     );
-
-    let client = api_client::build_streaming_client(&base_url);
     let token = cancel.new_group_token();
 
     tauri::async_runtime::spawn(async move {
-        let mut req = client.get(&url).header("Accept", "text/event-stream");
-        if let Some(token) = api_client::get_auth_token() {
-            req = req.header("Authorization", format!("Bearer {}", token));
-        }
-
-        let mut response = match req.send().await {
-            Ok(r) => r,
-            Err(e) => {
-                emit_stream_error(&app, "group-message", format!("Request failed: {}", e));
-                return;
-            }
-        };
-
-        // access 过期：尝试 refresh 后重试一次
-        if response.status() == StatusCode::UNAUTHORIZED {
-            let ok = ApiClient::new().refresh_auth().await.unwrap_or(false);
-            if ok {
-                let mut retry = client.get(&url).header("Accept", "text/event-stream");
-                if let Some(token) = api_client::get_auth_token() {
-                    retry = retry.header("Authorization", format!("Bearer {}", token));
-                }
-                response = match retry.send().await {
-                    Ok(r) => r,
-                    Err(e) => {
-                        emit_stream_error(&app, "group-message", format!("Request failed: {}", e));
-                        return;
-                    }
-                };
-            } else {
-                emit_auth_expired(&app);
-            }
-        }
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            emit_stream_error(&app, "group-message", format!("HTTP {}: {}", status, body));
-            return;
-        }
-
-        let mut stream = response.bytes_stream();
-        let mut sse_buf = String::new();
-        let mut saw_any_data = false;
-
-        loop {
-            tokio::select! {
-                chunk = stream.next() => {
-                    match chunk {
-                        Some(Ok(bytes)) => {
-                            let text = String::from_utf8_lossy(&bytes);
-                            handle_sse_text(
-                                &app,
-                                "group-message",
-                                &mut sse_buf,
-                                &text,
-                                &mut saw_any_data,
-                            );
-                        }
-                        Some(Err(e)) => {
-                            emit_stream_error(&app, "group-message", format!("Stream error: {}", e));
-                            break;
-                        }
-                        None => break,
-                    }
-                }
-                _ = token.cancelled() => {
-                    break;
-                }
-            }
+        let mut sink = EmitSink::new(&app, GROUP_MESSAGE_CHANNEL);
+        if let Err(e) = get_event_stream(&url, &token, &mut sink).await {
+            sink.error(e);
         }
     });
 
@@ -385,13 +687,23 @@ pub async fn subscribe_group_messages(
 pub async fn switch_role(
     session_id: String,
     role: String,
+    group_id: Option<String>,
 ) -> Result<ApiResponse<SwitchRoleResponse>, String> {
     let client = ApiClient::new();
     let request = SwitchRoleRequest { role };
 
-    client
+    let resp: ApiResponse<SwitchRoleResponse> = client
         .put(&format!("/sessions/{}/role", session_id), &request)
-        .await
+        .await?;
+    // 记住该群组上次使用的角色，重新打开群组时恢复
+    if let (Some(gid), Some(data)) = (group_id.as_deref(), resp.data.as_ref()) {
+        if resp.success {
+            if let Err(e) = role_views::remember_role(gid.trim(), &data.current_role) {
+                tracing::warn!("failed to remember group role: {}", e);
+            }
+        }
+    }
+    Ok(resp)
 }
 
 #[command]
@@ -406,10 +718,6 @@ pub async fn send_message(
     attachment_ids: Option<Vec<String>>,
     skip_ai_reply: Option<bool>,
 ) -> Result<(), String> {
-    let base_url = api_client::get_api_base_url();
-    let url = format!("{}/api/v1/sessions/{}/messages", base_url, session_id);
-
-    let client = api_client::build_streaming_client(&base_url);
     let request = SendMessageRequest {
         content,
         role,
@@ -419,83 +727,10 @@ pub async fn send_message(
     };
 
     let token = cancel.new_message_token();
-    emit_stream_phase(&app, "message-chunk", "requesting");
-    let mut req = client
-        .post(&url)
-        .header("Accept", "text/event-stream")
-        .header("Content-Type", "application/json")
-        .json(&request);
-
-    if let Some(token) = api_client::get_auth_token() {
-        req = req.header("Authorization", format!("Bearer {}", token));
-    }
-
-    let mut response = req
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
-
-    // access 过期：尝试 refresh 后重试一次
-    if response.status() == StatusCode::UNAUTHORIZED {
-        let ok = ApiClient::new().refresh_auth().await.unwrap_or(false);
-        if ok {
-            let mut retry = client
-                .post(&url)
-                .header("Accept", "text/event-stream")
-                .header("Content-Type", "application/json")
-                .json(&request);
-            if let Some(token) = api_client::get_auth_token() {
-                retry = retry.header("Authorization", format!("Bearer {}", token));
-            }
-            response = retry
-                .send()
-                .await
-                .map_err(|e| format!("Request failed: {}", e))?;
-        } else {
-            emit_auth_expired(&app);
-        }
-    }
-    emit_stream_phase(&app, "message-chunk", "connected");
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        emit_stream_error(&app, "message-chunk", format!("HTTP {}: {}", status, body));
-        return Ok(());
-    }
-
-    let mut stream = response.bytes_stream();
-    let mut sse_buf = String::new();
-    let mut saw_any_data = false;
-
-    loop {
-        tokio::select! {
-            chunk = stream.next() => {
-                match chunk {
-                    Some(Ok(bytes)) => {
-                        let text = String::from_utf8_lossy(&bytes);
-                        handle_sse_text(
-                            &app,
-                            "message-chunk",
-                            &mut sse_buf,
-                            &text,
-                            &mut saw_any_data,
-                        );
-                    }
-                    Some(Err(e)) => {
-                        emit_stream_error(&app, "message-chunk", format!("Stream error: {}", e));
-                        break;
-                    }
-                    None => break,
-                }
-            }
-            _ = token.cancelled() => {
-                let _ = app.emit("message-chunk", serde_json::json!({ "type": "done" }));
-                break;
-            }
-        }
-    }
-
-    Ok(())
+    let mut sink = EmitSink::new(&app, "message-chunk")
+        .done_on_cancel()
+        .with_usage(&session_id, request.role.as_deref());
+    stream_message(&session_id, &request, &token, &mut sink).await
 }
 
 #[command]
@@ -532,87 +767,12 @@ pub async fn subscribe_chat_run(
         return Ok(());
     }
 
-    let base_url = api_client::get_api_base_url();
-    let url = format!(
-        "{}/api/v1/chat-runs/{}/stream?afterSeq={}",
-        base_url,
-        rid,
-        after_seq.unwrap_or(0).max(0)
-    );
-
-    let client = api_client::build_streaming_client(&base_url);
     let token = cancel.new_message_token();
 
     tauri::async_runtime::spawn(async move {
-        let mut req = client.get(&url).header("Accept", "text/event-stream");
-        if let Some(token) = api_client::get_auth_token() {
-            req = req.header("Authorization", format!("Bearer {}", token));
-        }
-
-        let mut response = match req.send().await {
-            Ok(r) => r,
-            Err(e) => {
-                emit_stream_error(&app, "message-chunk", format!("Request failed: {}", e));
-                return;
-            }
-        };
-
-        // access 过期：尝试 refresh 后重试一次
-        if response.status() == StatusCode::UNAUTHORIZED {
-            let ok = ApiClient::new().refresh_auth().await.unwrap_or(false);
-            if ok {
-                let mut retry = client.get(&url).header("Accept", "text/event-stream");
-                if let Some(token) = api_client::get_auth_token() {
-                    retry = retry.header("Authorization", format!("Bearer {}", token));
-                }
-                response = match retry.send().await {
-                    Ok(r) => r,
-                    Err(e) => {
-                        emit_stream_error(&app, "message-chunk", format!("Request failed: {}", e));
-                        return;
-                    }
-                };
-            } else {
-                emit_auth_expired(&app);
-            }
-        }
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            emit_stream_error(&app, "message-chunk", format!("HTTP {}: {}", status, body));
-            return;
-        }
-
-        let mut stream = response.bytes_stream();
-        let mut sse_buf = String::new();
-        let mut saw_any_data = false;
-
-        loop {
-            tokio::select! {
-                chunk = stream.next() => {
-                    match chunk {
-                        Some(Ok(bytes)) => {
-                            let text = String::from_utf8_lossy(&bytes);
-                            handle_sse_text(
-                                &app,
-                                "message-chunk",
-                                &mut sse_buf,
-                                &text,
-                                &mut saw_any_data,
-                            );
-                        }
-                        Some(Err(e)) => {
-                            emit_stream_error(&app, "message-chunk", format!("Stream error: {}", e));
-                            break;
-                        }
-                        None => break,
-                    }
-                }
-                _ = token.cancelled() => {
-                    break;
-                }
-            }
+        let mut sink = EmitSink::new(&app, "message-chunk");
+        if let Err(e) = stream_chat_run(&rid, after_seq.unwrap_or(0), &token, &mut sink).await {
+            sink.error(e);
         }
     });
 
@@ -652,17 +812,17 @@ pub async fn resend_message(
     attachment_ids: Option<Vec<String>>,
     skip_ai_reply: Option<bool>,
 ) -> Result<(), String> {
-    let base_url = api_client::get_api_base_url();
     let mid = message_id.trim().to_string();
     if mid.is_empty() {
         return Ok(());
     }
     let url = format!(
         "{}/api/v1/sessions/{}/messages/{}/resend",
-        base_url, session_id, mid
+        api_client::get_api_base_url(),
+        session_id,
+        mid
     );
 
-    let client = api_client::build_streaming_client(&base_url);
     let request = SendMessageRequest {
         content,
         role,
@@ -672,83 +832,10 @@ pub async fn resend_message(
     };
 
     let token = cancel.new_message_token();
-    emit_stream_phase(&app, "message-chunk", "requesting");
-    let mut req = client
-        .post(&url)
-        .header("Accept", "text/event-stream")
-        .header("Content-Type", "application/json")
-        .json(&request);
-
-    if let Some(token) = api_client::get_auth_token() {
-        req = req.header("Authorization", format!("Bearer {}", token));
-    }
-
-    let mut response = req
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
-
-    // access 过期：尝试 refresh 后重试一次
-    if response.status() == StatusCode::UNAUTHORIZED {
-        let ok = ApiClient::new().refresh_auth().await.unwrap_or(false);
-        if ok {
-            let mut retry = client
-                .post(&url)
-                .header("Accept", "text/event-stream")
-                .header("Content-Type", "application/json")
-                .json(&request);
-            if let Some(token) = api_client::get_auth_token() {
-                retry = retry.header("Authorization", format!("Bearer {}", token));
-            }
-            response = retry
-                .send()
-                .await
-                .map_err(|e| format!("Request failed: {}", e))?;
-        } else {
-            emit_auth_expired(&app);
-        }
-    }
-    emit_stream_phase(&app, "message-chunk", "connected");
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        emit_stream_error(&app, "message-chunk", format!("HTTP {}: {}", status, body));
-        return Ok(());
-    }
-
-    let mut stream = response.bytes_stream();
-    let mut sse_buf = String::new();
-    let mut saw_any_data = false;
-
-    loop {
-        tokio::select! {
-            chunk = stream.next() => {
-                match chunk {
-                    Some(Ok(bytes)) => {
-                        let text = String::from_utf8_lossy(&bytes);
-                        handle_sse_text(
-                            &app,
-                            "message-chunk",
-                            &mut sse_buf,
-                            &text,
-                            &mut saw_any_data,
-                        );
-                    }
-                    Some(Err(e)) => {
-                        emit_stream_error(&app, "message-chunk", format!("Stream error: {}", e));
-                        break;
-                    }
-                    None => break,
-                }
-            }
-            _ = token.cancelled() => {
-                let _ = app.emit("message-chunk", serde_json::json!({ "type": "done" }));
-                break;
-            }
-        }
-    }
-
-    Ok(())
+    let mut sink = EmitSink::new(&app, "message-chunk")
+        .done_on_cancel()
+        .with_usage(&session_id, request.role.as_deref());
+    post_event_stream(&url, &request, &token, &mut sink).await
 }
 
 #[command]
@@ -760,10 +847,12 @@ pub async fn preview_ask_in_section(
     heading_title: Option<String>,
     question: String,
 ) -> Result<(), String> {
-    let base_url = api_client::get_api_base_url();
-    let url = format!("{}/api/v1/sessions/{}/preview-ask", base_url, session_id);
+    let url = format!(
+        "{}/api/v1/sessions/{}/preview-ask",
+        api_client::get_api_base_url(),
+        session_id
+    );
 
-    let client = api_client::build_streaming_client(&base_url);
     let request = PreviewAskRequest {
         question,
         heading_id,
@@ -771,84 +860,6 @@ pub async fn preview_ask_in_section(
     };
 
     let token = cancel.new_preview_token();
-    emit_stream_phase(&app, "preview-ask-chunk", "requesting");
-    let mut req = client
-        .post(&url)
-        .header("Accept", "text/event-stream")
-        .header("Content-Type", "application/json")
-        .json(&request);
-
-    if let Some(token) = api_client::get_auth_token() {
-        req = req.header("Authorization", format!("Bearer {}", token));
-    }
-
-    let mut response = req
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
-
-    if response.status() == StatusCode::UNAUTHORIZED {
-        let ok = ApiClient::new().refresh_auth().await.unwrap_or(false);
-        if ok {
-            let mut retry = client
-                .post(&url)
-                .header("Accept", "text/event-stream")
-                .header("Content-Type", "application/json")
-                .json(&request);
-            if let Some(token) = api_client::get_auth_token() {
-                retry = retry.header("Authorization", format!("Bearer {}", token));
-            }
-            response = retry
-                .send()
-                .await
-                .map_err(|e| format!("Request failed: {}", e))?;
-        } else {
-            emit_auth_expired(&app);
-        }
-    }
-    emit_stream_phase(&app, "preview-ask-chunk", "connected");
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        emit_stream_error(
-            &app,
-            "preview-ask-chunk",
-            format!("HTTP {}: {}", status, body),
-        );
-        return Ok(());
-    }
-
-    let mut stream = response.bytes_stream();
-    let mut sse_buf = String::new();
-    let mut saw_any_data = false;
-
-    loop {
-        tokio::select! {
-            chunk = stream.next() => {
-                match chunk {
-                    Some(Ok(bytes)) => {
-                        let text = String::from_utf8_lossy(&bytes);
-                        handle_sse_text(
-                            &app,
-                            "preview-ask-chunk",
-                            &mut sse_buf,
-                            &text,
-                            &mut saw_any_data,
-                        );
-                    }
-                    Some(Err(e)) => {
-                        emit_stream_error(&app, "preview-ask-chunk", format!("Stream error: {}", e));
-                        break;
-                    }
-                    None => break,
-                }
-            }
-            _ = token.cancelled() => {
-                let _ = app.emit("preview-ask-chunk", serde_json::json!({ "type": "done" }));
-                break;
-            }
-        }
-    }
-
-    Ok(())
+    let mut sink = EmitSink::new(&app, "preview-ask-chunk").done_on_cancel();
+    post_event_stream(&url, &request, &token, &mut sink).await
 }
@@ -0,0 +1,223 @@
+use futures::future::join_all;
+use tauri::{command, AppHandle};
+
+use super::config::{load_config_from_file, save_config_to_file};
+use super::document::get_document;
+use super::session::get_session;
+use crate::models::{ApiResponse, SessionInfo};
+use crate::services::session_documents::{
+    self, ContextBudgetSettings, SessionDocumentBudget, SessionDocumentLayout,
+};
+
+async fn load_session(session_id: &str) -> Result<SessionInfo, String> {
+    match get_session(session_id.to_string()).await? {
+        ApiResponse {
+            success: true,
+            data: Some(session),
+            ..
+        } => Ok(session),
+        ApiResponse { error, .. } => Err(error
+            .map(|e| e.message)
+            .unwrap_or_else(|| "获取会话失败".to_string())),
+    }
+}
+
+pub(crate) async fn load_budget(session_id: &str) -> Result<SessionDocumentBudget, String> {
+    let session = load_session(session_id).await?;
+    let ids = session_documents::session_document_ids(&session);
+    let documents = join_all(ids.iter().map(|id| get_document(id.clone())))
+        .await
+        .into_iter()
+        .filter_map(|r| r.ok().and_then(|r| r.data))
+        .collect::<Vec<_>>();
+    Ok(session_documents::build_budget(
+        &session,
+        &documents,
+        &session_documents::get_layout(session_id),
+        &session_documents::get_settings(),
+    ))
+}
+
+/// 获取会话文档的上下文预算（各文档 token 估算、类型、是否纳入、是否超限）
+#[command]
+pub async fn get_session_document_budget(
+    session_id: String,
+) -> Result<SessionDocumentBudget, String> {
+    load_budget(session_id.trim()).await
+}
+
+/// 调整文档优先级顺序（仅用于本机预算估算，见 SessionDocumentBudget::view_only）
+#[command]
+pub async fn reorder_session_documents(
+    session_id: String,
+    document_ids: Vec<String>,
+) -> Result<SessionDocumentBudget, String> {
+    let sid = session_id.trim();
+    let session = load_session(sid).await?;
+    let ids = session_documents::session_document_ids(&session);
+    let mut layout = session_documents::get_layout(sid);
+    layout.order = session_documents::ordered_ids(
+        &ids,
+        &SessionDocumentLayout {
+            order: document_ids,
+            excluded: Vec::new(),
+        },
+    );
+    session_documents::set_layout(sid, layout)?;
+    load_budget(sid).await
+}
+
+/// 在预算估算中移入 / 移出文档（文档仍保留在会话中，发送时服务端照常使用）；至少保留一个文档
+#[command]
+pub async fn set_session_document_included(
+    session_id: String,
+    document_id: String,
+    included: bool,
+) -> Result<SessionDocumentBudget, String> {
+    let sid = session_id.trim();
+    let session = load_session(sid).await?;
+    let ids = session_documents::session_document_ids(&session);
+    if !ids.contains(&document_id) {
+        return Err("文档不在当前会话中".to_string());
+    }
+    let mut layout = session_documents::get_layout(sid);
+    layout
+        .excluded
+        .retain(|id| id != &document_id && ids.contains(id));
+    if !included {
+        layout.excluded.push(document_id);
+        if ids.iter().all(|id| layout.excluded.contains(id)) {
+            return Err("至少保留一个文档在上下文中".to_string());
+        }
+    }
+    session_documents::set_layout(sid, layout)?;
+    load_budget(sid).await
+}
+
+/// 获取上下文预算设置
+#[command]
+pub async fn get_context_budget_settings() -> Result<ContextBudgetSettings, String> {
+    Ok(session_documents::get_settings())
+}
+
+/// 保存上下文预算设置（立即生效并写入 config.json）
+#[command]
+pub async fn save_context_budget_settings(
+    app: AppHandle,
+    settings: ContextBudgetSettings,
+) -> Result<(), String> {
+    if settings.model_token_limit < 1024 {
+        return Err("模型上限不能小于 1024 tokens".to_string());
+    }
+    if !(0.1..=1.0).contains(&settings.warn_ratio) {
+        return Err("提示阈值需在 0.1 - 1.0 之间".to_string());
+    }
+    let mut cfg = load_config_from_file(&app)?;
+    cfg.context_budget = Some(settings.clone());
+    save_config_to_file(&app, &cfg)?;
+    session_documents::set_settings(settings);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{signed_in, MockResponse};
+    use crate::services::session_documents;
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn session_document_budget_reorders_and_excludes() {
+        let (_guard, server) = signed_in().await;
+        let session = json!({
+            "sessionId": "session-docs",
+            "groupId": "group-1",
+            "documentId": "doc-1",
+            "documentIds": ["doc-1", "doc-2", "doc-3"],
+            "documentMetas": [
+                { "documentId": "doc-1", "documentType": "prd" },
+                { "documentId": "doc-2", "documentType": "reference" }
+            ],
+            "currentRole": "PM",
+            "mode": "QA"
+        });
+        for (id, tokens) in [("doc-1", 60_000), ("doc-2", 50_000), ("doc-3", 30_000)] {
+            server.script(
+                Method::GET,
+                &format!("/api/v1/documents/{}", id),
+                MockResponse::ok(json!({
+                    "id": id, "title": id, "charCount": tokens * 2, "tokenEstimate": tokens
+                })),
+            );
+        }
+        server.script(
+            Method::GET,
+            "/api/v1/sessions/session-docs",
+            MockResponse::ok(session.clone()),
+        );
+        session_documents::set_settings(session_documents::ContextBudgetSettings {
+            model_token_limit: 100_000,
+            warn_ratio: 0.8,
+        });
+
+        let budget = load_budget("session-docs").await.expect("budget");
+        assert_eq!(budget.included_tokens, 140_000);
+        assert_eq!(budget.level, session_documents::BudgetLevel::Over);
+        assert!(budget.documents[1].exceeds_budget);
+        assert_eq!(
+            budget.documents[1].document_type.as_deref(),
+            Some("reference")
+        );
+        assert!(budget.view_only);
+        let session_ids = vec![
+            "doc-1".to_string(),
+            "doc-2".to_string(),
+            "doc-3".to_string(),
+        ];
+        assert_eq!(
+            session_documents::context_document_ids("session-docs", &session_ids),
+            None
+        );
+
+        session_documents::set_layout(
+            "session-docs",
+            session_documents::SessionDocumentLayout {
+                order: vec!["doc-3".into()],
+                excluded: vec!["doc-2".into()],
+            },
+        )
+        .expect("layout");
+        server.script(
+            Method::GET,
+            "/api/v1/sessions/session-docs",
+            MockResponse::ok(session.clone()),
+        );
+        for (id, tokens) in [("doc-1", 60_000), ("doc-2", 50_000), ("doc-3", 30_000)] {
+            server.script(
+                Method::GET,
+                &format!("/api/v1/documents/{}", id),
+                MockResponse::ok(json!({
+                    "id": id, "title": id, "charCount": tokens * 2, "tokenEstimate": tokens
+                })),
+            );
+        }
+        let budget = load_budget("session-docs").await.expect("budget");
+        let order: Vec<_> = budget
+            .documents
+            .iter()
+            .map(|d| d.document_id.as_str())
+            .collect();
+        assert_eq!(order, ["doc-3", "doc-1", "doc-2"]);
+        assert_eq!(budget.included_tokens, 90_000);
+        assert_eq!(budget.level, session_documents::BudgetLevel::Near);
+        assert_eq!(budget.documents[2].priority, None);
+        assert_eq!(
+            session_documents::context_document_ids("session-docs", &session_ids),
+            Some(vec!["doc-3".to_string(), "doc-1".to_string()])
+        );
+
+        session_documents::set_layout("session-docs", Default::default()).expect("reset");
+        session_documents::set_settings(Default::default());
+    }
+}
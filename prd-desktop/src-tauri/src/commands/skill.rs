@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use tauri::command;
 
 use crate::models::ApiResponse;
-use crate::services::ApiClient;
+use crate::services::{usage_ledger, ApiClient};
 
 // Re-export for dialog access
 use tauri_plugin_dialog::DialogExt;
@@ -151,12 +151,17 @@ pub async fn execute_skill(
         context_scope_override: None,
         output_mode_override: None,
     };
-    client
+    let resp: ApiResponse<SkillExecuteResponse> = client
         .post(
             &format!("/api/prd-agent/skills/{}/execute", skill_key),
             &request,
         )
-        .await
+        .await?;
+    // 技能运行的回答在消息上不带技能信息，记下来用于按技能统计用量
+    if let Some(data) = resp.data.as_ref().filter(|_| resp.success) {
+        usage_ledger::note_skill_run(&data.assistant_message_id, &skill_key);
+    }
+    Ok(resp)
 }
 
 /// 创建个人技能
@@ -63,7 +63,7 @@ pub struct AcceleratedUpdateResult {
     pub github_latency_ms: Option<u64>,
 }
 
-fn get_updater_target_triple() -> &'static str {
+pub(crate) fn get_updater_target_triple() -> &'static str {
     if cfg!(target_os = "macos") && cfg!(target_arch = "aarch64") {
         "aarch64-apple-darwin"
     } else if cfg!(target_os = "macos") && cfg!(target_arch = "x86_64") {
@@ -0,0 +1,187 @@
+use chrono::Local;
+use tauri::{command, AppHandle};
+
+use super::config::{load_config_from_file, save_config_to_file};
+use super::defect_export::save_with_dialog;
+use crate::services::usage_ledger::{self, UsageGroupBy, UsagePricing, UsageRange, UsageReport};
+
+fn validate_range(range: &UsageRange) -> Result<(), String> {
+    match (range.from_ms, range.to_ms) {
+        (Some(from), Some(to)) if from >= to => Err("结束时间需晚于开始时间".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// token 用量报表（本机台账）；range 为空表示全部，group_by 默认按天
+#[command]
+pub async fn get_usage_report(
+    range: Option<UsageRange>,
+    group_by: Option<UsageGroupBy>,
+) -> Result<UsageReport, String> {
+    let range = range.unwrap_or_default();
+    validate_range(&range)?;
+    Ok(usage_ledger::report(
+        range,
+        group_by.unwrap_or(UsageGroupBy::Day),
+    ))
+}
+
+/// 导出用量报表为 CSV，通过系统保存对话框选择路径；用户取消时返回 None
+#[command]
+pub async fn export_usage_csv(
+    app: AppHandle,
+    range: Option<UsageRange>,
+    group_by: Option<UsageGroupBy>,
+) -> Result<Option<String>, String> {
+    let report = get_usage_report(range, group_by).await?;
+    // 带 BOM，Excel 直接打开时中文不乱码
+    let content = format!("\u{feff}{}", usage_ledger::to_csv(&report)?);
+
+    let default_name = format!("token-usage-{}.csv", Local::now().format("%Y%m%d"));
+    save_with_dialog(&app, &default_name, "CSV", "csv", content.as_bytes())
+}
+
+/// 获取价格表（未配置时为 None，报表不计算费用）
+#[command]
+pub async fn get_usage_pricing() -> Result<Option<UsagePricing>, String> {
+    Ok(usage_ledger::get_pricing())
+}
+
+/// 保存价格表（立即生效并写入 config.json）；传 None 清除
+#[command]
+pub async fn save_usage_pricing(
+    app: AppHandle,
+    pricing: Option<UsagePricing>,
+) -> Result<(), String> {
+    if let Some(p) = &pricing {
+        let prices = std::iter::once(&p.default_price).chain(p.skills.values());
+        if prices
+            .flat_map(|x| [x.input_per_million, x.output_per_million])
+            .any(|v| !v.is_finite() || v < 0.0)
+        {
+            return Err("价格需为非负数".to_string());
+        }
+        if p.currency.trim().is_empty() {
+            return Err("币种不能为空".to_string());
+        }
+    }
+    let mut cfg = load_config_from_file(&app)?;
+    cfg.usage_pricing = pricing.clone();
+    save_config_to_file(&app, &cfg)?;
+    usage_ledger::set_pricing(pricing);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::session::get_group_message_history;
+    use crate::mock_server::{signed_in, MockResponse};
+    use crate::services::usage_ledger::UsageContext;
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn usage_ledger_aggregates_history_and_stream_usage() {
+        let (_guard, server) = signed_in().await;
+        let ms = |s: &str| {
+            chrono::DateTime::parse_from_rfc3339(s)
+                .expect("time")
+                .timestamp_millis()
+        };
+        server.script(
+            Method::GET,
+            "/api/v1/groups/group-usage/messages",
+            MockResponse::ok(json!([
+                { "id": "usage-q", "groupSeq": 1, "role": "User", "content": "问",
+                  "timestamp": "2030-01-01T09:59:00Z", "tokenUsage": { "input": 999, "output": 999 } },
+                { "id": "usage-a1", "groupSeq": 2, "role": "Assistant", "viewRole": "DEV", "content": "答",
+                  "timestamp": "2030-01-01T10:00:00Z", "tokenUsage": { "input": 100, "output": 50 } },
+                { "id": "usage-a2", "groupSeq": 3, "role": "Assistant", "viewRole": "PM", "content": "答",
+                  "timestamp": "2030-01-02T10:00:00Z", "tokenUsage": { "input": 200, "output": 100 } }
+            ])),
+        );
+        get_group_message_history("group-usage".into(), None, None, None, None, None, None)
+            .await
+            .expect("history");
+        // 同一条回答的 done 事件不会重复计数
+        usage_ledger::record_stream_event(
+            &json!({ "type": "done", "messageId": "usage-a1", "tokenUsage": { "input": 100, "output": 50 } }),
+            None,
+        );
+        usage_ledger::set_pricing(Some(
+            serde_json::from_value(json!({
+                "currency": "CNY",
+                "inputPerMillion": 1.0,
+                "outputPerMillion": 2.0,
+                "skills": { "prd-review": { "inputPerMillion": 10.0, "outputPerMillion": 20.0 } }
+            }))
+            .expect("pricing"),
+        ));
+
+        let range = UsageRange {
+            from_ms: Some(ms("2030-01-01T00:00:00Z")),
+            to_ms: Some(ms("2030-01-03T00:00:00Z")),
+        };
+        let report = get_usage_report(Some(range.clone()), Some(UsageGroupBy::Role))
+            .await
+            .expect("report");
+        let rows: Vec<_> = report
+            .rows
+            .iter()
+            .map(|r| (r.key.as_str(), r.message_count, r.total_tokens))
+            .collect();
+        assert_eq!(rows, [("PM", 1, 300), ("DEV", 1, 150)]);
+        assert_eq!(report.total.total_tokens, 450);
+        assert!((report.rows[0].cost.expect("cost") - 0.0004).abs() < 1e-9);
+        let csv = usage_ledger::to_csv(&report).expect("csv");
+        assert!(csv.starts_with("role,messages,input_tokens,output_tokens,total_tokens,cost_cny\n"));
+        assert!(csv.contains("\ntotal,2,300,150,450,"));
+
+        let by_group = get_usage_report(Some(range.clone()), Some(UsageGroupBy::Group))
+            .await
+            .expect("report");
+        assert_eq!(by_group.rows.len(), 1);
+        assert_eq!(by_group.rows[0].key, "group-usage");
+        let by_day = get_usage_report(Some(range), None).await.expect("report");
+        assert_eq!(by_day.rows.len(), 2);
+
+        // 技能运行：done 事件先到，技能信息随后补上
+        let started = chrono::Utc::now().timestamp_millis();
+        usage_ledger::record_stream_event(
+            &json!({ "type": "done", "messageId": "usage-skill", "tokenUsage": { "input": 1000, "output": 500 } }),
+            Some(&UsageContext {
+                session_id: Some("session-1".into()),
+                role: Some("qa".into()),
+            }),
+        );
+        usage_ledger::note_skill_run("usage-skill", "prd-review");
+        let report = get_usage_report(
+            Some(UsageRange {
+                from_ms: Some(started),
+                to_ms: None,
+            }),
+            Some(UsageGroupBy::Skill),
+        )
+        .await
+        .expect("report");
+        let skill = report
+            .rows
+            .iter()
+            .find(|r| r.key == "prd-review")
+            .expect("skill row");
+        assert_eq!(skill.total_tokens, 1500);
+        assert!((skill.cost.expect("cost") - 0.02).abs() < 1e-9);
+
+        assert!(get_usage_report(
+            Some(UsageRange {
+                from_ms: Some(2),
+                to_ms: Some(1),
+            }),
+            None,
+        )
+        .await
+        .is_err());
+        usage_ledger::set_pricing(None);
+    }
+}
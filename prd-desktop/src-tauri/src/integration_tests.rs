@@ -0,0 +1,444 @@
+//! 基于 mock_server 的集成测试：refresh-on-401、SSE 解析、流取消、上传。
+//! 各请求的命令级用例放在对应模块的 tests 中；这里只保留跨模块的 HTTP 流程。
+
+use futures::StreamExt;
+use reqwest::Method;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::commands::defect::{create_defect, list_defect_users, list_defects, submit_defect};
+use crate::commands::defect_draft::{self, DefectDraftInput};
+use crate::commands::session::{
+    drain_sse_frames, stream_message, SendMessageRequest, SseFrame, StreamCancelState, StreamSink,
+};
+use crate::mock_server::{signed_in, MockResponse, MOCK_USER_ID};
+use crate::models::{AddDefectAttachmentResponse, ApiResponse, DefectListFilter, SessionInfo};
+use crate::services::{api_client, ApiClient};
+
+/// 把 SSE 帧转成便于断言的字符串：keepalive / data 中的 type 字段
+fn describe(frame: &SseFrame) -> String {
+    match frame {
+        SseFrame::KeepAlive => "keepalive".to_string(),
+        SseFrame::Data(data) => serde_json::from_str::<serde_json::Value>(data)
+            .ok()
+            .and_then(|v| v.get("type").and_then(|t| t.as_str()).map(str::to_string))
+            .unwrap_or_else(|| data.clone()),
+    }
+}
+
+#[tokio::test]
+async fn refreshes_access_token_on_401_and_retries() {
+    let (_guard, server) = signed_in().await;
+    server.expire_access_token();
+
+    let resp: ApiResponse<SessionInfo> = ApiClient::new()
+        .get("/sessions/session-1")
+        .await
+        .expect("request");
+
+    assert!(resp.success);
+    assert_eq!(resp.data.expect("session").session_id, "session-1");
+    assert_eq!(server.requests_to("/api/v1/auth/refresh").len(), 1);
+    assert_eq!(server.requests_to("/api/v1/sessions/*").len(), 2);
+    assert_eq!(api_client::get_auth_token(), Some(server.tokens().0));
+}
+
+#[tokio::test]
+async fn surfaces_unauthorized_when_refresh_fails() {
+    let (_guard, server) = signed_in().await;
+    server.revoke_session();
+
+    let resp: ApiResponse<SessionInfo> = ApiClient::new()
+        .get("/sessions/session-1")
+        .await
+        .expect("request");
+
+    assert!(!resp.success);
+    assert_eq!(resp.error.expect("error").code, "UNAUTHORIZED");
+    // 只尝试刷新一次，不会无限重试
+    assert_eq!(server.requests_to("/api/v1/auth/refresh").len(), 1);
+}
+
+#[tokio::test]
+async fn scripted_responses_take_precedence() {
+    let (_guard, server) = signed_in().await;
+    server.script(
+        Method::GET,
+        "/api/v1/sessions/*",
+        MockResponse::error(500, "INTERNAL_ERROR", "boom"),
+    );
+
+    let client = ApiClient::new();
+    let first: ApiResponse<SessionInfo> = client.get("/sessions/s").await.expect("request");
+    let second: ApiResponse<SessionInfo> = client.get("/sessions/s").await.expect("request");
+
+    assert_eq!(first.error.expect("error").code, "INTERNAL_ERROR");
+    assert!(second.success, "scripted response is consumed once");
+}
+
+#[tokio::test]
+async fn parses_chat_run_sse_across_chunk_boundaries() {
+    let (_guard, server) = signed_in().await;
+
+    let url = format!("{}/api/v1/sessions/session-1/messages", server.base_url());
+    let mut req = api_client::build_streaming_client(&server.base_url())
+        .post(&url)
+        .header("Accept", "text/event-stream")
+        .json(&json!({ "content": "你好" }));
+    if let Some(token) = api_client::get_auth_token() {
+        req = req.header("Authorization", format!("Bearer {}", token));
+    }
+    let response = api_client::send_streaming(req).await.expect("stream");
+    assert!(response.status().is_success());
+
+    let mut buf = String::new();
+    let mut frames = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.expect("chunk");
+        frames.extend(drain_sse_frames(&mut buf, &String::from_utf8_lossy(&chunk)));
+    }
+
+    let kinds: Vec<String> = frames.iter().map(describe).collect();
+    assert_eq!(kinds, ["start", "delta", "keepalive", "delta", "done"]);
+    let echoed = frames
+        .iter()
+        .any(|f| matches!(f, SseFrame::Data(d) if d.contains("你好")));
+    assert!(echoed, "second delta echoes the question");
+    assert!(buf.is_empty(), "no partial frame left behind");
+}
+
+/// 记录所有回调的 sink（CLI / GUI 之外的第三种消费方）
+#[derive(Default)]
+struct RecordingSink {
+    phases: Vec<String>,
+    events: Vec<serde_json::Value>,
+    errors: Vec<String>,
+    auth_expired: bool,
+}
+
+impl StreamSink for RecordingSink {
+    fn phase(&mut self, phase: &str) {
+        self.phases.push(phase.to_string());
+    }
+    fn keepalive(&mut self) {}
+    fn event(&mut self, event: serde_json::Value) {
+        self.events.push(event);
+    }
+    fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+    fn auth_expired(&mut self) {
+        self.auth_expired = true;
+    }
+    fn cancelled(&mut self) {}
+}
+
+#[tokio::test]
+async fn stream_message_refreshes_and_feeds_sink() {
+    let (_guard, server) = signed_in().await;
+    server.expire_access_token();
+
+    let request = SendMessageRequest {
+        content: "验收标准".to_string(),
+        ..Default::default()
+    };
+    let mut sink = RecordingSink::default();
+    stream_message(
+        "session-1",
+        &request,
+        &tokio_util::sync::CancellationToken::new(),
+        &mut sink,
+    )
+    .await
+    .expect("stream");
+
+    assert_eq!(sink.phases, ["requesting", "connected", "receiving"]);
+    let kinds: Vec<&str> = sink
+        .events
+        .iter()
+        .filter_map(|e| e.get("type").and_then(|t| t.as_str()))
+        .collect();
+    assert_eq!(kinds, ["start", "delta", "delta", "done"]);
+    assert!(sink.errors.is_empty());
+    assert!(!sink.auth_expired);
+    assert_eq!(server.requests_to("/api/v1/auth/refresh").len(), 1);
+}
+
+#[tokio::test]
+async fn stream_message_reports_auth_expired_when_refresh_fails() {
+    let (_guard, server) = signed_in().await;
+    server.revoke_session();
+
+    let mut sink = RecordingSink::default();
+    stream_message(
+        "session-1",
+        &SendMessageRequest::default(),
+        &tokio_util::sync::CancellationToken::new(),
+        &mut sink,
+    )
+    .await
+    .expect("stream");
+
+    assert!(sink.auth_expired);
+    assert!(sink.events.is_empty());
+    assert_eq!(sink.errors.len(), 1, "401 surfaces as a stream error");
+}
+
+#[test]
+fn sse_parser_handles_crlf_and_multiline_data() {
+    let mut buf = String::new();
+    assert!(drain_sse_frames(&mut buf, "data: line1\r\ndata: line2").is_empty());
+
+    let frames = drain_sse_frames(&mut buf, "\r\n\n: ping\n\nevent: x\n\n");
+    assert_eq!(frames.len(), 2);
+    assert!(matches!(&frames[0], SseFrame::Data(d) if d == "line1\nline2"));
+    assert!(matches!(frames[1], SseFrame::KeepAlive));
+    assert!(buf.is_empty());
+}
+
+#[tokio::test]
+async fn new_stream_token_cancels_running_stream() {
+    let (_guard, server) = signed_in().await;
+    let cancel = StreamCancelState::default();
+    let token = cancel.new_defect_token();
+
+    let url = format!("{}/api/defect-agent/defects/stream", server.base_url());
+    let req = api_client::build_streaming_client(&server.base_url())
+        .get(&url)
+        .header("Accept", "text/event-stream")
+        .header(
+            "Authorization",
+            format!(
+                "Bearer {}",
+                api_client::get_auth_token().unwrap_or_default()
+            ),
+        );
+    let response = api_client::send_streaming(req).await.expect("stream");
+
+    // 与 pump_event_stream 相同的读取方式：token 取消即退出
+    let reader = tokio::spawn(async move {
+        let mut stream = response.bytes_stream();
+        let mut keepalives = 0;
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => return keepalives,
+                next = stream.next() => match next {
+                    Some(Ok(_)) => keepalives += 1,
+                    _ => return -1,
+                },
+            }
+        }
+    });
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    // 重新订阅会取消上一个 token
+    let _next = cancel.new_defect_token();
+    let received = tokio::time::timeout(Duration::from_secs(2), reader)
+        .await
+        .expect("reader stops promptly")
+        .expect("reader task");
+    assert!(received > 0, "stream was open and sending keepalives");
+}
+
+#[tokio::test]
+async fn defect_list_pages_server_side_and_locally() {
+    let (_guard, server) = signed_in().await;
+    for title in ["登录失败", "导出超时", "登录页白屏"] {
+        create_defect(
+            format!("{}：复现步骤略", title),
+            "major".to_string(),
+            Some(title.to_string()),
+            MOCK_USER_ID.to_string(),
+            None,
+        )
+        .await
+        .expect("create");
+    }
+    let list = |filter: DefectListFilter| async move {
+        list_defects(Some(filter))
+            .await
+            .expect("list")
+            .data
+            .expect("page")
+    };
+
+    // 服务端原生条件：直接按 offset 翻页，total 为服务端总数
+    let first = list(DefectListFilter {
+        limit: Some(2),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(first.items.len(), 2);
+    assert_eq!(first.total, 3);
+    assert_eq!(first.next_cursor.as_deref(), Some("2"));
+    let query = server
+        .requests_to("/api/defect-agent/defects")
+        .pop()
+        .and_then(|r| r.query)
+        .unwrap_or_default();
+    assert!(!query.contains("sort=") && !query.contains("keyword="));
+
+    // 非默认排序：拉全量后本地排序分页
+    let sorted = DefectListFilter {
+        sort: Some("defectNo".to_string()),
+        order: Some("asc".to_string()),
+        limit: Some(2),
+        ..Default::default()
+    };
+    let page1 = list(sorted.clone()).await;
+    let numbers: Vec<_> = page1.items.iter().map(|d| d.defect_no.clone()).collect();
+    assert_eq!(numbers, ["DEF-0001", "DEF-0002"]);
+    assert_eq!(page1.next_cursor.as_deref(), Some("2"));
+    let page2 = list(DefectListFilter {
+        cursor: page1.next_cursor,
+        ..sorted
+    })
+    .await;
+    assert_eq!(page2.items[0].defect_no, "DEF-0003");
+    assert_eq!((page2.total, page2.next_cursor), (3, None));
+
+    // 关键字过滤：total 为本地过滤后的条数
+    let matched = list(DefectListFilter {
+        keyword: Some("登录".to_string()),
+        limit: Some(1),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(matched.total, 2);
+    assert_eq!(matched.items.len(), 1);
+    assert_eq!(matched.next_cursor.as_deref(), Some("1"));
+
+    for (sort, order) in [("priority", None), ("createdAt", Some("up"))] {
+        let filter = DefectListFilter {
+            sort: Some(sort.to_string()),
+            order: order.map(str::to_string),
+            ..Default::default()
+        };
+        assert!(list_defects(Some(filter)).await.is_err());
+    }
+}
+
+#[tokio::test]
+async fn defect_draft_keeps_created_defect_until_content_changes() {
+    let (_guard, server) = signed_in().await;
+    let dir = std::env::temp_dir().join(format!("prd-drafts-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = |id: Option<String>, content: &str| DefectDraftInput {
+        id,
+        content: content.to_string(),
+        severity: "major".to_string(),
+        title: None,
+        assignee_user_id: Some(MOCK_USER_ID.to_string()),
+        template_id: None,
+    };
+
+    let draft = defect_draft::save_draft_input(&dir, input(None, "点击无响应"))
+        .await
+        .expect("save");
+
+    // submit 失败且回滚失败：defectId 留在草稿中
+    server.script(
+        Method::POST,
+        "/api/defect-agent/defects/*/submit",
+        MockResponse::error(500, "INTERNAL_ERROR", "submit failed"),
+    );
+    server.script(
+        Method::DELETE,
+        "/api/defect-agent/defects/*",
+        MockResponse::error(500, "INTERNAL_ERROR", "delete failed"),
+    );
+    let failed = defect_draft::submit_draft(&dir, &draft.id, None)
+        .await
+        .expect("submit");
+    assert!(!failed.success && !failed.rolled_back);
+    assert_eq!(failed.stage.as_deref(), Some("submit"));
+    let stale = failed.draft.and_then(|d| d.defect_id).expect("defect id");
+
+    // 内容未变：保留；内容变化：清除并删除服务端残留
+    let same = defect_draft::save_draft_input(&dir, input(Some(draft.id.clone()), "点击无响应"))
+        .await
+        .expect("save");
+    assert_eq!(same.defect_id.as_deref(), Some(stale.as_str()));
+    let edited = defect_draft::save_draft_input(&dir, input(Some(draft.id.clone()), "点击后白屏"))
+        .await
+        .expect("save");
+    assert_eq!(edited.defect_id, None);
+    assert!(server
+        .requests_to(&format!("/api/defect-agent/defects/{}", stale))
+        .iter()
+        .any(|r| r.method == "DELETE"));
+
+    let submitted = defect_draft::submit_draft(&dir, &draft.id, None)
+        .await
+        .expect("submit");
+    assert!(submitted.success);
+    let defect = submitted.defect.expect("defect");
+    assert_ne!(defect.id, stale);
+    assert_eq!(defect.raw_content, "点击后白屏");
+    assert!(!dir.join(format!("{}.json", draft.id)).exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn uploads_defect_attachment_as_multipart() {
+    let (_guard, server) = signed_in().await;
+
+    let created = create_defect(
+        "登录按钮无响应".to_string(),
+        "major".to_string(),
+        Some("登录失败".to_string()),
+        MOCK_USER_ID.to_string(),
+        None,
+    )
+    .await
+    .expect("create");
+    let defect_id = created.data.expect("defect").defect.id;
+
+    let bytes = b"\x89PNG\r\n\x1a\nmock-image".to_vec();
+    let uploaded: ApiResponse<AddDefectAttachmentResponse> = ApiClient::new()
+        .post_file(
+            &format!("/api/defect-agent/defects/{}/attachments", defect_id),
+            bytes.clone(),
+            "screenshot.png".to_string(),
+            "image/png".to_string(),
+        )
+        .await
+        .expect("upload");
+    assert!(uploaded.success);
+    assert_eq!(
+        uploaded.data.expect("attachment").attachment.file_name,
+        "screenshot.png"
+    );
+
+    let request = server
+        .requests_to("/api/defect-agent/defects/*/attachments")
+        .pop()
+        .expect("upload request");
+    assert!(request
+        .header("content-type")
+        .unwrap_or_default()
+        .starts_with("multipart/form-data"));
+    assert!(request
+        .body
+        .windows(bytes.len())
+        .any(|w| w == bytes.as_slice()));
+
+    let submitted = submit_defect(defect_id, None).await.expect("submit");
+    assert!(submitted.success);
+    assert_eq!(
+        submitted.data.expect("defect").defect.status.as_str(),
+        "submitted"
+    );
+}
+
+#[tokio::test]
+async fn lists_defect_users() {
+    let (_guard, _server) = signed_in().await;
+    let resp = list_defect_users().await.expect("list users");
+    assert!(resp.success);
+    let users = resp.data.expect("users").items;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].user_id, MOCK_USER_ID);
+    assert_eq!(users[0].display_name.as_deref(), Some("Mock 用户"));
+}
@@ -1,7 +1,13 @@
+pub mod cli;
 mod commands;
+#[cfg(test)]
+mod integration_tests;
+#[cfg(any(test, feature = "mock-server"))]
+pub mod mock_server;
 mod models;
 mod services;
 
+use commands::defect::DefectCacheState;
 use commands::session::StreamCancelState;
 use tauri::menu::{MenuBuilder, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder};
 use tauri::Emitter;
@@ -36,7 +42,17 @@ pub fn run() {
         }
     }
 
-    let app = tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // 单实例：Linux/Windows 打开 prdagent:// 链接会启动新进程，这里把 argv 转发给已运行的实例
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+        services::deep_link::focus_main_window(app);
+        if let Some(url) = services::deep_link::find_in_args(argv) {
+            let _ = services::deep_link::dispatch(app, &url);
+        }
+    }));
+
+    let app = builder
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_shell::init())
@@ -48,14 +64,33 @@ pub fn run() {
         )
         .setup(|app| {
             app.manage(StreamCancelState::default());
+            app.manage(DefectCacheState::default());
+            // 日志：按天滚动写入应用日志目录；级别随后由 init_config 按配置调整
+            match app.path().app_log_dir() {
+                Ok(dir) => {
+                    if let Err(e) =
+                        services::logging::init(dir, &services::logging::LoggingConfig::default())
+                    {
+                        eprintln!("[logging] {}", e);
+                    }
+                }
+                Err(e) => eprintln!("[logging] Failed to get log dir: {}", e),
+            }
+            // panic 时把现场写入 crash_reports/，下次启动由前端提示上报
+            services::crash_reporter::install(app.handle());
+            services::network_inspector::attach(app.handle());
             // 初始化配置（从文件加载 API URL）
             commands::config::init_config(app.handle());
-
-            // cold-start deep link：从启动参数中读取 prdagent://... 并发给前端处理
-            if let Some(url) = std::env::args().find(|a| a.starts_with("prdagent://")) {
-                let _ = app.emit("deep-link", url);
+            // 会话文档排序 / 纳入上下文状态、群组上次角色、token 用量台账（本机保存）
+            if let Ok(dir) = app.path().app_data_dir() {
+                services::session_documents::init(dir.clone());
+                services::role_views::init(dir.clone());
+                services::usage_ledger::init(dir);
             }
 
+            // cold-start deep link：从启动参数中读取 prdagent://...，校验后发给前端处理
+            services::deep_link::dispatch_launch_args(app.handle());
+
             #[cfg(debug_assertions)]
             {
                 let window = app.get_webview_window("main").unwrap();
@@ -211,8 +246,12 @@ pub fn run() {
             commands::session::get_session,
             commands::session::get_message_history,
             commands::session::get_group_message_history,
+            commands::message_graph::get_thread,
+            commands::message_graph::get_resend_variants,
             commands::session::subscribe_group_messages,
             commands::session::switch_role,
+            commands::role_views::get_last_group_role,
+            commands::role_views::restore_group_role,
             commands::session::send_message,
             commands::session::create_chat_run,
             commands::session::subscribe_chat_run,
@@ -227,14 +266,40 @@ pub fn run() {
             commands::assets::get_desktop_asset_skins,
             commands::group::create_group,
             commands::group::join_group,
+            commands::group::preview_invite,
+            commands::group::generate_invite_link,
             commands::group::get_groups,
             commands::group::open_group_session,
             commands::group::bind_group_prd,
             commands::group::dissolve_group,
             commands::group::leave_group,
             commands::group::add_group_member,
+            commands::group::remove_group_member,
+            commands::group::update_group_member_role,
+            commands::group::update_group_member_tags,
+            commands::group::transfer_group_ownership,
+            commands::group_archive::archive_group_snapshot,
+            commands::group_archive::open_archive,
+            commands::group_archive::read_archive_asset,
+            commands::session_documents::get_session_document_budget,
+            commands::session_documents::reorder_session_documents,
+            commands::session_documents::set_session_document_included,
+            commands::session_documents::get_context_budget_settings,
+            commands::session_documents::save_context_budget_settings,
+            commands::usage::get_usage_report,
+            commands::usage::export_usage_csv,
+            commands::usage::get_usage_pricing,
+            commands::usage::save_usage_pricing,
+            commands::presence::get_group_presence,
+            commands::presence::subscribe_group_presence,
+            commands::presence::unsubscribe_group_presence,
+            commands::presence::send_typing,
+            commands::presence::report_user_activity,
             commands::group::get_group_members,
             commands::group::clear_group_context,
+            commands::context_snapshot::list_context_snapshots,
+            commands::context_snapshot::restore_group_context,
+            commands::context_snapshot::delete_context_snapshot,
             commands::prd_comments::get_prd_comments,
             commands::prd_comments::create_prd_comment,
             commands::prd_comments::delete_prd_comment,
@@ -254,6 +319,8 @@ pub fn run() {
             commands::updater::fetch_accelerated_manifest,
             commands::client_config::fetch_client_config,
             commands::defect::list_defects,
+            commands::defect::list_all_defects,
+            commands::defect::get_cached_defects,
             commands::defect::list_defect_users,
             commands::defect::list_defect_templates,
             commands::defect::create_defect,
@@ -272,11 +339,47 @@ pub fn run() {
             commands::defect::polish_defect,
             commands::defect::preview_defect_logs,
             commands::defect::add_defect_attachment,
+            commands::defect::get_defect_transitions,
+            commands::defect::preview_client_logs,
+            commands::logs::get_log_files,
+            commands::logs::read_log_tail,
+            commands::logs::export_logs_zip,
+            commands::crash::list_crash_reports,
+            commands::crash::file_crash_report,
+            commands::crash::dismiss_crash_report,
+            commands::defect::bulk_defect_action,
+            commands::defect::subscribe_defect,
+            commands::defect::subscribe_my_defects,
+            commands::defect_draft::save_defect_draft,
+            commands::defect_draft::list_defect_drafts,
+            commands::defect_draft::resume_defect_draft,
+            commands::defect_draft::delete_defect_draft,
+            commands::defect_draft::add_defect_draft_attachment,
+            commands::defect_draft::remove_defect_draft_attachment,
+            commands::defect_draft::submit_defect_draft,
+            commands::defect_export::export_defects,
+            commands::notification::get_notification_settings,
+            commands::notification::save_notification_settings,
+            commands::notification::set_group_muted,
+            commands::mcp::get_mcp_settings,
+            commands::mcp::save_mcp_settings,
+            commands::mcp::rotate_mcp_token,
+            commands::mcp::get_mcp_status,
+            commands::deep_link::take_pending_deep_link,
+            commands::deep_link::parse_deep_link,
+            commands::bridge::get_bridge_status,
+            commands::bridge::set_bridge_enabled,
+            commands::bridge::pair_bridge_client,
+            commands::bridge::revoke_bridge_client,
             commands::defect::close_defect,
             commands::defect::delete_defect,
             commands::defect::verify_pass_defect,
             commands::defect::verify_fail_defect,
             commands::devtools::open_devtools,
+            commands::devtools::get_network_log,
+            commands::devtools::clear_network_log,
+            commands::devtools::replay_request,
+            commands::devtools::copy_request_as_curl,
             commands::attachment::upload_attachment,
             commands::skill::get_skills,
             commands::skill::execute_skill,
@@ -300,12 +403,23 @@ pub fn run() {
                     cancel_state.cancel_all();
                 }
                 services::api_client::stop_desktop_presence_heartbeat();
+                services::presence::stop_watch();
+                services::usage_ledger::flush();
+            }
+            // 主窗口获得焦点：记为用户活动（空闲判断）；点击系统通知激活时跳转到对应的 deep link
+            tauri::RunEvent::WindowEvent {
+                label,
+                event: tauri::WindowEvent::Focused(true),
+                ..
+            } if label == "main" => {
+                services::presence::touch();
+                services::notifier::on_main_window_focused(_app_handle);
             }
             // warm-start deep link（macOS/iOS）：应用运行中收到 URL 打开事件
             #[cfg(any(target_os = "macos", target_os = "ios"))]
             tauri::RunEvent::Opened { urls } => {
                 for url in urls {
-                    let _ = _app_handle.emit("deep-link", url.to_string());
+                    let _ = services::deep_link::dispatch(_app_handle, url.as_str());
                 }
             }
             _ => {}
@@ -0,0 +1,716 @@
+//! 进程内 mock API：实现桌面端用到的 `/api/v1`（auth / sessions / groups / documents /
+//! chat-run SSE）与 `/api/defect-agent` 接口子集，供离线开发（`prd-mock-api`）与集成测试使用。
+//!
+//! - 默认行为足够让桌面端登录、打开会话、收发消息、提交缺陷
+//! - `script` 可按 method + path 注入一次性响应（优先于鉴权与默认行为），用于构造异常场景
+//! - 所有请求都会被记录，测试可断言请求次数、请求头与请求体
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{header, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// mock 登录用户
+pub const MOCK_USER_ID: &str = "user-mock";
+/// 请求体最多读取的字节数（上传测试用，足够大即可）
+const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+/// 保持打开的 SSE 连接发送 keepalive 的间隔
+const KEEPALIVE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 脚本化响应
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    Json {
+        status: u16,
+        body: Value,
+    },
+    /// SSE：按顺序发送原始分片（可以在帧中间切开，用于测试跨分片解析）；
+    /// keep_open 为 true 时发完后持续发送 keepalive，直到客户端断开
+    Sse {
+        chunks: Vec<String>,
+        interval: Duration,
+        keep_open: bool,
+    },
+}
+
+impl MockResponse {
+    /// 200 + `{ success: true, data }`
+    pub fn ok(data: Value) -> Self {
+        Self::Json {
+            status: 200,
+            body: json!({ "success": true, "data": data, "error": null }),
+        }
+    }
+
+    /// 指定状态码 + `{ success: false, error: { code, message } }`
+    pub fn error(status: u16, code: &str, message: &str) -> Self {
+        Self::Json {
+            status,
+            body: json!({
+                "success": false,
+                "data": null,
+                "error": { "code": code, "message": message }
+            }),
+        }
+    }
+
+    /// 每个事件一帧 `data: <json>\n\n`，发完即关闭
+    pub fn sse(events: &[Value]) -> Self {
+        Self::Sse {
+            chunks: events.iter().map(|e| format!("data: {}\n\n", e)).collect(),
+            interval: Duration::from_millis(10),
+            keep_open: false,
+        }
+    }
+}
+
+/// 服务端收到的一次请求
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    /// 小写请求头名 → 值
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(|s| s.as_str())
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// 查询参数（未解码，测试中的取值均为简单 ASCII）
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .as_deref()?
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v)
+    }
+}
+
+struct Script {
+    method: Method,
+    pattern: String,
+    responses: VecDeque<MockResponse>,
+}
+
+struct Inner {
+    scripts: Vec<Script>,
+    requests: Vec<RecordedRequest>,
+    token_seq: u32,
+    access_token: String,
+    refresh_token: String,
+    defects: Vec<Value>,
+    defect_seq: u32,
+}
+
+impl Inner {
+    fn new() -> Self {
+        Self {
+            scripts: Vec::new(),
+            requests: Vec::new(),
+            token_seq: 1,
+            access_token: "mock-access-1".to_string(),
+            refresh_token: "mock-refresh-1".to_string(),
+            defects: Vec::new(),
+            defect_seq: 0,
+        }
+    }
+
+    fn rotate_tokens(&mut self) {
+        self.token_seq += 1;
+        self.access_token = format!("mock-access-{}", self.token_seq);
+        self.refresh_token = format!("mock-refresh-{}", self.token_seq);
+    }
+
+    fn take_script(&mut self, method: &Method, path: &str) -> Option<MockResponse> {
+        let script = self.scripts.iter_mut().find(|s| {
+            s.method == *method && path_matches(&s.pattern, path) && !s.responses.is_empty()
+        })?;
+        script.responses.pop_front()
+    }
+}
+
+type Shared = Arc<Mutex<Inner>>;
+
+/// 运行中的 mock 服务；drop 时关闭
+pub struct MockServer {
+    addr: SocketAddr,
+    inner: Shared,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// 在 127.0.0.1 的随机端口启动（需在 tokio runtime 中调用）
+    pub async fn start() -> std::io::Result<Self> {
+        Self::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await
+    }
+
+    pub async fn bind(addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let inner: Shared = Arc::new(Mutex::new(Inner::new()));
+        let app = Router::new().fallback(handle).with_state(inner.clone());
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = rx.await;
+                })
+                .await;
+        });
+        Ok(Self {
+            addr,
+            inner,
+            shutdown: Some(tx),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 形如 http://127.0.0.1:12345，可直接作为 apiBaseUrl
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// 注入一次性响应；同一 method + pattern 可多次调用，按 FIFO 消费。
+    /// pattern 中的 `*` 匹配单个路径段，如 `/api/v1/sessions/*/messages`
+    pub fn script(&self, method: Method, pattern: &str, response: MockResponse) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(script) = inner
+            .scripts
+            .iter_mut()
+            .find(|s| s.method == method && s.pattern == pattern)
+        {
+            script.responses.push_back(response);
+            return;
+        }
+        inner.scripts.push(Script {
+            method,
+            pattern: pattern.to_string(),
+            responses: VecDeque::from([response]),
+        });
+    }
+
+    /// 当前有效的 (access_token, refresh_token)
+    pub fn tokens(&self) -> (String, String) {
+        let inner = self.inner.lock().unwrap();
+        (inner.access_token.clone(), inner.refresh_token.clone())
+    }
+
+    /// 让当前 access token 失效（refresh token 仍然有效），模拟 access 过期
+    pub fn expire_access_token(&self) {
+        self.inner.lock().unwrap().access_token = "mock-access-expired".to_string();
+    }
+
+    /// 让 access 与 refresh token 都失效，模拟会话被踢下线
+    pub fn revoke_session(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.access_token = "mock-access-revoked".to_string();
+        inner.refresh_token = "mock-refresh-revoked".to_string();
+    }
+
+    /// 已收到的请求（按到达顺序）
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.inner.lock().unwrap().requests.clone()
+    }
+
+    /// 匹配 pattern 的请求（不区分 method）
+    pub fn requests_to(&self, pattern: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|r| path_matches(pattern, &r.path))
+            .collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    pattern.len() == path.len() && pattern.iter().zip(&path).all(|(p, s)| *p == "*" || p == s)
+}
+
+fn now_iso() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+async fn handle(State(inner): State<Shared>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .unwrap_or_default();
+    let recorded = RecordedRequest {
+        method: parts.method.to_string(),
+        path: parts.uri.path().to_string(),
+        query: parts.uri.query().map(|q| q.to_string()),
+        headers: parts
+            .headers
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.as_str().to_string(),
+                    v.to_str().unwrap_or_default().to_string(),
+                )
+            })
+            .collect(),
+        body: body.to_vec(),
+    };
+
+    let response = {
+        let mut inner = inner.lock().unwrap();
+        inner.requests.push(recorded.clone());
+        match inner.take_script(&parts.method, &recorded.path) {
+            Some(scripted) => scripted,
+            None => route(&mut inner, &parts.method, &recorded),
+        }
+    };
+    into_http(response)
+}
+
+fn into_http(response: MockResponse) -> Response {
+    match response {
+        // body 为 null 时返回空 body（如鉴权中间件的 401）
+        MockResponse::Json { status, body } => (
+            StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            [(header::CONTENT_TYPE, "application/json")],
+            if body.is_null() {
+                String::new()
+            } else {
+                body.to_string()
+            },
+        )
+            .into_response(),
+        MockResponse::Sse {
+            chunks,
+            interval,
+            keep_open,
+        } => {
+            let chunks: VecDeque<String> = chunks.into();
+            let stream = futures::stream::unfold(chunks, move |mut chunks| async move {
+                let next = match chunks.pop_front() {
+                    Some(chunk) => chunk,
+                    None if keep_open => ": keepalive\n\n".to_string(),
+                    None => return None,
+                };
+                tokio::time::sleep(if chunks.is_empty() && keep_open {
+                    KEEPALIVE_INTERVAL
+                } else {
+                    interval
+                })
+                .await;
+                Some((Ok::<_, Infallible>(Bytes::from(next)), chunks))
+            });
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "text/event-stream")
+                .header(header::CACHE_CONTROL, "no-cache")
+                .body(Body::from_stream(stream))
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+/// 默认路由（未被 script 覆盖时）
+fn route(inner: &mut Inner, method: &Method, req: &RecordedRequest) -> MockResponse {
+    let path = req.path.as_str();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    // 免鉴权
+    match (method, segments.as_slice()) {
+        (&Method::POST, ["api", "v1", "auth", "login"]) => {
+            let body = req.json();
+            let username = body
+                .get("username")
+                .and_then(|v| v.as_str())
+                .unwrap_or("mock")
+                .to_string();
+            if body.get("password").and_then(|v| v.as_str()) == Some("wrong") {
+                return MockResponse::error(401, "INVALID_CREDENTIALS", "用户名或密码错误");
+            }
+            inner.rotate_tokens();
+            return MockResponse::ok(login_payload(inner, &username));
+        }
+        (&Method::POST, ["api", "v1", "auth", "refresh"]) => {
+            let presented = req
+                .json()
+                .get("refreshToken")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            if presented.as_deref() != Some(inner.refresh_token.as_str()) {
+                return MockResponse::error(401, "UNAUTHORIZED", "refresh token 无效");
+            }
+            inner.rotate_tokens();
+            return MockResponse::ok(login_payload(inner, "mock"));
+        }
+        _ => {}
+    }
+
+    let bearer = req
+        .header("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if bearer != Some(inner.access_token.as_str()) {
+        // 与后端一致：鉴权中间件返回空 body 的 401
+        return MockResponse::Json {
+            status: 401,
+            body: Value::Null,
+        };
+    }
+
+    match (method, segments.as_slice()) {
+        (&Method::POST, ["api", "v1", "desktop", "presence", "heartbeat"]) => {
+            MockResponse::ok(json!({}))
+        }
+        (&Method::POST, ["api", "v1", "desktop", "presence", "typing"]) => {
+            MockResponse::ok(json!({}))
+        }
+        (&Method::GET, ["api", "v1", "desktop", "presence", "groups", _]) => {
+            MockResponse::ok(json!([{ "userId": MOCK_USER_ID, "online": true, "typing": false }]))
+        }
+
+        // ---- sessions / chat-run ----
+        (&Method::GET, ["api", "v1", "sessions", id]) => MockResponse::ok(json!({
+            "sessionId": id,
+            "groupId": "group-1",
+            "documentId": "doc-1",
+            "documentIds": ["doc-1"],
+            "currentRole": "PM",
+            "mode": "QA",
+            "guideStep": null
+        })),
+        (&Method::PUT, ["api", "v1", "sessions", id, "role"]) => MockResponse::ok(json!({
+            "sessionId": id,
+            "currentRole": req.json()["role"].as_str().unwrap_or("PM")
+        })),
+        (&Method::POST, ["api", "v1", "sessions", _, "messages"]) => chat_run_sse(req),
+        // runId 记录创建请求的序号，stream 时据此取回问题与角色
+        (&Method::POST, ["api", "v1", "sessions", _, "messages", "run"]) => {
+            let seq = inner.requests.len() - 1;
+            MockResponse::ok(json!({
+                "runId": format!("run-{}", seq),
+                "userMessageId": format!("user-{}", seq),
+                "assistantMessageId": format!("assistant-{}", seq),
+                "groupSeq": null
+            }))
+        }
+        (&Method::GET, ["api", "v1", "chat-runs", run_id, "stream"]) => {
+            let created = run_id
+                .strip_prefix("run-")
+                .and_then(|seq| seq.parse::<usize>().ok())
+                .and_then(|seq| inner.requests.get(seq));
+            match created {
+                Some(created) => chat_run_sse(created),
+                None => MockResponse::error(404, "RUN_NOT_FOUND", "run 不存在"),
+            }
+        }
+
+        // ---- groups ----
+        (&Method::GET, ["api", "v1", "groups"]) => MockResponse::ok(json!([group_payload()])),
+        (&Method::GET, ["api", "v1", "groups", "invite", code]) => {
+            if *code != "MOCK01" {
+                return MockResponse::error(404, "INVALID_INVITE_LINK", "邀请码无效");
+            }
+            MockResponse::ok(json!({
+                "groupId": "group-1",
+                "groupName": "Mock 群组",
+                "prdTitle": "Mock PRD",
+                "memberCount": 1,
+                "ownerDisplayName": "Mock 用户",
+                "alreadyMember": true
+            }))
+        }
+        (&Method::GET, ["api", "v1", "groups", _, "members"]) => MockResponse::ok(json!([{
+            "userId": MOCK_USER_ID,
+            "username": "mock",
+            "displayName": "Mock 用户",
+            "memberRole": "PM",
+            "tags": [],
+            "joinedAt": now_iso(),
+            "isOwner": true
+        }])),
+        (&Method::POST, ["api", "v1", "groups", _, "context", "clear"]) => {
+            MockResponse::ok(json!({}))
+        }
+        (&Method::GET, ["api", "v1", "groups", _, "messages", "stream"]) => MockResponse::Sse {
+            chunks: Vec::new(),
+            interval: KEEPALIVE_INTERVAL,
+            keep_open: true,
+        },
+
+        // ---- documents ----
+        (&Method::GET, ["api", "v1", "groups", _, "messages", _]) => {
+            MockResponse::error(404, "NOT_FOUND", "消息不存在")
+        }
+        (&Method::GET, ["api", "v1", "documents", id]) => MockResponse::ok(json!({
+            "id": id,
+            "title": "Mock PRD",
+            "content": "# Mock PRD\n\n用于离线开发的示例文档。"
+        })),
+        (&Method::POST, ["api", "v1", "documents"]) => {
+            let content = req
+                .json()
+                .get("content")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            MockResponse::ok(json!({
+                "sessionId": "session-1",
+                "document": {
+                    "id": "doc-1",
+                    "title": content.lines().next().unwrap_or("Untitled").trim_start_matches('#').trim(),
+                    "charCount": content.chars().count(),
+                    "tokenEstimate": content.chars().count() / 2
+                }
+            }))
+        }
+
+        // ---- defect-agent ----
+        (&Method::GET, ["api", "defect-agent", "users"]) => MockResponse::ok(json!({
+            "items": [{ "userId": MOCK_USER_ID, "username": "mock", "displayName": "Mock 用户" }]
+        })),
+        (&Method::GET, ["api", "defect-agent", "templates"]) => {
+            MockResponse::ok(json!({ "items": [] }))
+        }
+        (&Method::GET, ["api", "defect-agent", "stats"]) => MockResponse::ok(json!({
+            "total": inner.defects.len(),
+            "statusCounts": {},
+            "severityCounts": {}
+        })),
+        (&Method::GET, ["api", "defect-agent", "defects", "stream"]) => MockResponse::Sse {
+            chunks: Vec::new(),
+            interval: KEEPALIVE_INTERVAL,
+            keep_open: true,
+        },
+        // 与服务端一致：按 createdAt 倒序（mock 中即插入倒序），limit / offset 分页
+        (&Method::GET, ["api", "defect-agent", "defects"]) => {
+            let param = |name| req.query_param(name).and_then(|v| v.parse::<usize>().ok());
+            let offset = param("offset").unwrap_or(0);
+            let limit = param("limit").unwrap_or(20);
+            let items: Vec<&Value> = inner
+                .defects
+                .iter()
+                .rev()
+                .skip(offset)
+                .take(limit)
+                .collect();
+            MockResponse::ok(json!({
+                "items": items,
+                "total": inner.defects.len()
+            }))
+        }
+        (&Method::POST, ["api", "defect-agent", "defects"]) => {
+            let body = req.json();
+            inner.defect_seq += 1;
+            let defect = json!({
+                "id": format!("defect-{}", inner.defect_seq),
+                "defectNo": format!("DEF-{:04}", inner.defect_seq),
+                "title": body.get("title").cloned().unwrap_or(Value::Null),
+                "rawContent": body.get("content").cloned().unwrap_or(json!("")),
+                "severity": body.get("severity").cloned().unwrap_or(Value::Null),
+                "status": "draft",
+                "reporterId": MOCK_USER_ID,
+                "assigneeId": body.get("assigneeUserId").cloned().unwrap_or(Value::Null),
+                "attachments": [],
+                "createdAt": now_iso(),
+                "updatedAt": now_iso()
+            });
+            inner.defects.push(defect.clone());
+            MockResponse::ok(json!({ "defect": defect }))
+        }
+        (&Method::GET, ["api", "defect-agent", "defects", id]) => match find_defect(inner, id) {
+            Some(defect) => MockResponse::ok(json!({ "defect": defect, "messages": [] })),
+            None => MockResponse::error(404, "NOT_FOUND", "缺陷不存在"),
+        },
+        (&Method::DELETE, ["api", "defect-agent", "defects", id]) => {
+            let before = inner.defects.len();
+            inner
+                .defects
+                .retain(|d| d.get("id").and_then(|v| v.as_str()) != Some(*id));
+            if inner.defects.len() == before {
+                MockResponse::error(404, "NOT_FOUND", "缺陷不存在")
+            } else {
+                MockResponse::ok(json!({}))
+            }
+        }
+        (&Method::POST, ["api", "defect-agent", "defects", id, "attachments"]) => {
+            if find_defect(inner, id).is_none() {
+                return MockResponse::error(404, "NOT_FOUND", "缺陷不存在");
+            }
+            let file_name = multipart_file_name(&req.body).unwrap_or_else(|| "file".to_string());
+            MockResponse::ok(json!({
+                "attachment": {
+                    "id": format!("att-{}", inner.requests.len()),
+                    "fileName": file_name,
+                    "fileSize": req.body.len(),
+                    "mimeType": "application/octet-stream",
+                    "url": format!("/files/{}", file_name),
+                    "uploadedAt": now_iso()
+                }
+            }))
+        }
+        (&Method::GET, ["api", "defect-agent", "defects", _, "messages"]) => {
+            MockResponse::ok(json!({ "messages": [] }))
+        }
+        (&Method::POST, ["api", "defect-agent", "defects", id, action]) => {
+            let status = match *action {
+                "submit" => "submitted",
+                "assign" => "assigned",
+                "process" => "processing",
+                "resolve" => "resolved",
+                "reject" => "rejected",
+                "close" => "closed",
+                "verify-pass" => "verified",
+                "verify-fail" => "processing",
+                _ => return MockResponse::error(404, "NOT_FOUND", "未知操作"),
+            };
+            let Some(defect) = inner
+                .defects
+                .iter_mut()
+                .find(|d| d.get("id").and_then(|v| v.as_str()) == Some(*id))
+            else {
+                return MockResponse::error(404, "NOT_FOUND", "缺陷不存在");
+            };
+            defect["status"] = json!(status);
+            defect["updatedAt"] = json!(now_iso());
+            MockResponse::ok(json!({ "defect": defect.clone() }))
+        }
+
+        _ => MockResponse::error(
+            404,
+            "NOT_FOUND",
+            &format!("mock: no route for {} {}", method, path),
+        ),
+    }
+}
+
+fn login_payload(inner: &Inner, username: &str) -> Value {
+    json!({
+        "accessToken": inner.access_token,
+        "refreshToken": inner.refresh_token,
+        "sessionKey": "mock-session-key",
+        "clientType": "desktop",
+        "expiresIn": 3600,
+        "user": {
+            "userId": MOCK_USER_ID,
+            "username": username,
+            "displayName": "Mock 用户",
+            "role": "PM"
+        }
+    })
+}
+
+fn group_payload() -> Value {
+    json!({
+        "groupId": "group-1",
+        "groupName": "Mock 群组",
+        "prdDocumentId": "doc-1",
+        "prdTitle": "Mock PRD",
+        "inviteCode": "MOCK01",
+        "createdAt": now_iso(),
+        "memberCount": 1
+    })
+}
+
+fn find_defect(inner: &Inner, id: &str) -> Option<Value> {
+    inner
+        .defects
+        .iter()
+        .find(|d| d.get("id").and_then(|v| v.as_str()) == Some(id))
+        .cloned()
+}
+
+/// chat-run：start → 两段 delta → done；第二帧故意从中间切开，覆盖跨分片拼帧
+fn chat_run_sse(req: &RecordedRequest) -> MockResponse {
+    let question = req
+        .json()
+        .get("content")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let message_id = "assistant-1";
+    let start = json!({ "type": "start", "messageId": message_id }).to_string();
+    let delta1 =
+        json!({ "type": "delta", "messageId": message_id, "content": "Mock 回复：" }).to_string();
+    let delta2 =
+        json!({ "type": "delta", "messageId": message_id, "content": question }).to_string();
+    let done = json!({ "type": "done", "messageId": message_id }).to_string();
+    let (head, tail) = delta1.split_at(delta1.len() / 2);
+    MockResponse::Sse {
+        chunks: vec![
+            format!("data: {}\n\n", start),
+            format!("data: {}", head),
+            format!("{}\n\n: keepalive\n\n", tail),
+            format!("data: {}\n\n", delta2),
+            format!("data: {}\n\n", done),
+        ],
+        interval: Duration::from_millis(10),
+        keep_open: false,
+    }
+}
+
+fn multipart_file_name(body: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(body);
+    let start = text.find("filename=\"")? + "filename=\"".len();
+    let end = text[start..].find('"')?;
+    Some(text[start..start + end].to_string())
+}
+
+#[cfg(test)]
+lazy_static::lazy_static! {
+    /// ApiClient 的 base url 与登录态是进程级全局状态，用到 mock 的用例通过它串行执行
+    static ref SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+/// 测试用：启动 mock 并以 mock 用户登录；返回的 guard 在用例结束前不能 drop
+#[cfg(test)]
+pub(crate) async fn signed_in() -> (tokio::sync::MutexGuard<'static, ()>, MockServer) {
+    use crate::models::{ApiResponse, LoginResponse};
+    use crate::services::{api_client, ApiClient};
+
+    let guard = SERIAL.lock().await;
+    let server = MockServer::start().await.expect("start mock server");
+    api_client::set_api_base_url(server.base_url());
+
+    let resp: ApiResponse<LoginResponse> = ApiClient::new()
+        .post(
+            "/auth/login",
+            &json!({ "username": "mock", "password": "mock", "clientType": "desktop" }),
+        )
+        .await
+        .expect("login request");
+    let data = resp.data.expect("login data");
+    ApiClient::set_token(data.access_token);
+    ApiClient::set_auth_session(
+        Some(data.user.user_id),
+        Some(data.refresh_token),
+        Some(data.session_key),
+        Some(data.client_type),
+    );
+    (guard, server)
+}
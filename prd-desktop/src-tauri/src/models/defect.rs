@@ -0,0 +1,812 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 服务端新增但客户端尚未建模的字段：原样保留并透传给前端，避免升级后端时丢字段
+pub type ExtraFields = serde_json::Map<String, serde_json::Value>;
+
+// ---------------------------------------------------------------------------
+// 状态 / 严重程度
+// ---------------------------------------------------------------------------
+
+/// 缺陷状态（与后端 DefectStatus 常量一致）
+/// 未知取值保留在 `Unknown` 中，序列化时原样写回
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum DefectStatus {
+    Draft,
+    Reviewing,
+    Awaiting,
+    Submitted,
+    Assigned,
+    Processing,
+    Verifying,
+    Resolved,
+    Rejected,
+    Closed,
+    Unknown(String),
+}
+
+impl DefectStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Draft => "draft",
+            Self::Reviewing => "reviewing",
+            Self::Awaiting => "awaiting",
+            Self::Submitted => "submitted",
+            Self::Assigned => "assigned",
+            Self::Processing => "processing",
+            Self::Verifying => "verifying",
+            Self::Resolved => "resolved",
+            Self::Rejected => "rejected",
+            Self::Closed => "closed",
+            Self::Unknown(s) => s.as_str(),
+        }
+    }
+
+    /// 当前状态下允许执行的流转动作（用于前端禁用非法按钮）
+    /// 注意：这里只判断状态，不判断“是否为报告人/被指派人”，权限仍以后端为准
+    pub fn allowed_actions(&self) -> Vec<DefectAction> {
+        DefectAction::ALL
+            .iter()
+            .copied()
+            .filter(|a| a.is_allowed_from(self))
+            .collect()
+    }
+}
+
+impl From<String> for DefectStatus {
+    fn from(value: String) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "draft" => Self::Draft,
+            "reviewing" => Self::Reviewing,
+            "awaiting" => Self::Awaiting,
+            "submitted" => Self::Submitted,
+            "assigned" => Self::Assigned,
+            "processing" => Self::Processing,
+            "verifying" => Self::Verifying,
+            "resolved" => Self::Resolved,
+            "rejected" => Self::Rejected,
+            "closed" => Self::Closed,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<DefectStatus> for String {
+    fn from(value: DefectStatus) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for DefectStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 缺陷严重程度（与后端 DefectSeverity 常量一致）
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Severity {
+    Blocker,
+    Critical,
+    Major,
+    Minor,
+    Trivial,
+    Suggestion,
+    Unknown(String),
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Blocker => "blocker",
+            Self::Critical => "critical",
+            Self::Major => "major",
+            Self::Minor => "minor",
+            Self::Trivial => "trivial",
+            Self::Suggestion => "suggestion",
+            Self::Unknown(s) => s.as_str(),
+        }
+    }
+
+    /// 排序权重：越严重越小（未知排最后）
+    pub fn rank(&self) -> u8 {
+        match self {
+            Self::Blocker => 0,
+            Self::Critical => 1,
+            Self::Major => 2,
+            Self::Minor => 3,
+            Self::Trivial => 4,
+            Self::Suggestion => 5,
+            Self::Unknown(_) => 6,
+        }
+    }
+}
+
+impl From<String> for Severity {
+    fn from(value: String) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "blocker" => Self::Blocker,
+            "critical" => Self::Critical,
+            "major" => Self::Major,
+            "minor" => Self::Minor,
+            "trivial" => Self::Trivial,
+            "suggestion" => Self::Suggestion,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<Severity> for String {
+    fn from(value: Severity) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 状态机
+// ---------------------------------------------------------------------------
+
+/// 桌面端可触发的缺陷流转动作，取值即后端路由后缀（/defects/{id}/{action}）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DefectAction {
+    Submit,
+    Assign,
+    Process,
+    Resolve,
+    Reject,
+    VerifyPass,
+    VerifyFail,
+    Close,
+}
+
+impl DefectAction {
+    pub const ALL: [DefectAction; 8] = [
+        Self::Submit,
+        Self::Assign,
+        Self::Process,
+        Self::Resolve,
+        Self::Reject,
+        Self::VerifyPass,
+        Self::VerifyFail,
+        Self::Close,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Submit => "submit",
+            Self::Assign => "assign",
+            Self::Process => "process",
+            Self::Resolve => "resolve",
+            Self::Reject => "reject",
+            Self::VerifyPass => "verify-pass",
+            Self::VerifyFail => "verify-fail",
+            Self::Close => "close",
+        }
+    }
+
+    /// submit / assign / process / verify-* / close 与 DefectAgentController 的状态校验一致；
+    /// 后端对 resolve / reject 只校验权限不校验状态，这里额外限定为处理中的状态，
+    /// 避免在草稿或已关闭的缺陷上误操作（客户端更严格，不会放行后端会拒绝的请求）
+    pub fn is_allowed_from(&self, status: &DefectStatus) -> bool {
+        use DefectStatus as S;
+        match self {
+            Self::Submit => matches!(status, S::Draft | S::Awaiting),
+            Self::Assign => matches!(status, S::Submitted | S::Assigned),
+            Self::Process => matches!(status, S::Assigned),
+            Self::Resolve => matches!(status, S::Submitted | S::Assigned | S::Processing),
+            Self::Reject => matches!(status, S::Submitted | S::Assigned | S::Processing),
+            Self::VerifyPass | Self::VerifyFail => matches!(status, S::Verifying),
+            Self::Close => matches!(status, S::Verifying | S::Resolved | S::Rejected),
+        }
+    }
+
+    /// 动作成功后的目标状态（submit 之后由后端决定是否直接指派，这里按 submitted 估算）
+    pub fn target_status(&self) -> DefectStatus {
+        match self {
+            Self::Submit => DefectStatus::Submitted,
+            Self::Assign => DefectStatus::Assigned,
+            Self::Process => DefectStatus::Processing,
+            Self::Resolve => DefectStatus::Verifying,
+            Self::Reject => DefectStatus::Rejected,
+            Self::VerifyPass => DefectStatus::Closed,
+            Self::VerifyFail => DefectStatus::Processing,
+            Self::Close => DefectStatus::Closed,
+        }
+    }
+
+    /// 需要附带说明文字的动作（resolution / reason）
+    pub fn requires_text(&self) -> bool {
+        matches!(self, Self::Resolve | Self::Reject | Self::VerifyFail)
+    }
+
+    /// 需要指定处理人的动作
+    pub fn requires_assignee(&self) -> bool {
+        matches!(self, Self::Assign)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 实体
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectAttachment {
+    pub id: String,
+    #[serde(default)]
+    pub file_name: String,
+    #[serde(default)]
+    pub file_size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uploaded_at: Option<String>,
+    /// file / screenshot / log-request / log-error
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub attachment_type: Option<String>,
+    #[serde(default)]
+    pub is_system_generated: bool,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Defect {
+    pub id: String,
+    #[serde(default)]
+    pub defect_no: String,
+    #[serde(default)]
+    pub template_id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub raw_content: String,
+    #[serde(default)]
+    pub structured_data: HashMap<String, String>,
+    #[serde(default)]
+    pub attachments: Vec<DefectAttachment>,
+    pub status: DefectStatus,
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub reporter_id: String,
+    #[serde(default)]
+    pub reporter_name: Option<String>,
+    #[serde(default)]
+    pub reporter_avatar_file_name: Option<String>,
+    #[serde(default)]
+    pub assignee_id: Option<String>,
+    #[serde(default)]
+    pub assignee_name: Option<String>,
+    #[serde(default)]
+    pub assignee_avatar_file_name: Option<String>,
+    #[serde(default)]
+    pub reporter_unread: bool,
+    #[serde(default)]
+    pub assignee_unread: bool,
+    /// reporter | assignee
+    #[serde(default)]
+    pub last_comment_by: Option<String>,
+    #[serde(default)]
+    pub resolution: Option<String>,
+    #[serde(default)]
+    pub resolved_by_id: Option<String>,
+    #[serde(default)]
+    pub resolved_by_name: Option<String>,
+    #[serde(default)]
+    pub resolved_at: Option<String>,
+    #[serde(default)]
+    pub reject_reason: Option<String>,
+    #[serde(default)]
+    pub rejected_by_id: Option<String>,
+    #[serde(default)]
+    pub rejected_by_name: Option<String>,
+    #[serde(default)]
+    pub verified_by_id: Option<String>,
+    #[serde(default)]
+    pub verified_by_name: Option<String>,
+    #[serde(default)]
+    pub verified_at: Option<String>,
+    #[serde(default)]
+    pub verify_fail_reason: Option<String>,
+    #[serde(default)]
+    pub submitted_at: Option<String>,
+    #[serde(default)]
+    pub closed_at: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectMessage {
+    pub id: String,
+    #[serde(default)]
+    pub defect_id: String,
+    #[serde(default)]
+    pub seq: i64,
+    /// user / assistant / system
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub user_name: Option<String>,
+    #[serde(default)]
+    pub avatar_file_name: Option<String>,
+    #[serde(default)]
+    pub content: String,
+    /// human / ai
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub agent_name: Option<String>,
+    #[serde(default)]
+    pub attachment_ids: Option<Vec<String>>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectTemplate {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub example_content: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// 可指派用户（/api/defect-agent/users，AdminUser 兼容形状）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectUser {
+    pub user_id: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub avatar_file_name: Option<String>,
+    #[serde(default)]
+    pub resolved_defect_count: i64,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectStats {
+    #[serde(default)]
+    pub total: i64,
+    #[serde(default)]
+    pub status_counts: HashMap<String, i64>,
+    #[serde(default)]
+    pub severity_counts: HashMap<String, i64>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+// ---------------------------------------------------------------------------
+// 响应包装（与 DefectAgentController 返回的匿名对象一一对应）
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectListResponse {
+    #[serde(default)]
+    pub items: Vec<Defect>,
+    #[serde(default)]
+    pub total: i64,
+    #[serde(default)]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub offset: Option<i64>,
+    /// 下一页游标（客户端生成；为空表示已到末页）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectEnvelope {
+    pub defect: Defect,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectDetailResponse {
+    pub defect: Defect,
+    #[serde(default)]
+    pub messages: Vec<DefectMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectMessagesResponse {
+    #[serde(default)]
+    pub messages: Vec<DefectMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendDefectMessageResponse {
+    pub message: DefectMessage,
+    #[serde(default)]
+    pub defect: Option<Defect>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddDefectAttachmentResponse {
+    pub attachment: DefectAttachment,
+    #[serde(default)]
+    pub defect: Option<Defect>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectItemsResponse<T> {
+    #[serde(default = "Vec::new")]
+    pub items: Vec<T>,
+}
+
+// ---------------------------------------------------------------------------
+// 列表筛选
+// ---------------------------------------------------------------------------
+
+/// 缺陷列表筛选条件（list_defects / list_all_defects / 本地缓存查询共用）
+/// - 服务端只支持 scope / status / severity / assigneeId，且固定按 createdAt 倒序分页；
+///   其余条件（reporter、日期、关键字、排序）需拉取全量后在客户端过滤与排序
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectListFilter {
+    /// 服务端 filter 语义：all / submitted / assigned / completed / rejected
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub status: Option<DefectStatus>,
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    #[serde(default)]
+    pub assignee_id: Option<String>,
+    #[serde(default)]
+    pub reporter_id: Option<String>,
+    /// 创建时间下限（含），ISO 日期或时间
+    #[serde(default)]
+    pub created_from: Option<String>,
+    /// 创建时间上限（含），ISO 日期或时间；只给日期时包含当天
+    #[serde(default)]
+    pub created_to: Option<String>,
+    /// 匹配编号 / 标题 / 正文（不区分大小写）
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// createdAt（默认）/ updatedAt / severity / status / defectNo
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// asc / desc（默认）
+    #[serde(default)]
+    pub order: Option<String>,
+    /// 上一页返回的 nextCursor
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+/// 支持的排序字段
+const SORT_KEYS: [&str; 5] = ["createdAt", "updatedAt", "severity", "status", "defectNo"];
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+impl DefectListFilter {
+    /// 是否为“无筛选”的全量查询（用于决定本地缓存是整体替换还是增量合并）
+    pub fn is_unfiltered(&self) -> bool {
+        self.scope().is_none()
+            && self.status.is_none()
+            && self.severity.is_none()
+            && self.assignee_id().is_none()
+            && self.reporter_id().is_none()
+            && self.created_from().is_none()
+            && self.created_to().is_none()
+            && self.keyword().is_none()
+    }
+
+    pub fn scope(&self) -> Option<&str> {
+        non_empty(&self.scope)
+    }
+
+    pub fn assignee_id(&self) -> Option<&str> {
+        non_empty(&self.assignee_id)
+    }
+
+    pub fn reporter_id(&self) -> Option<&str> {
+        non_empty(&self.reporter_id)
+    }
+
+    pub fn created_from(&self) -> Option<&str> {
+        non_empty(&self.created_from)
+    }
+
+    pub fn created_to(&self) -> Option<&str> {
+        non_empty(&self.created_to)
+    }
+
+    pub fn keyword(&self) -> Option<&str> {
+        non_empty(&self.keyword)
+    }
+
+    pub fn sort(&self) -> &str {
+        non_empty(&self.sort).unwrap_or("createdAt")
+    }
+
+    pub fn is_ascending(&self) -> bool {
+        non_empty(&self.order)
+            .map(|o| o.eq_ignore_ascii_case("asc"))
+            .unwrap_or(false)
+    }
+
+    /// 拒绝未知的排序字段与方向，避免静默退回默认排序
+    pub fn validate(&self) -> Result<(), String> {
+        if !SORT_KEYS.contains(&self.sort()) {
+            return Err(format!("不支持的排序字段：{}", self.sort()));
+        }
+        match non_empty(&self.order) {
+            Some(o) if !o.eq_ignore_ascii_case("asc") && !o.eq_ignore_ascii_case("desc") => {
+                Err(format!("不支持的排序方向：{}", o))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 是否包含服务端不支持的条件（需全量拉取后在本地过滤 / 排序 / 分页）
+    pub fn needs_local_query(&self) -> bool {
+        self.reporter_id().is_some()
+            || self.created_from().is_some()
+            || self.created_to().is_some()
+            || self.keyword().is_some()
+            || self.sort() != "createdAt"
+            || self.is_ascending()
+    }
+
+    /// 游标即 offset；非法游标按第一页处理
+    pub fn offset(&self) -> i64 {
+        non_empty(&self.cursor)
+            .and_then(|c| c.parse::<i64>().ok())
+            .unwrap_or(0)
+            .max(0)
+    }
+
+    pub fn matches(&self, defect: &Defect) -> bool {
+        if let Some(status) = &self.status {
+            if &defect.status != status {
+                return false;
+            }
+        }
+        if let Some(severity) = &self.severity {
+            if defect.severity.as_ref() != Some(severity) {
+                return false;
+            }
+        }
+        if let Some(assignee) = self.assignee_id() {
+            if defect.assignee_id.as_deref() != Some(assignee) {
+                return false;
+            }
+        }
+        if let Some(reporter) = self.reporter_id() {
+            if defect.reporter_id != reporter {
+                return false;
+            }
+        }
+        // ISO 时间字符串可直接按字典序比较；上限只给日期时按同长度前缀比较，即包含当天
+        if let Some(from) = self.created_from() {
+            if defect.created_at.as_str() < from {
+                return false;
+            }
+        }
+        if let Some(to) = self.created_to() {
+            let created = defect
+                .created_at
+                .get(..to.len())
+                .unwrap_or(&defect.created_at);
+            if created > to {
+                return false;
+            }
+        }
+        if let Some(keyword) = self.keyword() {
+            let keyword = keyword.to_lowercase();
+            let hit = defect.defect_no.to_lowercase().contains(&keyword)
+                || defect
+                    .title
+                    .as_deref()
+                    .unwrap_or_default()
+                    .to_lowercase()
+                    .contains(&keyword)
+                || defect.raw_content.to_lowercase().contains(&keyword);
+            if !hit {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn sort_defects(&self, defects: &mut [Defect]) {
+        let sort = self.sort().to_string();
+        defects.sort_by(|a, b| {
+            let ord = match sort.as_str() {
+                "updatedAt" => a.updated_at.cmp(&b.updated_at),
+                // 默认倒序时最严重的排最前
+                "severity" => severity_rank(b).cmp(&severity_rank(a)),
+                "status" => a.status.as_str().cmp(b.status.as_str()),
+                "defectNo" => defect_no_key(a).cmp(&defect_no_key(b)),
+                _ => a.created_at.cmp(&b.created_at),
+            };
+            if self.is_ascending() {
+                ord
+            } else {
+                ord.reverse()
+            }
+        });
+    }
+}
+
+fn severity_rank(defect: &Defect) -> u8 {
+    defect
+        .severity
+        .as_ref()
+        .map(Severity::rank)
+        .unwrap_or(u8::MAX)
+}
+
+/// 缺陷编号为纯数字，按数值排序；非数字编号排在最后
+fn defect_no_key(defect: &Defect) -> (u64, String) {
+    (
+        defect.defect_no.parse::<u64>().unwrap_or(u64::MAX),
+        defect.defect_no.clone(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition_table_matches_controller_checks() {
+        use DefectAction as A;
+        use DefectStatus as S;
+        let cases = [
+            (S::Draft, vec![A::Submit]),
+            (S::Awaiting, vec![A::Submit]),
+            (S::Submitted, vec![A::Assign, A::Resolve, A::Reject]),
+            (
+                S::Assigned,
+                vec![A::Assign, A::Process, A::Resolve, A::Reject],
+            ),
+            (S::Processing, vec![A::Resolve, A::Reject]),
+            (S::Verifying, vec![A::VerifyPass, A::VerifyFail, A::Close]),
+            (S::Resolved, vec![A::Close]),
+            (S::Rejected, vec![A::Close]),
+            (S::Closed, vec![]),
+            (S::Reviewing, vec![]),
+            (S::Unknown("archived".to_string()), vec![]),
+        ];
+        for (status, expected) in cases {
+            assert_eq!(status.allowed_actions(), expected, "status {}", status);
+        }
+    }
+
+    #[test]
+    fn target_status_of_each_action_is_allowed_to_continue() {
+        assert_eq!(
+            DefectAction::Resolve.target_status(),
+            DefectStatus::Verifying
+        );
+        assert!(DefectAction::VerifyFail
+            .target_status()
+            .allowed_actions()
+            .contains(&DefectAction::Resolve));
+        assert!(DefectAction::Close
+            .target_status()
+            .allowed_actions()
+            .is_empty());
+    }
+
+    #[test]
+    fn unknown_status_round_trips_verbatim() {
+        let status: DefectStatus = serde_json::from_str("\"Archived\"").unwrap();
+        assert_eq!(status, DefectStatus::Unknown("Archived".to_string()));
+        assert_eq!(serde_json::to_string(&status).unwrap(), "\"Archived\"");
+
+        let known: DefectStatus = serde_json::from_str("\" Processing \"").unwrap();
+        assert_eq!(known, DefectStatus::Processing);
+        assert_eq!(serde_json::to_string(&known).unwrap(), "\"processing\"");
+
+        let severity: Severity = serde_json::from_str("\"urgent\"").unwrap();
+        assert_eq!(severity, Severity::Unknown("urgent".to_string()));
+        assert_eq!(serde_json::to_string(&severity).unwrap(), "\"urgent\"");
+    }
+
+    #[test]
+    fn action_serde_uses_route_suffix() {
+        for action in DefectAction::ALL {
+            let json = serde_json::to_string(&action).unwrap();
+            assert_eq!(json, format!("\"{}\"", action.as_str()));
+            let back: DefectAction = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, action);
+        }
+    }
+
+    #[test]
+    fn list_filter_splits_server_and_local_conditions() {
+        let server_only = DefectListFilter {
+            scope: Some("assigned".to_string()),
+            status: Some(DefectStatus::Processing),
+            sort: Some("createdAt".to_string()),
+            order: Some("DESC".to_string()),
+            ..Default::default()
+        };
+        assert!(server_only.validate().is_ok());
+        assert!(!server_only.needs_local_query());
+
+        for local in [
+            DefectListFilter {
+                keyword: Some("登录".to_string()),
+                ..Default::default()
+            },
+            DefectListFilter {
+                sort: Some("severity".to_string()),
+                ..Default::default()
+            },
+            DefectListFilter {
+                order: Some("asc".to_string()),
+                ..Default::default()
+            },
+        ] {
+            assert!(local.validate().is_ok());
+            assert!(local.needs_local_query());
+        }
+
+        let bad_sort = DefectListFilter {
+            sort: Some("priority".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_sort.validate().is_err());
+        let bad_order = DefectListFilter {
+            order: Some("up".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_order.validate().is_err());
+    }
+}
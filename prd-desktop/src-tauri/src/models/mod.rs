@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+mod defect;
+
+pub use defect::*;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
@@ -117,6 +121,22 @@ pub struct GroupInfo {
     pub member_count: i32,
 }
 
+/// 加入前的邀请预览（不需要是群成员）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupInvitePreview {
+    pub group_id: String,
+    pub group_name: String,
+    #[serde(default)]
+    pub prd_title: Option<String>,
+    pub member_count: i32,
+    #[serde(default)]
+    pub owner_display_name: Option<String>,
+    /// 当前用户已在群内时，前端直接打开群组而不是再次加入
+    #[serde(default)]
+    pub already_member: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GroupMemberInfo {
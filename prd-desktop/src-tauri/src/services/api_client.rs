@@ -5,6 +5,9 @@ use std::time::Duration;
 use tokio_util::sync::CancellationToken;
 
 use crate::models::{ApiError, ApiResponse, LoginResponse};
+use crate::services::client_log;
+use crate::services::network_inspector::{self, NetworkLogId};
+use crate::services::presence;
 
 /// 正式 API 地址由打包环境注入；本地调试仍默认连接本机 API。
 fn compiled_default_api_url() -> &'static str {
@@ -58,17 +61,21 @@ pub fn get_auth_token() -> Option<String> {
     AUTH_TOKEN.read().unwrap().clone()
 }
 
+/// 获取当前登录用户 id（用于群主等权限判断）
+pub fn get_auth_user_id() -> Option<String> {
+    AUTH_USER_ID.read().unwrap().clone()
+}
+
+/// 获取当前 refresh 会话 (userId, refreshToken, sessionKey, clientType)；CLI 用于落盘登录态
+pub fn get_auth_session() -> Option<(String, String, String, String)> {
+    ApiClient::get_refresh_ctx()
+}
+
 /// 获取当前 API 基础 URL
-#[allow(dead_code)]
 pub fn get_api_base_url() -> String {
     API_BASE_URL.read().unwrap().clone()
 }
 
-/// 获取当前 client id（用于附件上传等手动拼 header 的场景）
-pub fn get_client_id_pub() -> Option<String> {
-    CLIENT_ID.read().unwrap().clone()
-}
-
 /// 获取默认 API 地址
 pub fn get_default_api_url() -> String {
     configured_default_api_url()
@@ -98,8 +105,9 @@ fn start_desktop_presence_heartbeat() {
                 break;
             }
 
-            // 仅在有 token 时发送心跳
-            if AUTH_TOKEN.read().unwrap().is_some() {
+            // 仅在有 token 且用户未空闲、未锁屏时发送心跳；期间服务端会自然将我们标为离线
+            presence::refresh_screen_locked().await;
+            if AUTH_TOKEN.read().unwrap().is_some() && !presence::is_idle() {
                 let base_url = API_BASE_URL.read().unwrap().clone();
                 let url = format!(
                     "{}/api/v1/desktop/presence/heartbeat",
@@ -237,8 +245,7 @@ impl ApiClient {
         }
 
         let url = format!("{}/api/v1/auth/refresh", Self::get_base_url());
-        #[cfg(debug_assertions)]
-        eprintln!("[api] POST {} (refresh)", url);
+        tracing::debug!("POST {} (refresh)", url);
 
         let req = RefreshRequest {
             refresh_token,
@@ -248,8 +255,8 @@ impl ApiClient {
         };
 
         let request = self.apply_common_headers(self.client.post(&url).json(&req));
-        let response = request
-            .send()
+        let response = self
+            .send_logged(request)
             .await
             .map_err(|e| format!("Refresh request failed: {}", e))?;
 
@@ -290,6 +297,72 @@ impl ApiClient {
         Ok(false)
     }
 
+    /// 所有 ApiClient 请求的统一出口，见 execute_logged
+    async fn send_logged(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        execute_logged(request, false).await
+    }
+
+    /// 按原样重发一次请求（网络检查器的 replay）：鉴权等公共头取当前值，而不是录制时的值
+    pub async fn send_raw(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        content_type: Option<&str>,
+        body: Option<Vec<u8>>,
+    ) -> Result<reqwest::Response, String> {
+        let mut request = self.apply_common_headers(self.client.request(method, url));
+        if let Some(content_type) = content_type {
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        self.send_logged(request)
+            .await
+            .map_err(|e| format!("Request failed: {}", e))
+    }
+
+    /// 下载文件（附件等），返回未读取响应体的响应：
+    /// - 相对路径按 API 地址解析；与 API 同源的地址带鉴权头，401 时刷新 token 重试一次
+    /// - 其他来源（对象存储签名 URL 等）不带鉴权头
+    pub async fn download(&self, url: &str) -> Result<reqwest::Response, String> {
+        let base = Self::get_base_url();
+        let url = if url.starts_with("http://") || url.starts_with("https://") {
+            url.to_string()
+        } else {
+            format!(
+                "{}/{}",
+                base.trim_end_matches('/'),
+                url.trim_start_matches('/')
+            )
+        };
+        if !is_same_origin(&url, &base) {
+            return send_streaming(build_http_client(&url).get(&url))
+                .await
+                .map_err(|e| format!("Request failed: {}", e));
+        }
+
+        tracing::debug!("GET {} (download)", url);
+        let mut refreshed = false;
+        loop {
+            let request = self.apply_common_headers(self.client.get(&url));
+            let response = send_streaming(request)
+                .await
+                .map_err(|e| format!("Request failed: {}", e))?;
+            if response.status() == StatusCode::UNAUTHORIZED
+                && !refreshed
+                && self.try_refresh().await.unwrap_or(false)
+            {
+                refreshed = true;
+                continue;
+            }
+            return Ok(response);
+        }
+    }
+
     /// 尝试刷新 access token（用于 SSE 场景手动处理 401）
     pub async fn refresh_auth(&self) -> Result<bool, String> {
         self.try_refresh().await
@@ -298,14 +371,13 @@ impl ApiClient {
     pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<ApiResponse<T>, String> {
         let url = Self::build_url(path);
 
-        #[cfg(debug_assertions)]
-        eprintln!("[api] GET {}", url);
+        tracing::debug!("GET {}", url);
 
         for attempt in 0..2 {
             let request = self.apply_common_headers(self.client.get(&url));
 
-            let response = request
-                .send()
+            let response = self
+                .send_logged(request)
                 .await
                 .map_err(|e| format!("Request failed: {}", e))?;
 
@@ -319,8 +391,7 @@ impl ApiClient {
                 continue;
             }
 
-            #[cfg(debug_assertions)]
-            eprintln!("[api] <- {} {}", status.as_u16(), url);
+            tracing::info!("<- {} {}", status.as_u16(), url);
 
             let text = response
                 .text()
@@ -383,14 +454,13 @@ impl ApiClient {
     ) -> Result<ApiResponse<T>, String> {
         let url = Self::build_url(path);
 
-        #[cfg(debug_assertions)]
-        eprintln!("[api] POST {}", url);
+        tracing::debug!("POST {}", url);
 
         for attempt in 0..2 {
             let request = self.apply_common_headers(self.client.post(&url).json(body));
 
-            let response = request
-                .send()
+            let response = self
+                .send_logged(request)
                 .await
                 .map_err(|e| format!("Request failed: {}", e))?;
 
@@ -404,8 +474,7 @@ impl ApiClient {
             }
 
             let headers = format!("{:?}", response.headers());
-            #[cfg(debug_assertions)]
-            eprintln!("[api] <- {} {}", status.as_u16(), url);
+            tracing::info!("<- {} {}", status.as_u16(), url);
 
             let text = response
                 .text()
@@ -465,14 +534,13 @@ impl ApiClient {
     ) -> Result<ApiResponse<T>, String> {
         let url = Self::build_url(path);
 
-        #[cfg(debug_assertions)]
-        eprintln!("[api] PUT {}", url);
+        tracing::debug!("PUT {}", url);
 
         for attempt in 0..2 {
             let request = self.apply_common_headers(self.client.put(&url).json(body));
 
-            let response = request
-                .send()
+            let response = self
+                .send_logged(request)
                 .await
                 .map_err(|e| format!("Request failed: {}", e))?;
 
@@ -484,8 +552,7 @@ impl ApiClient {
                 continue;
             }
 
-            #[cfg(debug_assertions)]
-            eprintln!("[api] <- {} {}", status.as_u16(), url);
+            tracing::info!("<- {} {}", status.as_u16(), url);
 
             let text = response
                 .text()
@@ -542,19 +609,17 @@ impl ApiClient {
     pub async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<ApiResponse<T>, String> {
         let url = Self::build_url(path);
 
-        #[cfg(debug_assertions)]
-        eprintln!("[api] DELETE {}", url);
+        tracing::debug!("DELETE {}", url);
 
         let request = self.apply_common_headers(self.client.delete(&url));
 
-        let response = request
-            .send()
+        let response = self
+            .send_logged(request)
             .await
             .map_err(|e| format!("Request failed: {}", e))?;
 
         let status = response.status();
-        #[cfg(debug_assertions)]
-        eprintln!("[api] <- {} {}", status.as_u16(), url);
+        tracing::info!("<- {} {}", status.as_u16(), url);
         let text = response
             .text()
             .await
@@ -604,14 +669,13 @@ impl ApiClient {
     ) -> Result<ApiResponse<T>, String> {
         let url = Self::build_url(path);
 
-        #[cfg(debug_assertions)]
-        eprintln!("[api] PATCH {}", url);
+        tracing::debug!("PATCH {}", url);
 
         for attempt in 0..2 {
             let request = self.apply_common_headers(self.client.patch(&url).json(body));
 
-            let response = request
-                .send()
+            let response = self
+                .send_logged(request)
                 .await
                 .map_err(|e| format!("Request failed: {}", e))?;
 
@@ -623,8 +687,7 @@ impl ApiClient {
                 continue;
             }
 
-            #[cfg(debug_assertions)]
-            eprintln!("[api] <- {} {}", status.as_u16(), url);
+            tracing::info!("<- {} {}", status.as_u16(), url);
 
             let text = response
                 .text()
@@ -689,8 +752,7 @@ impl ApiClient {
     ) -> Result<ApiResponse<T>, String> {
         let url = Self::build_url(path);
 
-        #[cfg(debug_assertions)]
-        eprintln!("[api] POST (multipart) {}", url);
+        tracing::debug!("POST (multipart) {}", url);
 
         for attempt in 0..2 {
             let part = reqwest::multipart::Part::bytes(file_bytes.clone())
@@ -700,8 +762,8 @@ impl ApiClient {
             let form = reqwest::multipart::Form::new().part("file", part);
 
             let request = self.apply_common_headers(self.client.post(&url).multipart(form));
-            let response = request
-                .send()
+            let response = self
+                .send_logged(request)
                 .await
                 .map_err(|e| format!("Request failed: {}", e))?;
 
@@ -714,8 +776,7 @@ impl ApiClient {
                 continue;
             }
 
-            #[cfg(debug_assertions)]
-            eprintln!("[api] <- {} {}", status.as_u16(), url);
+            tracing::info!("<- {} {}", status.as_u16(), url);
 
             let text = response
                 .text()
@@ -778,6 +839,90 @@ fn is_localhost_url(api_base_url: &str) -> bool {
     )
 }
 
+/// scheme / host / port 均相同（端口缺省时取协议默认端口）
+fn is_same_origin(a: &str, b: &str) -> bool {
+    match (Url::parse(a), Url::parse(b)) {
+        (Ok(a), Ok(b)) => {
+            a.scheme() == b.scheme()
+                && a.host_str() == b.host_str()
+                && a.port_or_known_default() == b.port_or_known_default()
+        }
+        _ => false,
+    }
+}
+
+/// 请求统一出口：记录方法、路径、状态、耗时与脱敏后的请求头到客户端日志；
+/// 开发者模式下同时交给网络检查器采集（非流式请求会先读完响应体再重新包装返回）
+async fn execute_logged(
+    request: reqwest::RequestBuilder,
+    streaming: bool,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let (client, built) = request.build_split();
+    let request = built?;
+    let method = request.method().to_string();
+    let path = client_log::loggable_path(request.url());
+    let headers = client_log::redact_headers(request.headers());
+    let capture = network_inspector::begin(&request, streaming);
+
+    let started = std::time::Instant::now();
+    let result = client.execute(request).await;
+    client_log::record_api(
+        &method,
+        path,
+        result.as_ref().ok().map(|r| r.status().as_u16()),
+        started.elapsed(),
+        result.as_ref().err().map(|e| e.to_string()),
+        headers,
+    );
+
+    let Some(capture) = capture else {
+        return result;
+    };
+    let response = match result {
+        Ok(r) => r,
+        Err(e) => {
+            capture.finish(None, None, None, Some(e.to_string()));
+            return Err(e);
+        }
+    };
+    let status = response.status();
+    if streaming {
+        capture.finish(Some(status.as_u16()), Some(response.headers()), None, None);
+        return Ok(response);
+    }
+
+    let version = response.version();
+    let headers = response.headers().clone();
+    let body = match response.bytes().await {
+        Ok(b) => b,
+        Err(e) => {
+            capture.finish(
+                Some(status.as_u16()),
+                Some(&headers),
+                None,
+                Some(e.to_string()),
+            );
+            return Err(e);
+        }
+    };
+    let id = capture.id();
+    capture.finish(Some(status.as_u16()), Some(&headers), Some(&body), None);
+
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    rebuilt.extensions_mut().insert(NetworkLogId(id));
+    Ok(reqwest::Response::from(rebuilt))
+}
+
+/// SSE / 下载等流式请求的发送入口（代替 RequestBuilder::send），响应体不被读取
+pub async fn send_streaming(
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, reqwest::Error> {
+    execute_logged(request, true).await
+}
+
 /// 统一构建 HTTP client：
 /// - 对 localhost/127.0.0.1/::1 自动绕过系统/环境代理，避免被全局代理截胡导致 503
 /// - 其他地址保持 reqwest 默认行为（允许使用环境代理）
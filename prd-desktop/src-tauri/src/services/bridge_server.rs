@@ -0,0 +1,555 @@
+//! 本机桥接 API：浏览器扩展 / 脚本把网页选区、Jira 工单等内容直接送进 PRD Agent。
+//!
+//! - 默认关闭；开启后只监听 127.0.0.1
+//! - 每个调用方先在桌面端配对，拿到一次性展示的 token；请求须带 `Authorization: Bearer <token>`
+//! - 配对时登记的 origin 决定 CORS：浏览器请求的 Origin 必须与该 token 的 origin 一致；
+//!   未登记 origin 的配对只接受不带 Origin 的请求（脚本 / curl）
+//! - 业务请求使用当前登录用户的凭据，直接复用现有命令
+
+use axum::body::Body;
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Mutex, RwLock};
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+
+use crate::commands::{defect, document};
+use crate::models::{ApiError, ApiResponse};
+use crate::services::api_client;
+use crate::services::deep_link::{self, DeepLinkRoute};
+
+pub const DEFAULT_PORT: u16 = 17322;
+/// 附件以 base64 放在 JSON 里，放宽 axum 默认的 2MB 限制
+const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+
+/// 桥接设置（持久化在 config.json 的 bridge 字段）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub pairings: Vec<BridgePairing>,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl Default for BridgeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            pairings: Vec::new(),
+        }
+    }
+}
+
+/// 一个已配对的调用方
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgePairing {
+    pub id: String,
+    /// 如 chrome-extension://<id>、https://jira.example.com；为空表示仅限非浏览器调用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub token: String,
+    pub created_at: String,
+}
+
+/// 对前端展示的配对信息（不含 token）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgePairingInfo {
+    pub id: String,
+    pub origin: Option<String>,
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
+impl From<&BridgePairing> for BridgePairingInfo {
+    fn from(p: &BridgePairing) -> Self {
+        Self {
+            id: p.id.clone(),
+            origin: p.origin.clone(),
+            label: p.label.clone(),
+            created_at: p.created_at.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeStatus {
+    pub enabled: bool,
+    pub running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub pairings: Vec<BridgePairingInfo>,
+}
+
+struct RunningServer {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+}
+
+lazy_static::lazy_static! {
+    static ref SETTINGS: RwLock<BridgeSettings> = RwLock::new(BridgeSettings::default());
+    static ref SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+    static ref LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+}
+
+pub fn get_settings() -> BridgeSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// 更新设置并按需启动 / 停止 / 重启服务；配对变化无需重启（每个请求实时读取）
+pub fn set_settings(app: &AppHandle, settings: BridgeSettings) {
+    *SETTINGS.write().unwrap() = settings.clone();
+
+    let mut server = SERVER.lock().unwrap();
+    let keep = settings.enabled && server.as_ref().is_some_and(|s| s.port == settings.port);
+    if keep {
+        return;
+    }
+    if let Some(running) = server.take() {
+        let _ = running.shutdown.send(());
+    }
+    if !settings.enabled {
+        return;
+    }
+
+    let (tx, rx) = oneshot::channel();
+    *server = Some(RunningServer {
+        port: settings.port,
+        shutdown: tx,
+    });
+    tauri::async_runtime::spawn(serve(app.clone(), settings.port, rx));
+}
+
+pub fn status() -> BridgeStatus {
+    let settings = get_settings();
+    let port = SERVER.lock().unwrap().as_ref().map(|s| s.port);
+    BridgeStatus {
+        enabled: settings.enabled,
+        running: port.is_some(),
+        url: port.map(|p| format!("http://127.0.0.1:{}", p)),
+        last_error: LAST_ERROR.lock().unwrap().clone(),
+        pairings: settings.pairings.iter().map(Into::into).collect(),
+    }
+}
+
+/// 规范化 origin：scheme://host[:port]，去掉路径与末尾斜杠；非法时报错
+pub fn normalize_origin(origin: &str) -> Result<String, String> {
+    let trimmed = origin.trim().trim_end_matches('/');
+    let url = reqwest::Url::parse(trimmed).map_err(|_| format!("origin 格式不正确: {}", origin))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("origin 缺少 host: {}", origin))?;
+    Ok(match url.port() {
+        Some(port) => format!("{}://{}:{}", url.scheme(), host, port),
+        None => format!("{}://{}", url.scheme(), host),
+    })
+}
+
+/// 新建配对，返回完整配对信息（token 只在此处返回一次）
+pub fn new_pairing(origin: Option<String>, label: Option<String>) -> Result<BridgePairing, String> {
+    let origin = match origin.filter(|o| !o.trim().is_empty()) {
+        Some(o) => Some(normalize_origin(&o)?),
+        None => None,
+    };
+    Ok(BridgePairing {
+        id: uuid::Uuid::new_v4().to_string(),
+        origin,
+        label: label
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty()),
+        token: format!("pab_{}", uuid::Uuid::new_v4().simple()),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// HTTP
+// ---------------------------------------------------------------------------
+
+async fn serve(app: AppHandle, port: u16, shutdown: oneshot::Receiver<()>) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            let message = format!("桥接服务监听 {} 失败: {}", addr, e);
+            tracing::warn!("{}", message);
+            *LAST_ERROR.lock().unwrap() = Some(message);
+            let mut server = SERVER.lock().unwrap();
+            if server.as_ref().is_some_and(|s| s.port == port) {
+                *server = None;
+            }
+            return;
+        }
+    };
+    *LAST_ERROR.lock().unwrap() = None;
+    tracing::info!("bridge server listening on http://{}", addr);
+
+    let router = Router::new()
+        .route("/v1/status", get(get_status))
+        .route(
+            "/v1/sessions/{session_id}/documents",
+            post(add_document_to_session),
+        )
+        .route("/v1/defects", post(create_defect))
+        .route("/v1/groups/{group_id}/open", post(open_group))
+        .layer(middleware::from_fn(authorize))
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
+        .with_state(app);
+    let _ = axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = shutdown.await;
+        })
+        .await;
+    tracing::info!("bridge server on {} stopped", addr);
+}
+
+fn error_response(status: StatusCode, code: &str, message: &str) -> Response {
+    let body = ApiResponse::<Value> {
+        success: false,
+        data: None,
+        error: Some(ApiError {
+            code: code.to_string(),
+            message: message.to_string(),
+        }),
+    };
+    (status, Json(body)).into_response()
+}
+
+fn cors_headers(response: &mut Response, origin: &str) {
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(origin) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+    }
+    headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+}
+
+/// CORS 预检 + token 校验：
+/// - OPTIONS：Origin 属于任一配对时放行，否则 403
+/// - 其他请求：token 必须匹配某个配对；带 Origin 时还必须与该配对的 origin 一致
+async fn authorize(request: Request<Body>, next: Next) -> Response {
+    let origin = request
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .map(|o| normalize_origin(o).unwrap_or_else(|_| o.to_string()));
+    let settings = get_settings();
+
+    if request.method() == Method::OPTIONS {
+        let Some(origin) = origin.filter(|o| {
+            settings
+                .pairings
+                .iter()
+                .any(|p| p.origin.as_deref() == Some(o.as_str()))
+        }) else {
+            return StatusCode::FORBIDDEN.into_response();
+        };
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        cors_headers(&mut response, &origin);
+        let headers = response.headers_mut();
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static("GET, POST"),
+        );
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            HeaderValue::from_static("authorization, content-type"),
+        );
+        headers.insert(
+            header::ACCESS_CONTROL_MAX_AGE,
+            HeaderValue::from_static("600"),
+        );
+        return response;
+    }
+
+    let token = bearer_token(request.headers());
+    let Some(pairing) = token.and_then(|t| {
+        settings
+            .pairings
+            .iter()
+            .find(|p| token_matches(&p.token, &t))
+    }) else {
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "未配对或 token 无效",
+        );
+    };
+    if origin.is_some() && origin != pairing.origin {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "PERMISSION_DENIED",
+            "请求来源与配对时登记的 origin 不一致",
+        );
+    }
+
+    tracing::info!(
+        pairing = pairing.label.as_deref().unwrap_or(&pairing.id),
+        "bridge {} {}",
+        request.method(),
+        request.uri().path()
+    );
+    let mut response = next.run(request).await;
+    if let Some(origin) = origin {
+        cors_headers(&mut response, &origin);
+    }
+    response
+}
+
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// 定长比较 token，耗时不随首个不同字节的位置变化；expected 为空时一律不匹配
+pub(crate) fn token_matches(expected: &str, presented: &str) -> bool {
+    let (a, b) = (expected.as_bytes(), presented.as_bytes());
+    if a.is_empty() || a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 命令结果 → HTTP：ApiResponse 原样返回；命令本身出错时为 502
+fn respond<T: Serialize>(result: Result<ApiResponse<T>, String>) -> Response {
+    match result {
+        Ok(resp) => Json(resp).into_response(),
+        Err(e) => error_response(StatusCode::BAD_GATEWAY, "BRIDGE_ERROR", &e),
+    }
+}
+
+/// 桌面端未登录时的拒绝响应
+fn not_signed_in() -> Option<Response> {
+    if api_client::get_auth_token().is_some() {
+        return None;
+    }
+    Some(error_response(
+        StatusCode::UNAUTHORIZED,
+        "NOT_SIGNED_IN",
+        "PRD Agent 桌面端未登录",
+    ))
+}
+
+async fn get_status(State(app): State<AppHandle>) -> Response {
+    Json(json!({
+        "success": true,
+        "data": {
+            "version": app.package_info().version.to_string(),
+            "signedIn": api_client::get_auth_token().is_some()
+        },
+        "error": null
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddDocumentBody {
+    content: String,
+    #[serde(default)]
+    document_type: Option<String>,
+}
+
+async fn add_document_to_session(
+    Path(session_id): Path<String>,
+    Json(body): Json<AddDocumentBody>,
+) -> Response {
+    if let Some(resp) = not_signed_in() {
+        return resp;
+    }
+    if body.content.trim().is_empty() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_FORMAT",
+            "content 不能为空",
+        );
+    }
+    respond(document::add_document_to_session(session_id, body.content, body.document_type).await)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BridgeAttachment {
+    file_name: String,
+    #[serde(default)]
+    mime_type: Option<String>,
+    data_base64: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateDefectBody {
+    content: String,
+    #[serde(default)]
+    severity: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    assignee_user_id: String,
+    #[serde(default)]
+    template_id: Option<String>,
+    #[serde(default)]
+    attachments: Vec<BridgeAttachment>,
+    /// 默认创建后立即提交；传 false 只保存为草稿
+    #[serde(default)]
+    submit: Option<bool>,
+}
+
+/// 创建缺陷 → 逐个上传附件 → 提交；附件失败不阻断提交，失败项在 failedAttachments 中返回
+async fn create_defect(Json(body): Json<CreateDefectBody>) -> Response {
+    if let Some(resp) = not_signed_in() {
+        return resp;
+    }
+    let mut created = match defect::create_defect(
+        body.content,
+        body.severity.unwrap_or_else(|| "major".to_string()),
+        body.title,
+        body.assignee_user_id,
+        body.template_id,
+    )
+    .await
+    {
+        Ok(ApiResponse {
+            success: true,
+            data: Some(data),
+            ..
+        }) => data.defect,
+        other => return respond(other),
+    };
+
+    let mut attachments = Vec::new();
+    let mut failed = Vec::new();
+    for attachment in body.attachments {
+        let uploaded = defect::add_defect_attachment(
+            created.id.clone(),
+            attachment.data_base64,
+            attachment.file_name.clone(),
+            attachment
+                .mime_type
+                .unwrap_or_else(|| "application/octet-stream".to_string()),
+        )
+        .await;
+        match uploaded {
+            Ok(resp) if resp.success => attachments.extend(resp.data.map(|d| d.attachment)),
+            Ok(resp) => failed.push(json!({
+                "fileName": attachment.file_name,
+                "error": resp.error.map(|e| e.message).unwrap_or_default()
+            })),
+            Err(e) => failed.push(json!({ "fileName": attachment.file_name, "error": e })),
+        }
+    }
+
+    // 提交失败时草稿已存在：data 里照常带上草稿，调用方可据 defect.id 重试提交或自行删除
+    let mut error = None;
+    let mut status = StatusCode::OK;
+    if body.submit.unwrap_or(true) {
+        match defect::submit_defect(created.id.clone(), None).await {
+            Ok(resp) if resp.success => {
+                if let Some(data) = resp.data {
+                    created = data.defect;
+                }
+            }
+            Ok(resp) => {
+                error = Some(resp.error.unwrap_or_else(|| ApiError {
+                    code: "SUBMIT_FAILED".to_string(),
+                    message: "缺陷已创建为草稿，但提交失败".to_string(),
+                }))
+            }
+            Err(e) => {
+                status = StatusCode::BAD_GATEWAY;
+                error = Some(ApiError {
+                    code: "BRIDGE_ERROR".to_string(),
+                    message: e,
+                });
+            }
+        }
+    }
+
+    let body = json!({
+        "success": error.is_none(),
+        "data": {
+            "defect": created,
+            "attachments": attachments,
+            "failedAttachments": failed
+        },
+        "error": error
+    });
+    (status, Json(body)).into_response()
+}
+
+/// 在桌面端打开群组：校验后走 deep-link 路由，并唤起主窗口
+async fn open_group(State(app): State<AppHandle>, Path(group_id): Path<String>) -> Response {
+    let gid = group_id.trim();
+    let url = format!("{}://group/{}", deep_link::SCHEME, gid);
+    let valid = matches!(
+        deep_link::parse(&url),
+        Ok(DeepLinkRoute::Group { ref group_id, seq: None }) if group_id == gid
+    );
+    if !valid {
+        return error_response(StatusCode::BAD_REQUEST, "INVALID_FORMAT", "groupId 不合法");
+    }
+    deep_link::focus_main_window(&app);
+    let _ = deep_link::dispatch(&app, &url);
+    Json(json!({ "success": true, "data": { "url": url }, "error": null })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_origin_keeps_scheme_host_and_port_only() {
+        assert_eq!(
+            normalize_origin("https://jira.example.com/browse/ABC-1/"),
+            Ok("https://jira.example.com".to_string())
+        );
+        assert_eq!(
+            normalize_origin(" http://localhost:5173/ "),
+            Ok("http://localhost:5173".to_string())
+        );
+        assert_eq!(
+            normalize_origin("https://example.com:443"),
+            Ok("https://example.com".to_string())
+        );
+        assert_eq!(
+            normalize_origin("chrome-extension://abcdefghijklmnop"),
+            Ok("chrome-extension://abcdefghijklmnop".to_string())
+        );
+        assert!(normalize_origin("not an origin").is_err());
+        assert!(normalize_origin("file:///etc/passwd").is_err());
+    }
+
+    #[test]
+    fn pairing_tokens_match_only_exactly() {
+        let pairing = new_pairing(Some("https://a.example.com/x".into()), Some(" ext ".into()))
+            .expect("pairing");
+        assert_eq!(pairing.origin.as_deref(), Some("https://a.example.com"));
+        assert_eq!(pairing.label.as_deref(), Some("ext"));
+        assert!(token_matches(&pairing.token, &pairing.token.clone()));
+        assert!(!token_matches(&pairing.token, &pairing.token[1..]));
+        assert!(!token_matches("", ""));
+    }
+}
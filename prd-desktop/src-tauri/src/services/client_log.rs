@@ -0,0 +1,188 @@
+use reqwest::header::HeaderMap;
+use reqwest::Url;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 环形缓冲容量：只保留最近的记录，够定位“刚才那一下为什么失败”
+const CAPACITY: usize = 500;
+
+/// 需要脱敏的请求头 / query 参数（小写比较）
+const SENSITIVE_KEYS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "set-cookie",
+    "x-refresh-token",
+    "token",
+    "access_token",
+    "refresh_token",
+];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClientLogKind {
+    /// ApiClient 发出的请求
+    Api,
+    /// SSE 流的阶段变化（connected / receiving …）
+    Stream,
+    /// 流错误、鉴权过期等客户端侧错误
+    Error,
+}
+
+/// 一条客户端日志（桌面端自身视角，区别于 preview_defect_logs 的服务端日志）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientLogEntry {
+    pub seq: u64,
+    pub ts_ms: i64,
+    pub kind: ClientLogKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// 流事件的通道名（message-chunk / group-message …）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+}
+
+struct Ring {
+    next_seq: u64,
+    entries: VecDeque<ClientLogEntry>,
+}
+
+lazy_static::lazy_static! {
+    static ref RING: Mutex<Ring> = Mutex::new(Ring {
+        next_seq: 1,
+        entries: VecDeque::with_capacity(CAPACITY),
+    });
+}
+
+fn now_ms() -> i64 {
+    let dur = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_millis(0));
+    dur.as_millis() as i64
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SENSITIVE_KEYS.contains(&key.as_str())
+}
+
+fn push(mut entry: ClientLogEntry) {
+    let mut ring = RING.lock().unwrap();
+    entry.seq = ring.next_seq;
+    ring.next_seq += 1;
+    if ring.entries.len() >= CAPACITY {
+        ring.entries.pop_front();
+    }
+    ring.entries.push_back(entry);
+}
+
+fn blank(kind: ClientLogKind) -> ClientLogEntry {
+    ClientLogEntry {
+        seq: 0,
+        ts_ms: now_ms(),
+        kind,
+        method: None,
+        path: None,
+        status: None,
+        latency_ms: None,
+        channel: None,
+        message: None,
+        error: None,
+        headers: None,
+    }
+}
+
+/// 请求头脱敏：敏感头只保留 scheme（如 "Bearer ***"）
+pub fn redact_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let key = name.as_str().to_string();
+            let value = if is_sensitive(&key) {
+                let raw = value.to_str().unwrap_or_default();
+                match raw.split_once(' ') {
+                    Some((scheme, _)) => format!("{} ***", scheme),
+                    None => "***".to_string(),
+                }
+            } else {
+                value.to_str().unwrap_or("<binary>").to_string()
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// 只记录 path + query，且 query 中的 token 类参数脱敏
+pub fn loggable_path(url: &Url) -> String {
+    let mut path = url.path().to_string();
+    let pairs: Vec<String> = url
+        .query_pairs()
+        .map(|(k, v)| {
+            if is_sensitive(&k) {
+                format!("{}=***", k)
+            } else {
+                format!("{}={}", k, v)
+            }
+        })
+        .collect();
+    if !pairs.is_empty() {
+        path.push('?');
+        path.push_str(&pairs.join("&"));
+    }
+    path
+}
+
+pub fn record_api(
+    method: &str,
+    path: String,
+    status: Option<u16>,
+    latency: Duration,
+    error: Option<String>,
+    headers: BTreeMap<String, String>,
+) {
+    let mut entry = blank(ClientLogKind::Api);
+    entry.method = Some(method.to_string());
+    entry.path = Some(path);
+    entry.status = status;
+    entry.latency_ms = Some(latency.as_millis() as u64);
+    entry.error = error;
+    entry.headers = Some(headers);
+    push(entry);
+}
+
+pub fn record_stream(channel: &str, message: &str) {
+    let mut entry = blank(ClientLogKind::Stream);
+    entry.channel = Some(channel.to_string());
+    entry.message = Some(message.to_string());
+    push(entry);
+}
+
+pub fn record_error(channel: &str, error: &str) {
+    let mut entry = blank(ClientLogKind::Error);
+    entry.channel = Some(channel.to_string());
+    entry.error = Some(error.to_string());
+    push(entry);
+}
+
+/// 最近的日志（按时间升序）；limit 为空返回全部
+pub fn snapshot(limit: Option<usize>) -> Vec<ClientLogEntry> {
+    let ring = RING.lock().unwrap();
+    let skip = limit
+        .map(|l| ring.entries.len().saturating_sub(l))
+        .unwrap_or(0);
+    ring.entries.iter().skip(skip).cloned().collect()
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+use crate::commands::updater::get_updater_target_triple;
+use crate::services::logging;
+
+/// 崩溃报告中附带的日志行数
+const LOG_TAIL_LINES: usize = 200;
+/// 配置中需要抹掉的字段（小写包含匹配）
+const SECRET_CONFIG_KEYS: &[&str] = &["token", "password", "secret", "key", "clientid"];
+
+/// 一次 panic 的现场（写入 app_data_dir/crash_reports/{id}.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    /// RFC3339 本地时间
+    pub created_at: String,
+    pub app_version: String,
+    pub target: String,
+    pub os: String,
+    #[serde(default)]
+    pub thread: Option<String>,
+    pub message: String,
+    #[serde(default)]
+    pub location: Option<String>,
+    pub backtrace: String,
+    /// 崩溃前最新日志文件的末尾若干行
+    #[serde(default)]
+    pub log_tail: String,
+    /// 已脱敏的 config.json
+    #[serde(default)]
+    pub config: Option<serde_json::Value>,
+}
+
+pub fn crash_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_data_dir.join("crash_reports"))
+}
+
+/// 安装 panic hook（setup 中尽早调用）
+/// release 为 panic = "abort"：hook 是进程退出前唯一的机会，必须同步落盘，
+/// 且不能依赖可能已被持有/中毒的全局锁，因此所需的路径与版本在安装时就取好
+pub fn install(app: &AppHandle) {
+    let dir = match crash_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!("crash reporter disabled: {}", e);
+            return;
+        }
+    };
+    let config_path = app
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|d| d.join("config.json"));
+    let log_dir = logging::log_dir();
+    let app_version = app.package_info().version.to_string();
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // abort 构建中进程随即退出：先把排队中的日志刷盘，log_tail 才包含崩溃前的最后几行
+        // （unwind 构建中 panic 可能被 tokio 捕获、进程继续运行，不能停掉文件日志）
+        if cfg!(panic = "abort") {
+            logging::flush_before_exit();
+        }
+        let report = build_report(
+            info,
+            &app_version,
+            log_dir.as_deref(),
+            config_path.as_deref(),
+        );
+        match write_report(&dir, &report) {
+            Ok(path) => eprintln!("[crash] report written to {}", path.display()),
+            Err(e) => eprintln!("[crash] failed to write report: {}", e),
+        }
+        previous(info);
+    }));
+}
+
+fn build_report(
+    info: &PanicHookInfo<'_>,
+    app_version: &str,
+    log_dir: Option<&Path>,
+    config_path: Option<&Path>,
+) -> CrashReport {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string());
+    let now = chrono::Local::now();
+
+    CrashReport {
+        id: format!(
+            "{}-{}",
+            now.format("%Y%m%d-%H%M%S"),
+            &Uuid::new_v4().simple().to_string()[..8]
+        ),
+        created_at: now.to_rfc3339(),
+        app_version: app_version.to_string(),
+        target: get_updater_target_triple().to_string(),
+        os: std::env::consts::OS.to_string(),
+        thread: std::thread::current().name().map(|s| s.to_string()),
+        message: logging::redact(&message),
+        location: info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+        backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        log_tail: log_dir
+            .and_then(logging::latest_log_file)
+            .and_then(|path| logging::read_tail(&path, LOG_TAIL_LINES).ok())
+            .unwrap_or_default(),
+        config: config_path.and_then(read_sanitized_config),
+    }
+}
+
+fn read_sanitized_config(path: &Path) -> Option<serde_json::Value> {
+    let text = fs::read_to_string(path).ok()?;
+    let mut value: serde_json::Value = serde_json::from_str(&text).ok()?;
+    strip_secrets(&mut value);
+    Some(value)
+}
+
+fn strip_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                let lower = key.to_ascii_lowercase();
+                if SECRET_CONFIG_KEYS.iter().any(|k| lower.contains(k)) {
+                    *v = serde_json::Value::String("***".to_string());
+                } else {
+                    strip_secrets(v);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_secrets),
+        _ => {}
+    }
+}
+
+fn write_report(dir: &Path, report: &CrashReport) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create crash dir: {}", e))?;
+    let json = serde_json::to_string_pretty(report)
+        .map_err(|e| format!("Failed to serialize crash report: {}", e))?;
+    let path = dir.join(format!("{}.json", report.id));
+    fs::write(&path, json).map_err(|e| format!("Failed to write crash report: {}", e))?;
+    Ok(path)
+}
+
+/// 尚未处理（上报或忽略）的崩溃报告，最新的在前
+pub fn list_reports(app: &AppHandle) -> Result<Vec<CrashReport>, String> {
+    let dir = crash_dir(app)?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut reports: Vec<CrashReport> = entries
+        .flatten()
+        .filter(|e| e.path().extension().and_then(|x| x.to_str()) == Some("json"))
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|text| serde_json::from_str(&text).ok())
+        .collect();
+    reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(reports)
+}
+
+pub fn load_report(app: &AppHandle, id: &str) -> Result<CrashReport, String> {
+    // id 会拼进文件名，只接受 build_report 生成的格式
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid crash report id: {}", id));
+    }
+    list_reports(app)?
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| format!("Crash report not found: {}", id))
+}
+
+pub fn remove_report(app: &AppHandle, id: &str) -> Result<(), String> {
+    let report = load_report(app, id)?;
+    fs::remove_file(crash_dir(app)?.join(format!("{}.json", report.id)))
+        .map_err(|e| format!("Failed to delete crash report: {}", e))
+}
@@ -0,0 +1,310 @@
+//! prdagent:// 深链路由：统一解析、校验后再发给前端。
+//!
+//! 入口：冷启动参数、单实例转发（Linux/Windows）、RunEvent::Opened（macOS/iOS）、
+//! 通知点击、本地桥接 API。非法或不安全的链接只记日志，不会到达前端。
+
+use reqwest::Url;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::group;
+use crate::models::GroupInvitePreview;
+use crate::services::api_client;
+
+pub const SCHEME: &str = "prdagent";
+
+/// 原始链接事件（规范化后的 URL，兼容前端已有的处理）
+pub const DEEP_LINK_EVENT: &str = "deep-link";
+/// 解析后的类型化路由事件
+pub const DEEP_LINK_ROUTE_EVENT: &str = "deep-link-route";
+/// join 链接的邀请预览（已登录时自动拉取）
+pub const INVITE_PREVIEW_EVENT: &str = "invite-preview";
+
+const MAX_LINK_LEN: usize = 2048;
+const MAX_ID_LEN: usize = 64;
+
+/// 已支持的深链路由
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DeepLinkRoute {
+    /// prdagent://group/{groupId}[?seq=N]
+    #[serde(rename_all = "camelCase")]
+    Group { group_id: String, seq: Option<i64> },
+    /// prdagent://join/{inviteCode}
+    #[serde(rename_all = "camelCase")]
+    Join { invite_code: String },
+    /// prdagent://defect/{defectId}
+    #[serde(rename_all = "camelCase")]
+    Defect { defect_id: String },
+    /// prdagent://skill/import?url=https://...
+    #[serde(rename_all = "camelCase")]
+    SkillImport { url: String },
+}
+
+impl DeepLinkRoute {
+    /// 规范化后的链接（发给前端的 deep-link 事件使用）
+    pub fn to_url(&self) -> String {
+        match self {
+            Self::Group {
+                group_id,
+                seq: Some(seq),
+            } => format!("{}://group/{}?seq={}", SCHEME, group_id, seq),
+            Self::Group {
+                group_id,
+                seq: None,
+            } => format!("{}://group/{}", SCHEME, group_id),
+            Self::Join { invite_code } => format!("{}://join/{}", SCHEME, invite_code),
+            Self::Defect { defect_id } => format!("{}://defect/{}", SCHEME, defect_id),
+            Self::SkillImport { url } => {
+                let mut link =
+                    Url::parse(&format!("{}://skill/import", SCHEME)).expect("static deep link");
+                link.query_pairs_mut().append_pair("url", url);
+                link.to_string()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RouteEvent<'a> {
+    url: String,
+    route: &'a DeepLinkRoute,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InvitePreviewEvent {
+    invite_code: String,
+    preview: Option<GroupInvitePreview>,
+    error: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    /// 冷启动时前端可能尚未开始监听：保留最近一条，等前端主动取走
+    static ref PENDING: Mutex<Option<DeepLinkRoute>> = Mutex::new(None);
+}
+
+/// 路径段中的 id：字母、数字、- 和 _
+pub(crate) fn valid_id(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= MAX_ID_LEN
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 技能导入只允许 https（本机调试允许 http://localhost），且不得携带账号密码
+fn validate_import_url(raw: &str) -> Result<String, String> {
+    let url = Url::parse(raw.trim()).map_err(|_| "技能导入地址不合法".to_string())?;
+    let loopback = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match url.scheme() {
+        "https" => {}
+        "http" if loopback => {}
+        _ => return Err("技能导入地址必须为 https".to_string()),
+    }
+    if url.host_str().unwrap_or("").is_empty() {
+        return Err("技能导入地址缺少主机名".to_string());
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err("技能导入地址不能包含账号密码".to_string());
+    }
+    Ok(url.to_string())
+}
+
+/// 解析并校验 prdagent:// 链接
+pub fn parse(raw: &str) -> Result<DeepLinkRoute, String> {
+    let raw = raw.trim();
+    if raw.len() > MAX_LINK_LEN {
+        return Err("链接过长".to_string());
+    }
+    if raw.chars().any(|c| c.is_control()) {
+        return Err("链接包含控制字符".to_string());
+    }
+    let url = Url::parse(raw).map_err(|_| "链接格式不合法".to_string())?;
+    if !url.scheme().eq_ignore_ascii_case(SCHEME) {
+        return Err(format!("不支持的协议: {}", url.scheme()));
+    }
+    if !url.username().is_empty() || url.password().is_some() || url.port().is_some() {
+        return Err("链接格式不合法".to_string());
+    }
+    let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|p| !p.is_empty()).collect())
+        .unwrap_or_default();
+    let query = |key: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    };
+
+    match (host.as_str(), segments.as_slice()) {
+        ("group", [id]) if valid_id(id) => {
+            let seq = match query("seq") {
+                Some(v) => Some(
+                    v.parse::<i64>()
+                        .ok()
+                        .filter(|n| *n >= 0)
+                        .ok_or_else(|| "seq 不合法".to_string())?,
+                ),
+                None => None,
+            };
+            Ok(DeepLinkRoute::Group {
+                group_id: id.to_string(),
+                seq,
+            })
+        }
+        ("join", [code]) if valid_id(code) => Ok(DeepLinkRoute::Join {
+            invite_code: code.to_string(),
+        }),
+        ("defect", [id]) if valid_id(id) => Ok(DeepLinkRoute::Defect {
+            defect_id: id.to_string(),
+        }),
+        ("skill", ["import"]) => {
+            let target = query("url").ok_or_else(|| "缺少技能导入地址".to_string())?;
+            Ok(DeepLinkRoute::SkillImport {
+                url: validate_import_url(&target)?,
+            })
+        }
+        ("group" | "join" | "defect" | "skill", _) => Err(format!("链接参数不合法: {}", raw)),
+        _ => Err(format!("未知的链接: {}", raw)),
+    }
+}
+
+/// 从启动参数（或单实例转发的 argv）中找出深链
+pub fn find_in_args<I, S>(args: I) -> Option<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let prefix = format!("{}://", SCHEME);
+    args.into_iter()
+        .map(|a| a.as_ref().trim().to_string())
+        .find(|a| {
+            a.get(..prefix.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(&prefix))
+        })
+}
+
+/// 唤起主窗口
+pub fn focus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// 校验后发给前端；返回解析出的路由，非法链接返回错误且不发事件
+pub fn dispatch(app: &AppHandle, raw: &str) -> Result<DeepLinkRoute, String> {
+    let route = match parse(raw) {
+        Ok(route) => route,
+        Err(e) => {
+            tracing::warn!("rejected deep link: {}", e);
+            return Err(e);
+        }
+    };
+    let url = route.to_url();
+    let _ = app.emit(DEEP_LINK_EVENT, url.clone());
+    let _ = app.emit(DEEP_LINK_ROUTE_EVENT, RouteEvent { url, route: &route });
+    if let DeepLinkRoute::Join { invite_code } = &route {
+        spawn_invite_preview(app, invite_code.clone());
+    }
+    Ok(route)
+}
+
+/// join 链接：已登录时后台拉取邀请预览，结果通过 invite-preview 事件下发
+fn spawn_invite_preview(app: &AppHandle, invite_code: String) {
+    if api_client::get_auth_token().is_none() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let (preview, error) = match group::preview_invite(invite_code.clone()).await {
+            Ok(resp) if resp.success => (resp.data, None),
+            Ok(resp) => (
+                None,
+                Some(
+                    resp.error
+                        .map(|e| e.message)
+                        .unwrap_or_else(|| "邀请预览失败".to_string()),
+                ),
+            ),
+            Err(e) => (None, Some(e)),
+        };
+        let _ = app.emit(
+            INVITE_PREVIEW_EVENT,
+            InvitePreviewEvent {
+                invite_code,
+                preview,
+                error,
+            },
+        );
+    });
+}
+
+/// 冷启动：处理启动参数中的深链，并保留给尚未开始监听的前端
+pub fn dispatch_launch_args(app: &AppHandle) {
+    if let Some(raw) = find_in_args(std::env::args()) {
+        if let Ok(route) = dispatch(app, &raw) {
+            *PENDING.lock().unwrap() = Some(route);
+        }
+    }
+}
+
+/// 取走冷启动时尚未处理的路由（前端启动完成后调用一次）
+pub fn take_pending() -> Option<DeepLinkRoute> {
+    PENDING.lock().unwrap().take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deep_link_parser_accepts_known_routes_and_rejects_unsafe_ones() {
+        assert_eq!(
+            parse("prdagent://group/group-1?seq=42"),
+            Ok(DeepLinkRoute::Group {
+                group_id: "group-1".into(),
+                seq: Some(42)
+            })
+        );
+        assert_eq!(
+            parse("PRDAGENT://join/AbC123/"),
+            Ok(DeepLinkRoute::Join {
+                invite_code: "AbC123".into()
+            })
+        );
+        let import =
+            parse("prdagent://skill/import?url=https%3A%2F%2Fexample.com%2Fskills%2Fa.json")
+                .expect("skill import");
+        assert_eq!(
+            import,
+            DeepLinkRoute::SkillImport {
+                url: "https://example.com/skills/a.json".into()
+            }
+        );
+        assert_eq!(parse(&import.to_url()), Ok(import));
+
+        for bad in [
+            "https://group/abc",
+            "prdagent://group/",
+            "prdagent://group/a/b",
+            "prdagent://group/..%2Fetc",
+            "prdagent://group/abc?seq=-1",
+            "prdagent://defect/abc%00",
+            "prdagent://settings/open",
+            "prdagent://skill/import?url=file%3A%2F%2F%2Fetc%2Fpasswd",
+            "prdagent://skill/import?url=http%3A%2F%2Fexample.com%2Fa.json",
+            "prdagent://skill/import?url=https%3A%2F%2Fuser%3Apw%40example.com%2F",
+        ] {
+            assert!(parse(bad).is_err(), "should reject {}", bad);
+        }
+        assert_eq!(
+            find_in_args(["prd-agent-desktop", "--flag", "prdagent://defect/d-1"]),
+            Some("prdagent://defect/d-1".to_string())
+        );
+    }
+}
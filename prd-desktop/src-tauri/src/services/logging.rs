@@ -0,0 +1,211 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, TryLockError};
+use tracing::level_filters::LevelFilter;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Builder, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// 日志文件名形如 prd-agent.2026-01-01.log（按天滚动）
+pub const LOG_FILE_PREFIX: &str = "prd-agent";
+pub const LOG_FILE_SUFFIX: &str = "log";
+/// 最多保留的日志文件数（即最近 7 天）
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_LEVEL: &str = "info";
+/// 本 crate 内的模块可省略前缀，如 "services::api_client"
+const CRATE_MODULES: &[&str] = &["commands", "services", "models"];
+
+/// 日志设置（持久化在 config.json 的 logging 字段）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingConfig {
+    /// 全局级别：trace / debug / info / warn / error / off；为空时为 info
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    /// 按模块覆盖级别，如 { "services::api_client": "debug", "reqwest": "warn" }
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub modules: BTreeMap<String, String>,
+}
+
+impl LoggingConfig {
+    /// 转为 EnvFilter 指令串；级别非法时报错（EnvFilter 会把非法级别当成 target 静默接受）
+    fn directives(&self) -> Result<String, String> {
+        let level = self
+            .level
+            .as_deref()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .unwrap_or(DEFAULT_LEVEL);
+        let mut parts = vec![parse_level(level)?.to_string()];
+        for (module, level) in &self.modules {
+            let module = module.trim();
+            if module.is_empty() {
+                continue;
+            }
+            let level = parse_level(level.trim())?;
+            let first = module.split("::").next().unwrap_or_default();
+            if CRATE_MODULES.contains(&first) {
+                parts.push(format!(
+                    "{}::{}={}",
+                    env!("CARGO_CRATE_NAME"),
+                    module,
+                    level
+                ));
+            } else {
+                parts.push(format!("{}={}", module, level));
+            }
+        }
+        Ok(parts.join(","))
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level).map_err(|_| format!("Invalid log level: {}", level))
+}
+
+struct LoggingState {
+    dir: PathBuf,
+    filter: reload::Handle<EnvFilter, Registry>,
+    /// non_blocking 的后台线程随 guard 存活，drop 时刷盘
+    _guard: WorkerGuard,
+}
+
+lazy_static::lazy_static! {
+    static ref STATE: Mutex<Option<LoggingState>> = Mutex::new(None);
+    static ref BEARER: Regex = Regex::new(r"(?i)(bearer\s+)[A-Za-z0-9\-._~+/=]+").unwrap();
+    static ref SECRET_FIELD: Regex = Regex::new(
+        r#"(?i)((?:access_?token|refresh_?token|session_?key|password|token)"?\s*[:=]\s*"?)[^"\s,&}]+"#,
+    )
+    .unwrap();
+}
+
+/// 抹掉 Bearer 凭证与 token / password 类字段的值
+pub(crate) fn redact(text: &str) -> String {
+    let text = BEARER.replace_all(text, "${1}***");
+    SECRET_FIELD.replace_all(&text, "${1}***").into_owned()
+}
+
+/// 对写出的每条日志做脱敏（fmt 层每个事件只调用一次 write）
+struct Redacting<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// 初始化全局日志（run() 的 setup 中调用一次）：
+/// - 写入 dir 下按天滚动的文件，保留最近 MAX_LOG_FILES 个
+/// - debug 构建额外输出到 stderr
+pub fn init(dir: PathBuf, config: &LoggingConfig) -> Result<(), String> {
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log dir: {}", e))?;
+    let appender = Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| format!("Failed to create log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter = config
+        .directives()
+        .and_then(|d| EnvFilter::try_new(d).map_err(|e| e.to_string()))
+        .unwrap_or_else(|_| EnvFilter::new(DEFAULT_LEVEL));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(Redacting(writer));
+    let stderr_layer = cfg!(debug_assertions)
+        .then(|| tracing_subscriber::fmt::layer().with_writer(Redacting(io::stderr)));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(stderr_layer)
+        .try_init()
+        .map_err(|e| format!("Failed to init logging: {}", e))?;
+
+    *STATE.lock().unwrap() = Some(LoggingState {
+        dir,
+        filter: handle,
+        _guard: guard,
+    });
+    Ok(())
+}
+
+/// 应用新的级别设置（保存配置时调用）；日志尚未初始化时只做校验
+pub fn apply_config(config: &LoggingConfig) -> Result<(), String> {
+    let filter = EnvFilter::try_new(config.directives()?).map_err(|e| e.to_string())?;
+    if let Some(state) = STATE.lock().unwrap().as_ref() {
+        state
+            .filter
+            .reload(filter)
+            .map_err(|e| format!("Failed to apply log level: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 崩溃前把 non_blocking 队列中尚未写出的日志刷到文件（drop guard 会等待后台线程写完）
+/// 之后文件日志停止，只应在进程即将退出时调用；panic 线程若正持有锁则放弃，避免死锁
+pub(crate) fn flush_before_exit() {
+    let state = match STATE.try_lock() {
+        Ok(mut guard) => guard.take(),
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().take(),
+        Err(TryLockError::WouldBlock) => None,
+    };
+    drop(state);
+}
+
+/// 当前日志目录；未初始化时返回 None
+pub fn log_dir() -> Option<PathBuf> {
+    STATE.lock().unwrap().as_ref().map(|s| s.dir.clone())
+}
+
+/// 目录下最新的滚动日志文件
+pub fn latest_log_file(dir: &Path) -> Option<PathBuf> {
+    let suffix = format!(".{}", LOG_FILE_SUFFIX);
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.starts_with(LOG_FILE_PREFIX) && name.ends_with(&suffix)
+        })
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// 读取文件末尾 lines 行
+pub fn read_tail(path: &Path, lines: usize) -> Result<String, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read log file: {}", e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let all: Vec<&str> = text.lines().collect();
+    let skip = all.len().saturating_sub(lines);
+    Ok(all[skip..].join("\n"))
+}
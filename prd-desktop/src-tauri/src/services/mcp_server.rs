@@ -0,0 +1,779 @@
+//! MCP（Model Context Protocol）服务：把现有命令以 tools 形式暴露给 IDE 里的 AI 助手。
+//!
+//! - stdio：`prd-agent-cli mcp`（IDE 以子进程方式拉起，逐行 JSON-RPC）
+//! - HTTP：桌面端在设置中开启后监听 127.0.0.1，提供 `POST /mcp`（streamable HTTP，JSON 响应）
+//!   与 `GET /sse` + `POST /messages`（旧版 HTTP+SSE）两种传输；请求须带 `Authorization: Bearer <token>`
+//! - 所有工具使用当前登录用户的凭据；只有 allowlist 中的工具可见、可调用
+
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::commands::session::{stream_chat_run, StreamSink};
+use crate::commands::{defect, document, group, prd_comments, skill};
+use crate::models::{ApiResponse, DefectListFilter};
+use crate::services::api_client;
+use crate::services::bridge_server::{bearer_token, token_matches};
+
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+pub const DEFAULT_PORT: u16 = 17321;
+
+/// 只读工具：未配置 allowlist 时默认开放这些
+const READ_ONLY_TOOLS: &[&str] = &[
+    "get_groups",
+    "get_document_content",
+    "get_prd_comments",
+    "list_defects",
+];
+
+/// MCP 设置（持久化在 config.json 的 mcp 字段）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpSettings {
+    /// 是否在桌面端启动 HTTP 传输（stdio 传输由 CLI 提供，不受此开关影响）
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// 允许调用的工具名；写操作（create_defect / execute_skill）需显式加入
+    #[serde(default = "default_allowed_tools")]
+    pub allowed_tools: Vec<String>,
+    /// HTTP 传输的访问 token，由设置页展示给用户填入 IDE 配置；为空时拒绝所有 HTTP 请求
+    #[serde(default)]
+    pub token: String,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn default_allowed_tools() -> Vec<String> {
+    READ_ONLY_TOOLS.iter().map(|t| t.to_string()).collect()
+}
+
+impl Default for McpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            allowed_tools: default_allowed_tools(),
+            token: String::new(),
+        }
+    }
+}
+
+/// 生成新的 HTTP 传输 token
+pub fn new_token() -> String {
+    format!("mcp_{}", uuid::Uuid::new_v4().simple())
+}
+
+impl McpSettings {
+    fn allows(&self, tool: &str) -> bool {
+        self.allowed_tools.iter().any(|t| t == tool)
+    }
+}
+
+/// HTTP 传输的运行状态（供设置页展示）
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpStatus {
+    pub running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// 全部工具名及其是否已被 allowlist 放行
+    pub tools: Vec<McpToolState>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolState {
+    pub name: &'static str,
+    pub read_only: bool,
+    pub allowed: bool,
+}
+
+struct RunningServer {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+}
+
+lazy_static::lazy_static! {
+    static ref SETTINGS: RwLock<McpSettings> = RwLock::new(McpSettings::default());
+    static ref SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+    static ref LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+}
+
+pub fn get_settings() -> McpSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// 更新设置并按需启动 / 停止 / 重启 HTTP 传输（需在 tauri runtime 可用后调用）；
+/// 传入的 token 为空时沿用当前 token
+pub fn set_settings(mut settings: McpSettings) {
+    {
+        let mut current = SETTINGS.write().unwrap();
+        if settings.token.is_empty() {
+            settings.token = current.token.clone();
+        }
+        *current = settings.clone();
+    }
+
+    let mut server = SERVER.lock().unwrap();
+    let keep = settings.enabled && server.as_ref().is_some_and(|s| s.port == settings.port);
+    if keep {
+        return;
+    }
+    if let Some(running) = server.take() {
+        let _ = running.shutdown.send(());
+    }
+    if !settings.enabled {
+        return;
+    }
+
+    let (tx, rx) = oneshot::channel();
+    *server = Some(RunningServer {
+        port: settings.port,
+        shutdown: tx,
+    });
+    tauri::async_runtime::spawn(serve_http(settings.port, rx));
+}
+
+pub fn status() -> McpStatus {
+    let settings = get_settings();
+    let port = SERVER.lock().unwrap().as_ref().map(|s| s.port);
+    McpStatus {
+        running: port.is_some(),
+        url: port.map(|p| format!("http://127.0.0.1:{}/mcp", p)),
+        last_error: LAST_ERROR.lock().unwrap().clone(),
+        tools: TOOLS
+            .iter()
+            .map(|t| McpToolState {
+                name: t.name,
+                read_only: READ_ONLY_TOOLS.contains(&t.name),
+                allowed: settings.allows(t.name),
+            })
+            .collect(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tools
+// ---------------------------------------------------------------------------
+
+struct ToolSpec {
+    name: &'static str,
+    description: &'static str,
+    schema: fn() -> Value,
+}
+
+const TOOLS: &[ToolSpec] = &[
+    ToolSpec {
+        name: "get_groups",
+        description: "列出当前用户加入的 PRD 群组（含 groupId、群名、绑定的 PRD 文档）",
+        schema: || json!({ "type": "object", "properties": {} }),
+    },
+    ToolSpec {
+        name: "get_document_content",
+        description: "获取群组中 PRD 文档的完整内容（Markdown）",
+        schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "documentId": { "type": "string" },
+                    "groupId": { "type": "string" }
+                },
+                "required": ["documentId", "groupId"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "get_prd_comments",
+        description: "获取 PRD 文档的评论，可按章节（headingId）过滤",
+        schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "documentId": { "type": "string" },
+                    "groupId": { "type": "string" },
+                    "headingId": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1 }
+                },
+                "required": ["documentId", "groupId"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "list_defects",
+        description: "查询缺陷列表，支持按范围、状态、严重程度、关键字过滤与分页",
+        schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "scope": { "type": "string", "enum": ["all", "submitted", "assigned", "completed", "rejected"] },
+                    "status": { "type": "string" },
+                    "severity": { "type": "string" },
+                    "keyword": { "type": "string" },
+                    "sort": { "type": "string" },
+                    "order": { "type": "string", "enum": ["asc", "desc"] },
+                    "cursor": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1 }
+                }
+            })
+        },
+    },
+    ToolSpec {
+        name: "create_defect",
+        description: "创建并提交一条缺陷给指定处理人",
+        schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string" },
+                    "severity": { "type": "string", "enum": ["blocker", "critical", "major", "minor", "trivial", "suggestion"] },
+                    "title": { "type": "string" },
+                    "assigneeUserId": { "type": "string" },
+                    "templateId": { "type": "string" }
+                },
+                "required": ["content", "assigneeUserId"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "execute_skill",
+        description: "在指定会话中执行技能，等待生成完成后返回输出文本",
+        schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "skillKey": { "type": "string" },
+                    "sessionId": { "type": "string" },
+                    "userInput": { "type": "string" },
+                    "parameters": { "type": "object", "additionalProperties": { "type": "string" } }
+                },
+                "required": ["skillKey", "sessionId"]
+            })
+        },
+    },
+];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentArgs {
+    document_id: String,
+    group_id: String,
+    #[serde(default)]
+    heading_id: Option<String>,
+    #[serde(default)]
+    limit: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateDefectArgs {
+    content: String,
+    #[serde(default)]
+    severity: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    assignee_user_id: String,
+    #[serde(default)]
+    template_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExecuteSkillArgs {
+    skill_key: String,
+    session_id: String,
+    #[serde(default)]
+    user_input: Option<String>,
+    #[serde(default)]
+    parameters: Option<HashMap<String, String>>,
+}
+
+fn parse_args<T: serde::de::DeserializeOwned>(args: Value) -> Result<T, String> {
+    let args = if args.is_null() { json!({}) } else { args };
+    serde_json::from_value(args).map_err(|e| format!("参数错误: {}", e))
+}
+
+fn into_data<T: Serialize>(response: ApiResponse<T>) -> Result<Value, String> {
+    if !response.success {
+        return Err(response
+            .error
+            .map(|e| format!("{}: {}", e.code, e.message))
+            .unwrap_or_else(|| "请求失败".to_string()));
+    }
+    serde_json::to_value(response.data).map_err(|e| e.to_string())
+}
+
+/// 收集技能输出文本的 sink
+#[derive(Default)]
+struct CollectSink {
+    token: CancellationToken,
+    text: String,
+    error: Option<String>,
+}
+
+impl StreamSink for CollectSink {
+    fn phase(&mut self, _phase: &str) {}
+
+    fn keepalive(&mut self) {}
+
+    fn event(&mut self, event: Value) {
+        match event.get("type").and_then(|v| v.as_str()) {
+            Some("delta") => {
+                if let Some(content) = event.get("content").and_then(|v| v.as_str()) {
+                    self.text.push_str(content);
+                }
+            }
+            Some("done") => self.token.cancel(),
+            Some("error") => {
+                let message = event
+                    .get("errorMessage")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("stream error");
+                self.error(message.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.error.get_or_insert(message);
+        self.token.cancel();
+    }
+
+    fn auth_expired(&mut self) {
+        self.error("登录已过期，请在桌面端重新登录".to_string());
+    }
+
+    fn cancelled(&mut self) {}
+}
+
+async fn call_tool(name: &str, args: Value) -> Result<Value, String> {
+    if api_client::get_auth_token().is_none() {
+        return Err("PRD Agent 未登录，请先在桌面端（或 prd-agent-cli login）登录".to_string());
+    }
+    match name {
+        "get_groups" => into_data(group::get_groups().await?),
+        "get_document_content" => {
+            let a: DocumentArgs = parse_args(args)?;
+            into_data(document::get_document_content(a.document_id, a.group_id).await?)
+        }
+        "get_prd_comments" => {
+            let a: DocumentArgs = parse_args(args)?;
+            into_data(
+                prd_comments::get_prd_comments(a.document_id, a.group_id, a.heading_id, a.limit)
+                    .await?,
+            )
+        }
+        "list_defects" => {
+            let filter: DefectListFilter = parse_args(args)?;
+            into_data(defect::list_defects(Some(filter)).await?)
+        }
+        "create_defect" => {
+            let a: CreateDefectArgs = parse_args(args)?;
+            let created = defect::create_defect(
+                a.content,
+                a.severity.unwrap_or_else(|| "major".to_string()),
+                a.title,
+                a.assignee_user_id,
+                a.template_id,
+            )
+            .await?;
+            let id = created.data.as_ref().map(|d| d.defect.id.clone());
+            match id {
+                Some(id) if created.success => into_data(defect::submit_defect(id, None).await?),
+                _ => into_data(created),
+            }
+        }
+        "execute_skill" => {
+            let a: ExecuteSkillArgs = parse_args(args)?;
+            let run =
+                skill::execute_skill(a.skill_key, a.session_id, a.user_input, None, a.parameters)
+                    .await?;
+            let run_id = match into_data(run)?.get("runId").and_then(|v| v.as_str()) {
+                Some(id) => id.to_string(),
+                None => return Err("技能执行未返回 runId".to_string()),
+            };
+            let mut sink = CollectSink::default();
+            let token = sink.token.clone();
+            stream_chat_run(&run_id, 0, &token, &mut sink).await?;
+            match sink.error {
+                Some(e) => Err(e),
+                None => Ok(json!({ "runId": run_id, "output": sink.text })),
+            }
+        }
+        _ => Err(format!("未知工具: {}", name)),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// JSON-RPC
+// ---------------------------------------------------------------------------
+
+fn rpc_result(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn rpc_error(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message.into() } })
+}
+
+/// 处理一条 JSON-RPC 消息；通知（无 id）返回 None
+pub async fn handle_message(message: Value) -> Option<Value> {
+    let id = message.get("id").cloned();
+    let method = message
+        .get("method")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let Some(id) = id else {
+        // notifications/initialized、notifications/cancelled 等无需响应
+        return None;
+    };
+
+    let settings = get_settings();
+    let response = match method {
+        "initialize" => rpc_result(
+            id,
+            json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": { "listChanged": false } },
+                "serverInfo": { "name": "prd-agent", "version": env!("CARGO_PKG_VERSION") }
+            }),
+        ),
+        "ping" => rpc_result(id, json!({})),
+        "tools/list" => rpc_result(
+            id,
+            json!({
+                "tools": TOOLS
+                    .iter()
+                    .filter(|t| settings.allows(t.name))
+                    .map(|t| json!({
+                        "name": t.name,
+                        "description": t.description,
+                        "inputSchema": (t.schema)()
+                    }))
+                    .collect::<Vec<_>>()
+            }),
+        ),
+        "tools/call" => {
+            let name = params
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            if !TOOLS.iter().any(|t| t.name == name) || !settings.allows(name) {
+                return Some(rpc_error(
+                    id,
+                    -32602,
+                    format!("工具不存在或未在 allowlist 中启用: {}", name),
+                ));
+            }
+            let args = params.get("arguments").cloned().unwrap_or(Value::Null);
+            tracing::info!(tool = name, "mcp tools/call");
+            // 工具执行失败作为 isError 结果返回（让模型看到原因），而不是协议错误
+            let (text, is_error) = match call_tool(name, args).await {
+                Ok(data) => (
+                    serde_json::to_string_pretty(&data).unwrap_or_default(),
+                    false,
+                ),
+                Err(e) => (e, true),
+            };
+            rpc_result(
+                id,
+                json!({
+                    "content": [{ "type": "text", "text": text }],
+                    "isError": is_error
+                }),
+            )
+        }
+        _ => rpc_error(id, -32601, format!("Method not found: {}", method)),
+    };
+    Some(response)
+}
+
+/// 处理单条或批量消息；全部为通知时返回 None
+async fn handle_payload(payload: Value) -> Option<Value> {
+    match payload {
+        Value::Array(batch) => {
+            let mut responses = Vec::new();
+            for message in batch {
+                if let Some(resp) = handle_message(message).await {
+                    responses.push(resp);
+                }
+            }
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        message => handle_message(message).await,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// stdio transport
+// ---------------------------------------------------------------------------
+
+/// 逐行读取 stdin 的 JSON-RPC 消息并把响应逐行写到 stdout，直到 stdin 关闭
+pub async fn serve_stdio() -> std::io::Result<()> {
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(payload) => handle_payload(payload).await,
+            Err(e) => Some(rpc_error(
+                Value::Null,
+                -32700,
+                format!("Parse error: {}", e),
+            )),
+        };
+        if let Some(response) = response {
+            stdout.write_all(response.to_string().as_bytes()).await?;
+            stdout.write_all(b"\n").await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// HTTP transport
+// ---------------------------------------------------------------------------
+
+type SseSessions = Arc<Mutex<HashMap<String, mpsc::Sender<Value>>>>;
+
+async fn serve_http(port: u16, shutdown: oneshot::Receiver<()>) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            let message = format!("MCP 服务监听 {} 失败: {}", addr, e);
+            tracing::warn!("{}", message);
+            *LAST_ERROR.lock().unwrap() = Some(message);
+            let mut server = SERVER.lock().unwrap();
+            if server.as_ref().is_some_and(|s| s.port == port) {
+                *server = None;
+            }
+            return;
+        }
+    };
+    *LAST_ERROR.lock().unwrap() = None;
+    tracing::info!("mcp server listening on http://{}", addr);
+
+    let sessions: SseSessions = Arc::default();
+    let app = Router::new()
+        .route("/mcp", post(post_mcp))
+        .route("/sse", get(open_sse))
+        .route("/messages", post(post_message))
+        .with_state(sessions);
+    let _ = axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = shutdown.await;
+        })
+        .await;
+    tracing::info!("mcp server on {} stopped", addr);
+}
+
+/// 拒绝来自网页的跨域请求（防 DNS rebinding）：只接受无 Origin 或本机 Origin；
+/// 并要求 `Authorization: Bearer <token>` 与设置中的 token 一致
+fn check_origin(headers: &HeaderMap) -> Result<(), (StatusCode, &'static str)> {
+    if let Some(origin) = headers.get(header::ORIGIN) {
+        let host = origin
+            .to_str()
+            .ok()
+            .and_then(|o| reqwest::Url::parse(o).ok())
+            .and_then(|u| u.host_str().map(str::to_string));
+        if !matches!(
+            host.as_deref(),
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+        ) {
+            return Err((StatusCode::FORBIDDEN, "origin not allowed"));
+        }
+    }
+    let expected = get_settings().token;
+    match bearer_token(headers) {
+        Some(token) if token_matches(&expected, &token) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "missing or invalid token")),
+    }
+}
+
+async fn post_mcp(headers: HeaderMap, Json(payload): Json<Value>) -> Response {
+    if let Err(rejection) = check_origin(&headers) {
+        return rejection.into_response();
+    }
+    match handle_payload(payload).await {
+        Some(response) => Json(response).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+async fn open_sse(
+    State(sessions): State<SseSessions>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, &'static str)> {
+    check_origin(&headers)?;
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = mpsc::channel::<Value>(32);
+    sessions.lock().unwrap().insert(session_id.clone(), tx);
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("/messages?sessionId={}", session_id));
+    // guard 随流一起被 drop（客户端断开 / 服务停止），届时移除会话
+    let guard = SseSessionGuard {
+        sessions,
+        session_id,
+    };
+    let messages = futures::stream::unfold((rx, guard), |(mut rx, guard)| async move {
+        let message = rx.recv().await?;
+        let event = Event::default().event("message").data(message.to_string());
+        Some((Ok::<_, Infallible>(event), (rx, guard)))
+    });
+    let stream = futures::StreamExt::chain(
+        futures::stream::once(async { Ok::<_, Infallible>(endpoint) }),
+        messages,
+    );
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+struct SseSessionGuard {
+    sessions: SseSessions,
+    session_id: String,
+}
+
+impl Drop for SseSessionGuard {
+    fn drop(&mut self) {
+        self.sessions.lock().unwrap().remove(&self.session_id);
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageQuery {
+    session_id: String,
+}
+
+async fn post_message(
+    State(sessions): State<SseSessions>,
+    Query(query): Query<MessageQuery>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> Response {
+    if let Err(rejection) = check_origin(&headers) {
+        return rejection.into_response();
+    }
+    let Some(tx) = sessions.lock().unwrap().get(&query.session_id).cloned() else {
+        return (StatusCode::NOT_FOUND, "unknown session").into_response();
+    };
+    // 响应经 SSE 推回；客户端已断开时清理会话
+    tokio::spawn(async move {
+        if let Some(response) = handle_payload(payload).await {
+            if tx.send(response).await.is_err() {
+                sessions.lock().unwrap().remove(&query.session_id);
+            }
+        }
+    });
+    StatusCode::ACCEPTED.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::signed_in;
+
+    #[test]
+    fn sse_session_is_removed_when_stream_is_dropped() {
+        let sessions: SseSessions = Arc::default();
+        let (tx, _rx) = mpsc::channel::<Value>(1);
+        sessions.lock().unwrap().insert("s1".to_string(), tx);
+        let guard = SseSessionGuard {
+            sessions: sessions.clone(),
+            session_id: "s1".to_string(),
+        };
+        assert!(sessions.lock().unwrap().contains_key("s1"));
+        drop(guard);
+        assert!(sessions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn token_check_rejects_missing_and_wrong_tokens() {
+        let mut headers = HeaderMap::new();
+        assert!(bearer_token(&headers).is_none());
+        headers.insert(header::AUTHORIZATION, "Bearer mcp_abc".parse().unwrap());
+        let presented = bearer_token(&headers).unwrap();
+        assert!(token_matches("mcp_abc", &presented));
+        assert!(!token_matches("mcp_abd", &presented));
+        assert!(!token_matches("", ""));
+        assert!(new_token().starts_with("mcp_"));
+    }
+
+    #[tokio::test]
+    async fn mcp_lists_and_calls_only_allowed_tools() {
+        let (_guard, _server) = signed_in().await;
+        set_settings(McpSettings {
+            enabled: false,
+            allowed_tools: vec!["get_groups".to_string()],
+            ..Default::default()
+        });
+
+        let listed = handle_message(json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }))
+            .await
+            .expect("response");
+        let names: Vec<&str> = listed["result"]["tools"]
+            .as_array()
+            .expect("tools")
+            .iter()
+            .filter_map(|t| t["name"].as_str())
+            .collect();
+        assert_eq!(names, ["get_groups"]);
+
+        let called = handle_message(json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": { "name": "get_groups", "arguments": {} }
+        }))
+        .await
+        .expect("response");
+        assert_eq!(called["result"]["isError"], json!(false));
+        assert!(called["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("group-1"));
+
+        let denied = handle_message(json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": { "name": "create_defect", "arguments": {} }
+        }))
+        .await
+        .expect("response");
+        assert_eq!(denied["error"]["code"], json!(-32602));
+
+        let notification =
+            handle_message(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+                .await;
+        assert!(notification.is_none());
+        set_settings(McpSettings::default());
+    }
+}
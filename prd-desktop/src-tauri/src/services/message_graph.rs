@@ -0,0 +1,408 @@
+//! 会话关系图：由 replyToMessageId（回复）与 resendOfMessageId（重发）把扁平的消息列表
+//! 组装为回复线程与重发链。
+//!
+//! 消息来自本机已拉取的历史分页与群消息流（按群组缓存在内存中）；缺失的祖先消息由命令层按需补拉。
+//! 服务端重发后旧轮次对用户不可见，因此旧的候选回答只能从本机见过的消息中还原。
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::models::MessageHistoryItem;
+
+/// 每个群组最多缓存的消息数（超出时丢弃最早的）
+const MAX_CACHED_PER_GROUP: usize = 5000;
+/// 祖先链最大深度（防御异常数据形成的环）
+const MAX_DEPTH: usize = 100;
+
+/// 线程中的一个节点（子节点按 groupSeq 升序）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadNode {
+    pub message: MessageHistoryItem,
+    pub replies: Vec<ThreadNode>,
+}
+
+/// 以某条消息为中心的回复线程
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageThread {
+    /// 从根到父消息（不含自身）
+    pub ancestors: Vec<MessageHistoryItem>,
+    pub root: ThreadNode,
+    /// 仍未取到的祖先（链在此处中断）
+    pub missing_ancestor_id: Option<String>,
+}
+
+/// 同一问题的一次提问及其回答
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResendVariant {
+    pub prompt: MessageHistoryItem,
+    pub answers: Vec<MessageHistoryItem>,
+    /// 最新一次（当前可见的）提问
+    pub current: bool,
+}
+
+/// 某条回答的全部重新生成版本（按时间先后）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResendVariants {
+    pub message_id: String,
+    pub variants: Vec<ResendVariant>,
+    pub missing_ancestor_id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ConversationGraph {
+    messages: HashMap<String, MessageHistoryItem>,
+}
+
+fn seq_key(m: &MessageHistoryItem) -> (i64, String) {
+    (m.group_seq.unwrap_or(i64::MAX), m.timestamp.clone())
+}
+
+impl ConversationGraph {
+    pub fn extend(&mut self, items: impl IntoIterator<Item = MessageHistoryItem>) {
+        for m in items {
+            self.messages.insert(m.id.clone(), m);
+        }
+        if self.messages.len() > MAX_CACHED_PER_GROUP {
+            let mut all: Vec<_> = self
+                .messages
+                .values()
+                .map(|m| (seq_key(m), m.id.clone()))
+                .collect();
+            all.sort();
+            let drop = self.messages.len() - MAX_CACHED_PER_GROUP;
+            for (_, id) in all.into_iter().take(drop) {
+                self.messages.remove(&id);
+            }
+        }
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.messages.contains_key(id)
+    }
+
+    /// 沿回复链向上找到的第一条本地缺失的消息 id
+    pub fn missing_ancestor(&self, id: &str) -> Option<String> {
+        let mut seen = HashSet::new();
+        let mut cur = id.to_string();
+        while seen.insert(cur.clone()) && seen.len() <= MAX_DEPTH {
+            let m = self.messages.get(&cur)?;
+            let parent = m.reply_to_message_id.as_deref().filter(|s| !s.is_empty())?;
+            if !self.messages.contains_key(parent) {
+                return Some(parent.to_string());
+            }
+            cur = parent.to_string();
+        }
+        None
+    }
+
+    /// 重发链向上缺失的消息 id
+    fn missing_resend_origin(&self, id: &str) -> Option<String> {
+        let mut seen = HashSet::new();
+        let mut cur = id.to_string();
+        while seen.insert(cur.clone()) && seen.len() <= MAX_DEPTH {
+            let origin = self
+                .messages
+                .get(&cur)?
+                .resend_of_message_id
+                .as_deref()
+                .filter(|s| !s.is_empty())?;
+            if !self.messages.contains_key(origin) {
+                return Some(origin.to_string());
+            }
+            cur = origin.to_string();
+        }
+        None
+    }
+
+    fn replies_to(&self, id: &str) -> Vec<&MessageHistoryItem> {
+        let mut out: Vec<_> = self
+            .messages
+            .values()
+            .filter(|m| m.reply_to_message_id.as_deref() == Some(id))
+            .collect();
+        out.sort_by_key(|m| seq_key(m));
+        out
+    }
+
+    fn node(&self, m: &MessageHistoryItem, seen: &mut HashSet<String>) -> ThreadNode {
+        seen.insert(m.id.clone());
+        let mut replies = Vec::new();
+        for r in self.replies_to(&m.id) {
+            if !seen.contains(&r.id) {
+                replies.push(self.node(r, seen));
+            }
+        }
+        ThreadNode {
+            message: m.clone(),
+            replies,
+        }
+    }
+
+    /// 以 message_id 为中心的线程：祖先链 + 从根开始的回复树
+    pub fn thread(&self, message_id: &str) -> Option<MessageThread> {
+        let message = self.messages.get(message_id)?;
+        let mut chain = vec![message];
+        let mut seen: HashSet<&str> = HashSet::from([message_id]);
+        let mut cur = message;
+        while let Some(parent) = cur
+            .reply_to_message_id
+            .as_deref()
+            .and_then(|p| self.messages.get(p))
+        {
+            if !seen.insert(parent.id.as_str()) || chain.len() > MAX_DEPTH {
+                break;
+            }
+            chain.push(parent);
+            cur = parent;
+        }
+        chain.reverse();
+        let root = chain[0];
+        Some(MessageThread {
+            ancestors: chain[..chain.len() - 1]
+                .iter()
+                .map(|m| (*m).clone())
+                .collect(),
+            root: self.node(root, &mut HashSet::new()),
+            missing_ancestor_id: self.missing_ancestor(message_id),
+        })
+    }
+
+    /// 回答对应的提问：用户消息是自身，AI 回答取其 replyTo
+    fn prompt_of<'a>(&'a self, m: &'a MessageHistoryItem) -> &'a MessageHistoryItem {
+        if m.role.eq_ignore_ascii_case("assistant") {
+            if let Some(p) = m
+                .reply_to_message_id
+                .as_deref()
+                .and_then(|p| self.messages.get(p))
+            {
+                return p;
+            }
+        }
+        m
+    }
+
+    /// 提问的重发族：沿 resendOf 向上找到最初的提问，再收集所有（间接）重发它的提问
+    fn resend_family<'a>(&'a self, prompt: &'a MessageHistoryItem) -> Vec<&'a MessageHistoryItem> {
+        let mut origin = prompt;
+        let mut seen: HashSet<&str> = HashSet::from([prompt.id.as_str()]);
+        while let Some(prev) = origin
+            .resend_of_message_id
+            .as_deref()
+            .and_then(|p| self.messages.get(p))
+        {
+            if !seen.insert(prev.id.as_str()) {
+                break;
+            }
+            origin = prev;
+        }
+        let mut family = vec![origin];
+        let mut i = 0;
+        while i < family.len() && family.len() <= MAX_DEPTH {
+            let id = family[i].id.as_str();
+            let mut next: Vec<_> = self
+                .messages
+                .values()
+                .filter(|m| m.resend_of_message_id.as_deref() == Some(id))
+                .filter(|m| !family.iter().any(|f| f.id == m.id))
+                .collect();
+            next.sort_by_key(|m| seq_key(m));
+            family.extend(next);
+            i += 1;
+        }
+        family.sort_by_key(|m| seq_key(m));
+        family
+    }
+
+    /// 某条回答（或提问）的全部重新生成版本
+    pub fn resend_variants(&self, message_id: &str) -> Option<ResendVariants> {
+        let message = self.messages.get(message_id)?;
+        let prompt = self.prompt_of(message);
+        let family = self.resend_family(prompt);
+        let last = family.len().saturating_sub(1);
+        let variants = family
+            .iter()
+            .enumerate()
+            .map(|(i, p)| ResendVariant {
+                prompt: (*p).clone(),
+                answers: self
+                    .replies_to(&p.id)
+                    .into_iter()
+                    .filter(|m| m.role.eq_ignore_ascii_case("assistant"))
+                    .cloned()
+                    .collect(),
+                current: i == last,
+            })
+            .collect();
+        Some(ResendVariants {
+            message_id: message_id.to_string(),
+            variants,
+            missing_ancestor_id: self.missing_for(message_id),
+        })
+    }
+
+    /// 组装线程 / 重发链仍缺的下一条消息：自身、回复链祖先或最初的提问
+    pub fn missing_for(&self, message_id: &str) -> Option<String> {
+        let Some(message) = self.messages.get(message_id) else {
+            return Some(message_id.to_string());
+        };
+        self.missing_ancestor(message_id)
+            .or_else(|| self.missing_resend_origin(&self.prompt_of(message).id))
+    }
+
+    /// 线程根与重发族中最早的 groupSeq（之后的消息可能是它们的回复或重发）
+    pub fn earliest_seq(&self, message_id: &str) -> Option<i64> {
+        let root = self.thread(message_id)?.root.message.group_seq;
+        let origin = self
+            .resend_variants(message_id)
+            .and_then(|v| v.variants.first().and_then(|x| x.prompt.group_seq));
+        root.into_iter().chain(origin).min()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref GRAPHS: Mutex<HashMap<String, ConversationGraph>> = Mutex::new(HashMap::new());
+}
+
+/// 记录拉取到的消息
+pub fn ingest(group_id: &str, items: &[MessageHistoryItem]) {
+    if group_id.is_empty() || items.is_empty() {
+        return;
+    }
+    GRAPHS
+        .lock()
+        .unwrap()
+        .entry(group_id.to_string())
+        .or_default()
+        .extend(items.iter().cloned());
+}
+
+/// 记录群消息流中的新消息（type=message）
+pub fn ingest_event(event: &serde_json::Value) {
+    if event.get("type").and_then(|v| v.as_str()) != Some("message") {
+        return;
+    }
+    let Some(raw) = event.get("message") else {
+        return;
+    };
+    let group_id = raw
+        .get("groupId")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    if let Ok(m) = serde_json::from_value::<MessageHistoryItem>(raw.clone()) {
+        ingest(group_id, &[m]);
+    }
+}
+
+/// 在缓存中查找消息所在的群组
+pub fn find_group(message_id: &str) -> Option<String> {
+    GRAPHS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(_, g)| g.contains(message_id))
+        .map(|(gid, _)| gid.clone())
+}
+
+pub fn with_graph<R>(group_id: &str, f: impl FnOnce(&ConversationGraph) -> R) -> R {
+    let mut graphs = GRAPHS.lock().unwrap();
+    f(graphs.entry(group_id.to_string()).or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn msg(
+        id: &str,
+        seq: i64,
+        role: &str,
+        reply_to: Option<&str>,
+        resend_of: Option<&str>,
+    ) -> MessageHistoryItem {
+        serde_json::from_value(json!({
+            "id": id,
+            "groupSeq": seq,
+            "role": role,
+            "content": id,
+            "replyToMessageId": reply_to,
+            "resendOfMessageId": resend_of,
+            "viewRole": null,
+            "timestamp": format!("2024-01-01T00:00:{:02}Z", seq),
+            "tokenUsage": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn thread_collects_ancestors_and_sorted_replies() {
+        let mut graph = ConversationGraph::default();
+        graph.extend([
+            msg("q1", 1, "user", None, None),
+            msg("a1", 2, "assistant", Some("q1"), None),
+            msg("q2", 4, "user", Some("a1"), None),
+            msg("q3", 3, "user", Some("a1"), None),
+        ]);
+
+        let thread = graph.thread("q2").expect("thread");
+        let ancestors: Vec<_> = thread.ancestors.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ancestors, ["q1", "a1"]);
+        assert_eq!(thread.root.message.id, "q1");
+        let replies: Vec<_> = thread.root.replies[0]
+            .replies
+            .iter()
+            .map(|n| n.message.id.as_str())
+            .collect();
+        assert_eq!(replies, ["q3", "q2"]);
+        assert_eq!(thread.missing_ancestor_id, None);
+        assert_eq!(graph.earliest_seq("q2"), Some(1));
+    }
+
+    #[test]
+    fn missing_ancestor_stops_at_first_gap_and_survives_cycles() {
+        let mut graph = ConversationGraph::default();
+        graph.extend([
+            msg("a2", 5, "assistant", Some("q2"), None),
+            msg("x", 6, "user", Some("y"), None),
+            msg("y", 7, "user", Some("x"), None),
+        ]);
+        assert_eq!(graph.missing_ancestor("a2"), Some("q2".to_string()));
+        assert_eq!(graph.missing_for("a2"), Some("q2".to_string()));
+        assert_eq!(graph.missing_for("nope"), Some("nope".to_string()));
+        assert_eq!(graph.missing_ancestor("x"), None);
+        assert!(graph.thread("x").is_some());
+    }
+
+    #[test]
+    fn resend_variants_follow_the_resend_chain() {
+        let mut graph = ConversationGraph::default();
+        graph.extend([
+            msg("q1", 1, "user", None, None),
+            msg("a1", 2, "assistant", Some("q1"), None),
+            msg("q1b", 3, "user", None, Some("q1")),
+            msg("a1b", 4, "assistant", Some("q1b"), None),
+            msg("q1c", 5, "user", None, Some("q1b")),
+        ]);
+
+        let variants = graph.resend_variants("a1b").expect("variants");
+        let prompts: Vec<_> = variants
+            .variants
+            .iter()
+            .map(|v| (v.prompt.id.as_str(), v.answers.len(), v.current))
+            .collect();
+        assert_eq!(
+            prompts,
+            [("q1", 1, false), ("q1b", 1, false), ("q1c", 0, true)]
+        );
+        assert_eq!(variants.missing_ancestor_id, None);
+
+        let mut partial = ConversationGraph::default();
+        partial.extend([msg("q1b", 3, "user", None, Some("q1"))]);
+        assert_eq!(partial.missing_for("q1b"), Some("q1".to_string()));
+    }
+}
@@ -1,3 +1,17 @@
 pub mod api_client;
+pub mod bridge_server;
+pub mod client_log;
+pub mod crash_reporter;
+pub mod deep_link;
+pub mod logging;
+pub mod mcp_server;
+pub mod message_graph;
+pub mod network_inspector;
+pub mod notifier;
+pub mod presence;
+pub mod qr_code;
+pub mod role_views;
+pub mod session_documents;
+pub mod usage_ledger;
 
 pub use api_client::ApiClient;
@@ -0,0 +1,345 @@
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::services::{client_log, logging};
+
+/// 每条请求完成后推送给前端的事件
+pub const NETWORK_LOG_EVENT: &str = "network-log";
+/// 只保留最近的请求
+const CAPACITY: usize = 200;
+/// 展示用的请求 / 响应体最多保留的字符数
+const MAX_BODY_CHARS: usize = 16 * 1024;
+/// 超过该大小的请求体不保留原文（不可重放）
+const MAX_REPLAY_BODY_BYTES: usize = 1024 * 1024;
+
+/// 一条请求记录（仅开发者模式下采集）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkLogEntry {
+    pub id: u64,
+    pub ts_ms: i64,
+    /// SSE 等流式请求：只记录到响应头为止，不采集响应体
+    pub streaming: bool,
+    pub method: String,
+    /// 完整 URL（query 中的 token 类参数已脱敏）
+    pub url: String,
+    pub request_headers: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_headers: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_size: Option<u64>,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 是否可以 replay_request（流式请求、multipart 与超大请求体不可重放）
+    pub replayable: bool,
+}
+
+/// 重放所需的原始请求（不脱敏，只在内存中保存，不下发前端）
+#[derive(Debug, Clone)]
+pub struct ReplaySpec {
+    pub method: Method,
+    pub url: String,
+    pub content_type: Option<String>,
+    pub body: Option<Vec<u8>>,
+}
+
+struct Record {
+    entry: NetworkLogEntry,
+    replay: Option<ReplaySpec>,
+}
+
+/// 挂在重建后的 Response extensions 上，调用方据此找到对应记录
+#[derive(Debug, Clone, Copy)]
+pub struct NetworkLogId(pub u64);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+lazy_static::lazy_static! {
+    static ref RECORDS: Mutex<VecDeque<Record>> = Mutex::new(VecDeque::with_capacity(CAPACITY));
+    static ref APP: RwLock<Option<AppHandle>> = RwLock::new(None);
+}
+
+/// setup 中调用：事件需要 AppHandle 才能发出
+pub fn attach(app: &AppHandle) {
+    *APP.write().unwrap() = Some(app.clone());
+}
+
+/// 跟随 AppConfig.is_developer；关闭时清空已采集的记录
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        clear();
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn now_ms() -> i64 {
+    let dur = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_millis(0));
+    dur.as_millis() as i64
+}
+
+fn body_preview(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let text = if text.chars().count() > MAX_BODY_CHARS {
+        format!(
+            "{}…(truncated)",
+            text.chars().take(MAX_BODY_CHARS).collect::<String>()
+        )
+    } else {
+        text.into_owned()
+    };
+    logging::redact(&text)
+}
+
+fn full_url(url: &reqwest::Url) -> String {
+    format!(
+        "{}{}",
+        url.origin().ascii_serialization(),
+        client_log::loggable_path(url)
+    )
+}
+
+/// 一次进行中的采集（begin 时取好请求侧信息，finish 时补齐响应侧）
+pub struct Capture {
+    entry: NetworkLogEntry,
+    replay: Option<ReplaySpec>,
+    started: Instant,
+}
+
+/// 开始采集；未开启开发者模式时返回 None
+pub fn begin(request: &reqwest::Request, streaming: bool) -> Option<Capture> {
+    if !is_enabled() {
+        return None;
+    }
+    let body = request.body().map(|b| b.as_bytes());
+    // multipart 等流式请求体拿不到原文
+    let body_known = !matches!(body, Some(None));
+    let bytes = body.flatten();
+    let replay = (!streaming
+        && body_known
+        && bytes.is_none_or(|b| b.len() <= MAX_REPLAY_BODY_BYTES))
+    .then(|| ReplaySpec {
+        method: request.method().clone(),
+        url: request.url().to_string(),
+        content_type: request
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string()),
+        body: bytes.map(|b| b.to_vec()),
+    });
+
+    Some(Capture {
+        entry: NetworkLogEntry {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            ts_ms: now_ms(),
+            streaming,
+            method: request.method().to_string(),
+            url: full_url(request.url()),
+            request_headers: client_log::redact_headers(request.headers()),
+            request_body: match body {
+                Some(Some(b)) => Some(body_preview(b)),
+                Some(None) => Some("<stream>".to_string()),
+                None => None,
+            },
+            request_size: bytes.map(|b| b.len() as u64),
+            status: None,
+            response_headers: None,
+            response_body: None,
+            response_size: None,
+            duration_ms: 0,
+            error: None,
+            replayable: replay.is_some(),
+        },
+        replay,
+        started: Instant::now(),
+    })
+}
+
+impl Capture {
+    pub fn id(&self) -> u64 {
+        self.entry.id
+    }
+
+    /// 结束采集并推送 network-log 事件；body 为 None 表示未读取响应体（流式或失败）
+    pub fn finish(
+        mut self,
+        status: Option<u16>,
+        headers: Option<&HeaderMap>,
+        body: Option<&[u8]>,
+        error: Option<String>,
+    ) {
+        self.entry.duration_ms = self.started.elapsed().as_millis() as u64;
+        self.entry.status = status;
+        self.entry.response_headers = headers.map(client_log::redact_headers);
+        self.entry.response_body = body.map(body_preview);
+        self.entry.response_size = body.map(|b| b.len() as u64);
+        self.entry.error = error;
+
+        if let Some(app) = APP.read().unwrap().as_ref() {
+            let _ = app.emit(NETWORK_LOG_EVENT, &self.entry);
+        }
+        let mut records = RECORDS.lock().unwrap();
+        if records.len() >= CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(Record {
+            entry: self.entry,
+            replay: self.replay,
+        });
+    }
+}
+
+/// 最近的记录（按时间升序）；limit 为空返回全部
+pub fn snapshot(limit: Option<usize>) -> Vec<NetworkLogEntry> {
+    let records = RECORDS.lock().unwrap();
+    let skip = limit.map(|l| records.len().saturating_sub(l)).unwrap_or(0);
+    records.iter().skip(skip).map(|r| r.entry.clone()).collect()
+}
+
+pub fn get(id: u64) -> Option<(NetworkLogEntry, Option<ReplaySpec>)> {
+    RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|r| r.entry.id == id)
+        .map(|r| (r.entry.clone(), r.replay.clone()))
+}
+
+pub fn clear() {
+    RECORDS.lock().unwrap().clear();
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// 生成等价的 curl 命令；Authorization 用环境变量占位，其余敏感头省略
+/// - 有重放信息时按原始 URL 与请求体生成，可直接执行
+/// - 否则（流式、multipart、超大或二进制请求体）只能用脱敏 / 截断后的展示数据，首行注明不可直接重放
+pub fn to_curl(entry: &NetworkLogEntry, replay: Option<&ReplaySpec>) -> String {
+    let exact = replay.filter(|r| {
+        r.body
+            .as_deref()
+            .is_none_or(|b| std::str::from_utf8(b).is_ok())
+    });
+    let (url, body) = match exact {
+        Some(r) => (
+            r.url.as_str(),
+            r.body.as_deref().map(String::from_utf8_lossy),
+        ),
+        None => (
+            entry.url.as_str(),
+            entry.request_body.as_deref().map(Into::into),
+        ),
+    };
+
+    let mut parts = vec!["curl".to_string()];
+    if entry.method != "GET" {
+        parts.push(format!("-X {}", entry.method));
+    }
+    parts.push(shell_quote(url));
+    for (name, value) in &entry.request_headers {
+        if name == "authorization" {
+            parts.push("-H \"authorization: Bearer $PRD_AGENT_TOKEN\"".to_string());
+        } else if !value.contains("***") {
+            parts.push(format!(
+                "-H {}",
+                shell_quote(&format!("{}: {}", name, value))
+            ));
+        }
+    }
+    if let Some(body) = body {
+        parts.push(format!("--data-raw {}", shell_quote(&body)));
+    }
+    let curl = parts.join(" \\\n  ");
+    if exact.is_some() {
+        curl
+    } else {
+        format!(
+            "# 注意：URL / 请求体已脱敏或截断，命令不可直接重放\n{}",
+            curl
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(body: Option<&str>) -> NetworkLogEntry {
+        NetworkLogEntry {
+            id: 1,
+            ts_ms: 0,
+            streaming: false,
+            method: "POST".to_string(),
+            url: "http://localhost/api/v1/x?token=***".to_string(),
+            request_headers: BTreeMap::from([
+                ("authorization".to_string(), "Bearer ***".to_string()),
+                ("content-type".to_string(), "application/json".to_string()),
+                ("cookie".to_string(), "***".to_string()),
+            ]),
+            request_body: body.map(str::to_string),
+            request_size: None,
+            status: Some(200),
+            response_headers: None,
+            response_body: None,
+            response_size: None,
+            duration_ms: 1,
+            error: None,
+            replayable: false,
+        }
+    }
+
+    #[test]
+    fn curl_uses_replay_spec_when_available() {
+        let spec = ReplaySpec {
+            method: Method::POST,
+            url: "http://localhost/api/v1/x?token=abc".to_string(),
+            content_type: Some("application/json".to_string()),
+            body: Some(br#"{"password":"it's"}"#.to_vec()),
+        };
+        let curl = to_curl(&entry(Some(r#"{"password":"***"}"#)), Some(&spec));
+        assert!(!curl.starts_with('#'));
+        assert!(curl.contains("'http://localhost/api/v1/x?token=abc'"));
+        assert!(curl.contains(r#"--data-raw '{"password":"it'\''s"}'"#));
+        assert!(curl.contains("$PRD_AGENT_TOKEN"));
+        assert!(!curl.contains("cookie"));
+    }
+
+    #[test]
+    fn curl_without_replay_spec_is_marked_non_replayable() {
+        let curl = to_curl(&entry(Some("<stream>")), None);
+        assert!(curl.starts_with("# "));
+        assert!(curl.contains("token=***"));
+
+        let binary = ReplaySpec {
+            method: Method::POST,
+            url: "http://localhost/api/v1/x".to_string(),
+            content_type: None,
+            body: Some(vec![0xff, 0xfe]),
+        };
+        assert!(to_curl(&entry(None), Some(&binary)).starts_with("# "));
+    }
+}
@@ -0,0 +1,322 @@
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::models::{Defect, MessageHistoryItem};
+use crate::services::deep_link;
+
+/// 无法弹出系统通知时（如 Linux 未运行通知守护进程），改发该事件由前端展示应用内通知
+pub const IN_APP_NOTIFICATION_EVENT: &str = "in-app-notification";
+
+/// 通知设置（持久化在 config.json 的 notifications 字段）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 缺陷被指派给我
+    #[serde(default = "default_true")]
+    pub defect_assigned: bool,
+    /// 群消息中 @ 我
+    #[serde(default = "default_true")]
+    pub group_mentions: bool,
+    /// 已静音的群组
+    #[serde(default)]
+    pub muted_group_ids: Vec<String>,
+    /// 免打扰时段（本地时间）；未设置表示不启用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            defect_assigned: true,
+            group_mentions: true,
+            muted_group_ids: Vec::new(),
+            quiet_hours: None,
+        }
+    }
+}
+
+/// 免打扰时段，格式 "HH:MM"；start > end 表示跨午夜（如 22:00 ~ 08:00）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+impl QuietHours {
+    fn contains(&self, now: NaiveTime) -> bool {
+        let (Ok(start), Ok(end)) = (
+            NaiveTime::parse_from_str(self.start.trim(), "%H:%M"),
+            NaiveTime::parse_from_str(self.end.trim(), "%H:%M"),
+        ) else {
+            return false;
+        };
+        if start <= end {
+            now >= start && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+}
+
+/// 当前登录用户（用于识别 @ 我 / 指派给我）
+#[derive(Debug, Clone, Default)]
+struct Identity {
+    user_id: String,
+    username: Option<String>,
+    display_name: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref SETTINGS: RwLock<NotificationSettings> = RwLock::new(NotificationSettings::default());
+    static ref IDENTITY: RwLock<Option<Identity>> = RwLock::new(None);
+}
+
+pub fn set_settings(settings: NotificationSettings) {
+    *SETTINGS.write().unwrap() = settings;
+}
+
+pub fn get_settings() -> NotificationSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// 登录 / 恢复登录态时同步；user_id 为空表示已登出
+pub fn set_identity(
+    user_id: Option<String>,
+    username: Option<String>,
+    display_name: Option<String>,
+) {
+    let non_empty = |s: Option<String>| s.filter(|v| !v.trim().is_empty());
+    *IDENTITY.write().unwrap() = non_empty(user_id).map(|user_id| Identity {
+        user_id,
+        username: non_empty(username),
+        display_name: non_empty(display_name),
+    });
+}
+
+fn identity() -> Option<Identity> {
+    IDENTITY.read().unwrap().clone()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Notice {
+    kind: &'static str,
+    title: String,
+    body: String,
+    /// 点击后跳转的 prdagent:// 链接（走前端已有的 deep-link 处理）
+    url: String,
+}
+
+/// 群消息事件（group-message 通道的 data 帧）
+pub fn on_group_event(app: &AppHandle, event: &serde_json::Value) {
+    if event.get("type").and_then(|v| v.as_str()) != Some("message") {
+        return;
+    }
+    let Some(message) = event
+        .get("message")
+        .and_then(|m| serde_json::from_value::<MessageHistoryItem>(m.clone()).ok())
+    else {
+        return;
+    };
+    let group_id = event
+        .get("message")
+        .and_then(|m| m.get("groupId"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+
+    let settings = get_settings();
+    if !settings.enabled || !settings.group_mentions || group_id.is_empty() {
+        return;
+    }
+    if settings.muted_group_ids.iter().any(|g| g == group_id) {
+        return;
+    }
+    let Some(me) = identity() else {
+        return;
+    };
+    if message.sender_id.as_deref() == Some(me.user_id.as_str()) {
+        return;
+    }
+    let mentioned = [me.display_name.as_deref(), me.username.as_deref()]
+        .into_iter()
+        .flatten()
+        .any(|name| message.content.contains(&format!("@{}", name)));
+    if !mentioned {
+        return;
+    }
+
+    let sender = message
+        .sender_name
+        .clone()
+        .unwrap_or_else(|| "群成员".to_string());
+    let url = match message.group_seq {
+        Some(seq) => format!("prdagent://group/{}?seq={}", group_id, seq),
+        None => format!("prdagent://group/{}", group_id),
+    };
+    dispatch(
+        app,
+        Notice {
+            kind: "groupMention",
+            title: format!("{} 在群聊中提到了你", sender),
+            body: preview_text(&message.content),
+            url,
+        },
+    );
+}
+
+/// 缺陷更新事件；previous_assignee 为本地缓存中的旧指派人（None 表示此前未见过该缺陷）
+pub fn on_defect_updated(
+    app: &AppHandle,
+    previous_assignee: Option<Option<String>>,
+    defect: &Defect,
+) {
+    let settings = get_settings();
+    if !settings.enabled || !settings.defect_assigned {
+        return;
+    }
+    let Some(me) = identity() else {
+        return;
+    };
+    if defect.assignee_id.as_deref() != Some(me.user_id.as_str())
+        || defect.reporter_id == me.user_id
+    {
+        return;
+    }
+    if previous_assignee.flatten().as_deref() == Some(me.user_id.as_str()) {
+        return;
+    }
+
+    let title = defect
+        .title
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| preview_text(&defect.raw_content));
+    dispatch(
+        app,
+        Notice {
+            kind: "defectAssigned",
+            title: format!("缺陷 {} 已指派给你", defect.defect_no),
+            body: title,
+            url: format!("prdagent://defect/{}", defect.id),
+        },
+    );
+}
+
+fn preview_text(content: &str) -> String {
+    const MAX_CHARS: usize = 80;
+    let flat = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() > MAX_CHARS {
+        format!("{}…", flat.chars().take(MAX_CHARS).collect::<String>())
+    } else {
+        flat
+    }
+}
+
+fn dispatch(app: &AppHandle, notice: Notice) {
+    // 免打扰：完全静默（前端仍会通过原始事件刷新未读）
+    if let Some(quiet) = get_settings().quiet_hours {
+        if quiet.contains(Local::now().time()) {
+            return;
+        }
+    }
+    // 窗口在前台时用户已经能看到，不再打扰
+    let focused = app
+        .get_webview_window("main")
+        .and_then(|w| w.is_focused().ok())
+        .unwrap_or(false);
+    if focused {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if !show_native(&app, &notice) {
+            let _ = app.emit(IN_APP_NOTIFICATION_EVENT, &notice);
+        }
+    });
+}
+
+/// 弹出系统通知；返回 false 表示系统不支持（调用方降级为应用内通知）
+#[cfg(not(any(windows, target_os = "macos")))]
+fn show_native(app: &AppHandle, notice: &Notice) -> bool {
+    // Linux 下可以拿到点击回调（default action），直接回到 deep-link 处理
+    let handle = match notify_rust::Notification::new()
+        .appname(&app.package_info().name)
+        .summary(&notice.title)
+        .body(&notice.body)
+        .action("default", "打开")
+        .show()
+    {
+        Ok(h) => h,
+        Err(_) => return false,
+    };
+    let app = app.clone();
+    let url = notice.url.clone();
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if action == "default" {
+                deep_link::focus_main_window(&app);
+                let _ = deep_link::dispatch(&app, &url);
+            }
+        });
+    });
+    true
+}
+
+/// 弹出系统通知；返回 false 表示系统不支持（调用方降级为应用内通知）
+#[cfg(any(windows, target_os = "macos"))]
+fn show_native(_app: &AppHandle, notice: &Notice) -> bool {
+    let shown = notify_rust::Notification::new()
+        .summary(&notice.title)
+        .body(&notice.body)
+        .show()
+        .is_ok();
+    if shown {
+        pending_click::remember(notice.url.clone());
+    }
+    shown
+}
+
+/// Windows / macOS 拿不到点击回调：点击通知会激活应用，因此记住最近一条的跳转目标，
+/// 在主窗口短时间内重新获得焦点时再发 deep-link
+#[cfg(any(windows, target_os = "macos"))]
+mod pending_click {
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    const ACTIVATION_WINDOW: Duration = Duration::from_secs(30);
+
+    lazy_static::lazy_static! {
+        static ref PENDING: Mutex<Option<(Instant, String)>> = Mutex::new(None);
+    }
+
+    pub fn remember(url: String) {
+        *PENDING.lock().unwrap() = Some((Instant::now(), url));
+    }
+
+    pub fn take_recent() -> Option<String> {
+        let (at, url) = PENDING.lock().unwrap().take()?;
+        (at.elapsed() <= ACTIVATION_WINDOW).then_some(url)
+    }
+}
+
+/// 主窗口获得焦点时调用（lib.rs 的 WindowEvent::Focused）
+pub fn on_main_window_focused(app: &AppHandle) {
+    #[cfg(any(windows, target_os = "macos"))]
+    if let Some(url) = pending_click::take_recent() {
+        let _ = deep_link::dispatch(app, &url);
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let _ = app;
+}
@@ -0,0 +1,249 @@
+//! 群成员在线状态与"正在输入"：
+//! - 自己：记录最近一次用户活动并检测系统锁屏，空闲或锁屏时暂停心跳
+//! - 他人：轮询服务端在线状态，与 GroupMemberInfo 合并后按变化发 presence-changed
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::models::GroupMemberInfo;
+
+pub const PRESENCE_CHANGED_EVENT: &str = "presence-changed";
+
+/// 超过该时长没有用户活动即视为空闲，暂停心跳（服务端随后将我们标记为离线）
+const IDLE_AFTER: Duration = Duration::from_secs(5 * 60);
+/// 同一群组两次"正在输入"信号的最小间隔
+const TYPING_THROTTLE: Duration = Duration::from_secs(3);
+/// 锁屏检测命令的超时
+const LOCK_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+/// 在线状态轮询间隔
+pub const PRESENCE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 服务端返回的单个成员在线状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceEntry {
+    pub user_id: String,
+    #[serde(default)]
+    pub online: bool,
+    #[serde(default)]
+    pub typing: bool,
+    #[serde(default)]
+    pub last_seen_at: Option<String>,
+}
+
+/// 成员信息 + 在线状态（前端成员列表直接使用）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPresenceMember {
+    pub user_id: String,
+    pub display_name: String,
+    pub member_role: String,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    pub online: bool,
+    pub typing: bool,
+    #[serde(default)]
+    pub last_seen_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceChanged {
+    pub group_id: String,
+    /// 仅包含状态有变化的成员；首次推送为完整列表
+    pub members: Vec<GroupPresenceMember>,
+}
+
+static SCREEN_LOCKED: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    static ref LAST_ACTIVITY: Mutex<Instant> = Mutex::new(Instant::now());
+    static ref LAST_TYPING: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+    static ref WATCH_TOKEN: Mutex<Option<CancellationToken>> = Mutex::new(None);
+}
+
+/// 记录一次用户活动（窗口获得焦点、输入等）
+pub fn touch() {
+    *LAST_ACTIVITY.lock().unwrap() = Instant::now();
+}
+
+/// 是否处于空闲状态：超过阈值没有用户活动，或最近一次检测到系统已锁屏
+pub fn is_idle() -> bool {
+    SCREEN_LOCKED.load(Ordering::Relaxed) || LAST_ACTIVITY.lock().unwrap().elapsed() >= IDLE_AFTER
+}
+
+/// 重新检测系统是否锁屏（心跳前调用）；检测失败时按未锁屏处理
+pub async fn refresh_screen_locked() -> bool {
+    let locked = probe_screen_locked().await.unwrap_or(false);
+    if SCREEN_LOCKED.swap(locked, Ordering::Relaxed) && !locked {
+        // 刚解锁：视为一次用户活动，立即恢复在线
+        touch();
+    }
+    locked
+}
+
+/// 执行系统命令并返回 stdout；命令不存在、失败或超时时为 None
+async fn probe_output(program: &str, args: &[&str]) -> Option<String> {
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(args)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    {
+        // CREATE_NO_WINDOW：避免每次检测都闪出控制台窗口
+        cmd.creation_flags(0x0800_0000);
+    }
+    let output = tokio::time::timeout(LOCK_PROBE_TIMEOUT, cmd.output())
+        .await
+        .ok()?
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// macOS：会话字典中的 CGSSessionScreenIsLocked
+#[cfg(target_os = "macos")]
+async fn probe_screen_locked() -> Option<bool> {
+    let out = probe_output("ioreg", &["-n", "Root", "-d1"]).await?;
+    Some(out.contains("\"CGSSessionScreenIsLocked\"=Yes"))
+}
+
+/// Windows：锁屏时会运行 LogonUI.exe
+#[cfg(windows)]
+async fn probe_screen_locked() -> Option<bool> {
+    let out = probe_output(
+        "tasklist",
+        &["/FI", "IMAGENAME eq LogonUI.exe", "/NH", "/FO", "CSV"],
+    )
+    .await?;
+    Some(out.to_ascii_lowercase().contains("logonui.exe"))
+}
+
+/// Linux：systemd-logind 会话的 LockedHint（桌面环境锁屏时设置）
+#[cfg(target_os = "linux")]
+async fn probe_screen_locked() -> Option<bool> {
+    let session = std::env::var("XDG_SESSION_ID").ok()?;
+    let out = probe_output(
+        "loginctl",
+        &["show-session", &session, "-p", "LockedHint", "--value"],
+    )
+    .await?;
+    Some(out.trim() == "yes")
+}
+
+#[cfg(not(any(target_os = "macos", windows, target_os = "linux")))]
+async fn probe_screen_locked() -> Option<bool> {
+    None
+}
+
+/// "正在输入"节流：返回 true 表示本次应发送
+pub fn should_send_typing(group_id: &str) -> bool {
+    let mut guard = LAST_TYPING.lock().unwrap();
+    let now = Instant::now();
+    match guard.get(group_id) {
+        Some(at) if now.duration_since(*at) < TYPING_THROTTLE => false,
+        _ => {
+            guard.insert(group_id.to_string(), now);
+            true
+        }
+    }
+}
+
+/// 合并成员列表与在线状态；自己的在线状态以本机空闲判断为准
+pub fn merge(
+    members: Vec<GroupMemberInfo>,
+    entries: &[PresenceEntry],
+    self_user_id: Option<&str>,
+) -> Vec<GroupPresenceMember> {
+    members
+        .into_iter()
+        .map(|m| {
+            let entry = entries.iter().find(|e| e.user_id == m.user_id);
+            let is_self = self_user_id == Some(m.user_id.as_str());
+            let online = if is_self {
+                !is_idle()
+            } else {
+                entry.is_some_and(|e| e.online)
+            };
+            GroupPresenceMember {
+                online,
+                typing: !is_self && online && entry.is_some_and(|e| e.typing),
+                last_seen_at: entry.and_then(|e| e.last_seen_at.clone()),
+                user_id: m.user_id,
+                display_name: m.display_name,
+                member_role: m.member_role,
+                avatar_url: m.avatar_url,
+            }
+        })
+        .collect()
+}
+
+/// 与上一次快照相比有变化的成员
+pub fn diff(
+    previous: &[GroupPresenceMember],
+    current: &[GroupPresenceMember],
+) -> Vec<GroupPresenceMember> {
+    current
+        .iter()
+        .filter(|m| !previous.contains(m))
+        .cloned()
+        .collect()
+}
+
+/// 开始监听新的群组时取消旧的轮询（同一时间只关注当前打开的群）
+pub fn new_watch_token() -> CancellationToken {
+    let mut guard = WATCH_TOKEN.lock().unwrap();
+    if let Some(old) = guard.take() {
+        old.cancel();
+    }
+    let token = CancellationToken::new();
+    *guard = Some(token.clone());
+    token
+}
+
+pub fn stop_watch() {
+    if let Some(token) = WATCH_TOKEN.lock().unwrap().take() {
+        token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(user_id: &str, online: bool, typing: bool) -> GroupPresenceMember {
+        GroupPresenceMember {
+            user_id: user_id.to_string(),
+            display_name: user_id.to_string(),
+            member_role: "PM".to_string(),
+            avatar_url: None,
+            online,
+            typing,
+            last_seen_at: None,
+        }
+    }
+
+    #[test]
+    fn diff_reports_only_changed_or_new_members() {
+        let previous = [member("u1", true, false), member("u2", false, false)];
+        let current = [
+            member("u1", true, true),
+            member("u2", false, false),
+            member("u3", true, false),
+        ];
+        let changed: Vec<_> = diff(&previous, &current)
+            .into_iter()
+            .map(|m| m.user_id)
+            .collect();
+        assert_eq!(changed, ["u1", "u3"]);
+        assert!(diff(&current, &current).is_empty());
+        assert_eq!(diff(&[], &current).len(), 3);
+    }
+}
@@ -0,0 +1,57 @@
+//! 二维码渲染（邀请链接分享）：SVG 直接给前端内联，PNG 用于复制 / 保存图片。
+
+use qrcode::render::svg;
+use qrcode::{Color, EcLevel, QrCode};
+
+/// 二维码四周留白（模块数），规范要求至少 4
+const QUIET_ZONE: usize = 4;
+
+fn encode(data: &str) -> Result<QrCode, String> {
+    QrCode::with_error_correction_level(data.as_bytes(), EcLevel::M)
+        .map_err(|e| format!("生成二维码失败: {}", e))
+}
+
+/// 渲染为 SVG 字符串（最小边长 size 像素）
+pub fn render_svg(data: &str, size: u32) -> Result<String, String> {
+    let code = encode(data)?;
+    Ok(code
+        .render::<svg::Color>()
+        .min_dimensions(size, size)
+        .quiet_zone(true)
+        .build())
+}
+
+/// 渲染为灰度 PNG；每个模块放大为 scale × scale 像素
+pub fn render_png(data: &str, scale: usize) -> Result<Vec<u8>, String> {
+    let code = encode(data)?;
+    let scale = scale.clamp(1, 32);
+    let modules = code.width();
+    let colors = code.to_colors();
+    let side = (modules + QUIET_ZONE * 2) * scale;
+
+    let mut pixels = vec![0xFFu8; side * side];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let x0 = (i % modules + QUIET_ZONE) * scale;
+        let y0 = (i / modules + QUIET_ZONE) * scale;
+        for y in y0..y0 + scale {
+            pixels[y * side + x0..y * side + x0 + scale].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, side as u32, side as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| format!("生成二维码失败: {}", e))?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|e| format!("生成二维码失败: {}", e))?;
+    }
+    Ok(out)
+}
@@ -0,0 +1,132 @@
+//! 按角色查看对话：历史消息按 viewRole / senderRole 过滤，记住每个群组上次使用的角色。
+//!
+//! 上次角色仅保存在本机（group_roles.json），重新打开群组时用于恢复会话角色。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::models::MessageHistoryItem;
+
+const STORE_FILE: &str = "group_roles.json";
+
+/// 历史消息的角色过滤条件（字段为空表示不过滤）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleFilter {
+    /// 回答时使用的视角（AI 消息上的 viewRole）
+    #[serde(default)]
+    pub view_role: Option<String>,
+    /// 发送者的成员角色
+    #[serde(default)]
+    pub sender_role: Option<String>,
+}
+
+impl RoleFilter {
+    pub fn new(view_role: Option<&str>, sender_role: Option<&str>) -> Self {
+        let norm = |r: Option<&str>| {
+            r.map(|s| s.trim().to_ascii_uppercase())
+                .filter(|s| !s.is_empty())
+        };
+        Self {
+            view_role: norm(view_role),
+            sender_role: norm(sender_role),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.view_role.is_none() && self.sender_role.is_none()
+    }
+
+    /// 没有对应字段的旧消息不匹配任何角色
+    pub fn matches(&self, m: &MessageHistoryItem) -> bool {
+        let eq = |want: &Option<String>, got: &Option<String>| match want {
+            None => true,
+            Some(w) => got.as_deref().is_some_and(|g| g.eq_ignore_ascii_case(w)),
+        };
+        eq(&self.view_role, &m.view_role) && eq(&self.sender_role, &m.sender_role)
+    }
+
+    pub fn apply(&self, messages: Vec<MessageHistoryItem>) -> Vec<MessageHistoryItem> {
+        if self.is_empty() {
+            return messages;
+        }
+        messages.into_iter().filter(|m| self.matches(m)).collect()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref STORE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+    /// groupId -> 上次使用的角色
+    static ref LAST_ROLES: RwLock<HashMap<String, String>> = RwLock::new(HashMap::new());
+}
+
+/// 启动时调用：从 app_data_dir 载入各群组上次的角色（文件损坏时忽略）
+pub fn init(dir: PathBuf) {
+    let roles = fs::read_to_string(dir.join(STORE_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str::<HashMap<String, String>>(&s).ok())
+        .unwrap_or_default();
+    *LAST_ROLES.write().unwrap() = roles;
+    *STORE_DIR.write().unwrap() = Some(dir);
+}
+
+fn persist() -> Result<(), String> {
+    let Some(dir) = STORE_DIR.read().unwrap().clone() else {
+        return Ok(());
+    };
+    let content = serde_json::to_string_pretty(&*LAST_ROLES.read().unwrap())
+        .map_err(|e| format!("Failed to serialize group roles: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    fs::write(dir.join(STORE_FILE), content)
+        .map_err(|e| format!("Failed to write group roles: {}", e))
+}
+
+pub fn last_role(group_id: &str) -> Option<String> {
+    LAST_ROLES.read().unwrap().get(group_id).cloned()
+}
+
+pub fn remember_role(group_id: &str, role: &str) -> Result<(), String> {
+    let role = role.trim().to_ascii_uppercase();
+    if group_id.trim().is_empty() || role.is_empty() {
+        return Ok(());
+    }
+    {
+        let mut roles = LAST_ROLES.write().unwrap();
+        if roles.get(group_id).is_some_and(|r| *r == role) {
+            return Ok(());
+        }
+        roles.insert(group_id.to_string(), role);
+    }
+    persist()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn msg(view_role: Option<&str>, sender_role: Option<&str>) -> MessageHistoryItem {
+        serde_json::from_value(json!({
+            "id": "m", "role": "Assistant", "content": "", "timestamp": "2026-01-01T00:00:00Z",
+            "viewRole": view_role, "senderRole": sender_role
+        }))
+        .expect("message")
+    }
+
+    #[test]
+    fn filter_matches_roles_case_insensitively() {
+        let filter = RoleFilter::new(Some(" dev "), None);
+        assert!(filter.matches(&msg(Some("DEV"), None)));
+        assert!(!filter.matches(&msg(Some("QA"), None)));
+        // 没有 viewRole 的旧消息不匹配
+        assert!(!filter.matches(&msg(None, Some("DEV"))));
+
+        let both = RoleFilter::new(Some("qa"), Some("pm"));
+        assert!(both.matches(&msg(Some("qa"), Some("PM"))));
+        assert!(!both.matches(&msg(Some("QA"), Some("DEV"))));
+        assert!(RoleFilter::new(None, Some("  ")).is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+//! 会话多文档管理：文档排序、是否纳入上下文（不移除文档），以及按模型上限计算上下文预算。
+//!
+//! 排序与纳入状态仅保存在本机（session_documents.json），目前只用于预算估算与上下文快照：
+//! 服务端发送消息时尚不支持指定上下文文档，始终使用会话的全部文档。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::models::{DocumentInfo, SessionInfo};
+
+const STORE_FILE: &str = "session_documents.json";
+
+/// 上下文预算设置（持久化在 config.json 的 contextBudget 字段）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextBudgetSettings {
+    /// 模型上下文上限（token）
+    #[serde(default = "default_model_token_limit")]
+    pub model_token_limit: u32,
+    /// 达到上限的该比例时开始提示
+    #[serde(default = "default_warn_ratio")]
+    pub warn_ratio: f32,
+}
+
+fn default_model_token_limit() -> u32 {
+    128_000
+}
+
+fn default_warn_ratio() -> f32 {
+    0.8
+}
+
+impl Default for ContextBudgetSettings {
+    fn default() -> Self {
+        Self {
+            model_token_limit: default_model_token_limit(),
+            warn_ratio: default_warn_ratio(),
+        }
+    }
+}
+
+/// 单个会话的文档排序与排除列表
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDocumentLayout {
+    /// 优先级顺序（靠前优先）；未出现在此的文档排在后面，保持服务端顺序
+    #[serde(default)]
+    pub order: Vec<String>,
+    /// 暂时移出上下文的文档
+    #[serde(default)]
+    pub excluded: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetLevel {
+    Ok,
+    /// 接近上限
+    Near,
+    /// 超过上限：服务端会截断，靠后的文档可能不完整
+    Over,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDocumentEntry {
+    pub document_id: String,
+    pub title: Option<String>,
+    pub document_type: Option<String>,
+    pub char_count: i32,
+    pub token_estimate: i32,
+    pub included: bool,
+    /// 从 1 开始的优先级（仅纳入上下文的文档有值）
+    pub priority: Option<usize>,
+    /// 累加到该文档时已超过上限
+    pub exceeds_budget: bool,
+}
+
+/// 会话文档的上下文预算视图
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDocumentBudget {
+    pub session_id: String,
+    pub documents: Vec<SessionDocumentEntry>,
+    pub included_tokens: i64,
+    pub total_tokens: i64,
+    pub model_token_limit: u32,
+    pub usage_ratio: f32,
+    pub level: BudgetLevel,
+    pub warning: Option<String>,
+    /// 恒为 true：排序 / 移出只影响本机估算，服务端发送时仍使用会话的全部文档
+    pub view_only: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref SETTINGS: RwLock<ContextBudgetSettings> = RwLock::new(ContextBudgetSettings::default());
+    static ref STORE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+    static ref LAYOUTS: RwLock<HashMap<String, SessionDocumentLayout>> = RwLock::new(HashMap::new());
+}
+
+pub fn get_settings() -> ContextBudgetSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+pub fn set_settings(settings: ContextBudgetSettings) {
+    *SETTINGS.write().unwrap() = settings;
+}
+
+/// 启动时调用：从 app_data_dir 载入各会话的排序 / 排除状态（文件损坏时忽略）
+pub fn init(dir: PathBuf) {
+    let layouts = fs::read_to_string(dir.join(STORE_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str::<HashMap<String, SessionDocumentLayout>>(&s).ok())
+        .unwrap_or_default();
+    *LAYOUTS.write().unwrap() = layouts;
+    *STORE_DIR.write().unwrap() = Some(dir);
+}
+
+fn persist() -> Result<(), String> {
+    let Some(dir) = STORE_DIR.read().unwrap().clone() else {
+        return Ok(());
+    };
+    let content = serde_json::to_string_pretty(&*LAYOUTS.read().unwrap())
+        .map_err(|e| format!("Failed to serialize session documents: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    fs::write(dir.join(STORE_FILE), content)
+        .map_err(|e| format!("Failed to write session documents: {}", e))
+}
+
+pub fn get_layout(session_id: &str) -> SessionDocumentLayout {
+    LAYOUTS
+        .read()
+        .unwrap()
+        .get(session_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub fn set_layout(session_id: &str, layout: SessionDocumentLayout) -> Result<(), String> {
+    {
+        let mut layouts = LAYOUTS.write().unwrap();
+        if layout.order.is_empty() && layout.excluded.is_empty() {
+            layouts.remove(session_id);
+        } else {
+            layouts.insert(session_id.to_string(), layout);
+        }
+    }
+    persist()
+}
+
+/// 会话中的文档 id（服务端顺序）；旧会话只有 document_id
+pub fn session_document_ids(session: &SessionInfo) -> Vec<String> {
+    if session.document_ids.is_empty() {
+        vec![session.document_id.clone()]
+    } else {
+        session.document_ids.clone()
+    }
+}
+
+/// 按本地排序调整后的文档顺序；已不在会话中的 id 被忽略
+pub fn ordered_ids(ids: &[String], layout: &SessionDocumentLayout) -> Vec<String> {
+    let mut ordered: Vec<String> = layout
+        .order
+        .iter()
+        .filter(|id| ids.contains(id))
+        .cloned()
+        .collect();
+    for id in ids {
+        if !ordered.contains(id) {
+            ordered.push(id.clone());
+        }
+    }
+    ordered
+}
+
+/// 按本地排序 / 纳入状态得到的上下文文档（按优先级）；未调整过的会话返回 None
+pub fn context_document_ids(session_id: &str, ids: &[String]) -> Option<Vec<String>> {
+    let layout = LAYOUTS.read().unwrap().get(session_id).cloned()?;
+    Some(
+        ordered_ids(ids, &layout)
+            .into_iter()
+            .filter(|id| !layout.excluded.contains(id))
+            .collect(),
+    )
+}
+
+/// 合并文档信息与本地排序 / 纳入状态，计算预算
+pub fn build_budget(
+    session: &SessionInfo,
+    documents: &[DocumentInfo],
+    layout: &SessionDocumentLayout,
+    settings: &ContextBudgetSettings,
+) -> SessionDocumentBudget {
+    let limit = i64::from(settings.model_token_limit.max(1));
+    let mut included_tokens = 0i64;
+    let mut total_tokens = 0i64;
+    let mut priority = 0usize;
+    let entries: Vec<SessionDocumentEntry> = ordered_ids(&session_document_ids(session), layout)
+        .into_iter()
+        .map(|id| {
+            let info = documents.iter().find(|d| d.id == id);
+            let document_type = session
+                .document_metas
+                .iter()
+                .find(|m| m.document_id == id)
+                .map(|m| m.document_type.clone());
+            let token_estimate = info.map(|d| d.token_estimate).unwrap_or(0);
+            let included = !layout.excluded.contains(&id);
+            total_tokens += i64::from(token_estimate);
+            let mut exceeds_budget = false;
+            let entry_priority = if included {
+                included_tokens += i64::from(token_estimate);
+                exceeds_budget = included_tokens > limit;
+                priority += 1;
+                Some(priority)
+            } else {
+                None
+            };
+            SessionDocumentEntry {
+                title: info.map(|d| d.title.clone()),
+                char_count: info.map(|d| d.char_count).unwrap_or(0),
+                document_id: id,
+                document_type,
+                token_estimate,
+                included,
+                priority: entry_priority,
+                exceeds_budget,
+            }
+        })
+        .collect();
+
+    let usage_ratio = included_tokens as f32 / limit as f32;
+    let (level, warning) = if included_tokens > limit {
+        (
+            BudgetLevel::Over,
+            Some(format!(
+                "上下文约 {} tokens，超过模型上限 {}，靠后的文档可能被截断",
+                included_tokens, limit
+            )),
+        )
+    } else if usage_ratio >= settings.warn_ratio {
+        (
+            BudgetLevel::Near,
+            Some(format!(
+                "上下文约 {} tokens，已占模型上限的 {:.0}%",
+                included_tokens,
+                usage_ratio * 100.0
+            )),
+        )
+    } else {
+        (BudgetLevel::Ok, None)
+    };
+
+    SessionDocumentBudget {
+        session_id: session.session_id.clone(),
+        documents: entries,
+        included_tokens,
+        total_tokens,
+        model_token_limit: settings.model_token_limit,
+        usage_ratio,
+        level,
+        warning,
+        view_only: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(ids: &[&str]) -> SessionInfo {
+        SessionInfo {
+            session_id: "s1".to_string(),
+            group_id: None,
+            document_id: ids[0].to_string(),
+            document_ids: ids.iter().map(|s| s.to_string()).collect(),
+            document_metas: Vec::new(),
+            current_role: "PM".to_string(),
+            mode: "QA".to_string(),
+            guide_step: None,
+        }
+    }
+
+    fn doc(id: &str, tokens: i32) -> DocumentInfo {
+        DocumentInfo {
+            id: id.to_string(),
+            title: id.to_uppercase(),
+            char_count: tokens * 2,
+            token_estimate: tokens,
+        }
+    }
+
+    #[test]
+    fn build_budget_orders_excludes_and_flags_overflow() {
+        let session = session(&["a", "b", "c"]);
+        let documents = [doc("a", 60), doc("b", 30), doc("c", 50)];
+        let layout = SessionDocumentLayout {
+            order: vec!["c".to_string(), "gone".to_string()],
+            excluded: vec!["b".to_string()],
+        };
+        let settings = ContextBudgetSettings {
+            model_token_limit: 100,
+            warn_ratio: 0.8,
+        };
+
+        let budget = build_budget(&session, &documents, &layout, &settings);
+        let view: Vec<_> = budget
+            .documents
+            .iter()
+            .map(|d| (d.document_id.as_str(), d.priority, d.exceeds_budget))
+            .collect();
+        assert_eq!(
+            view,
+            [
+                ("c", Some(1), false),
+                ("a", Some(2), true),
+                ("b", None, false)
+            ]
+        );
+        assert_eq!(budget.included_tokens, 110);
+        assert_eq!(budget.total_tokens, 140);
+        assert_eq!(budget.level, BudgetLevel::Over);
+        assert!(budget.warning.is_some());
+    }
+
+    #[test]
+    fn build_budget_warns_near_the_limit() {
+        let session = session(&["a"]);
+        let settings = ContextBudgetSettings {
+            model_token_limit: 100,
+            warn_ratio: 0.8,
+        };
+        let near = build_budget(
+            &session,
+            &[doc("a", 85)],
+            &SessionDocumentLayout::default(),
+            &settings,
+        );
+        assert_eq!(near.level, BudgetLevel::Near);
+        let ok = build_budget(
+            &session,
+            &[doc("a", 10)],
+            &SessionDocumentLayout::default(),
+            &settings,
+        );
+        assert_eq!(ok.level, BudgetLevel::Ok);
+        assert_eq!(ok.warning, None);
+    }
+}
@@ -0,0 +1,619 @@
+//! 本机 token 用量台账：记录 AI 回答的 tokenUsage，按天 / 群组 / 角色 / 技能汇总，
+//! 可选按价格表折算费用。
+//!
+//! 来源：历史消息分页、群消息流中的新消息、流式回答的 done 事件；同一条消息按 messageId 去重，
+//! 后到的来源只补全群组 / 角色等缺失字段。台账保存在 usage_ledger.json，变更后合并延迟写盘。
+
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::models::MessageHistoryItem;
+
+const STORE_FILE: &str = "usage_ledger.json";
+/// 台账最多保留的记录数（超出时丢弃最早的）
+const MAX_RECORDS: usize = 50_000;
+const UNKNOWN_KEY: &str = "unknown";
+/// 技能提示最多保留的条数（回答用量迟迟未到时丢弃最早的）
+const MAX_SKILL_HINTS: usize = 1_000;
+/// 变更后延迟写盘，流式回答 / 历史分页连续到达时合并为一次写入
+const PERSIST_DELAY: Duration = Duration::from_secs(2);
+
+/// 单条 AI 回答的用量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRecord {
+    pub message_id: String,
+    #[serde(default)]
+    pub group_id: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// 回答视角（viewRole）
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub skill_key: Option<String>,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub at_ms: i64,
+}
+
+/// 每百万 token 的价格
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPrice {
+    #[serde(default)]
+    pub input_per_million: f64,
+    #[serde(default)]
+    pub output_per_million: f64,
+}
+
+/// 价格表（持久化在 config.json 的 usagePricing 字段；未配置时报表不计算费用）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsagePricing {
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(flatten)]
+    pub default_price: TokenPrice,
+    /// 按技能覆盖（技能可能使用不同模型）
+    #[serde(default)]
+    pub skills: HashMap<String, TokenPrice>,
+}
+
+fn default_currency() -> String {
+    "CNY".to_string()
+}
+
+impl UsagePricing {
+    pub fn cost(&self, record: &UsageRecord) -> f64 {
+        let price = record
+            .skill_key
+            .as_deref()
+            .and_then(|k| self.skills.get(k))
+            .unwrap_or(&self.default_price);
+        (record.input_tokens as f64 * price.input_per_million
+            + record.output_tokens as f64 * price.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// 报表时间范围（毫秒时间戳，含起点不含终点；为空表示不限）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRange {
+    #[serde(default)]
+    pub from_ms: Option<i64>,
+    #[serde(default)]
+    pub to_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UsageGroupBy {
+    /// 本地日期（YYYY-MM-DD）
+    Day,
+    Group,
+    Role,
+    Skill,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRow {
+    /// 分组键；缺少对应字段的记录归入 unknown
+    pub key: String,
+    pub message_count: usize,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub group_by: UsageGroupBy,
+    pub range: UsageRange,
+    /// 按天时日期升序，其余按总 token 降序
+    pub rows: Vec<UsageRow>,
+    pub total: UsageRow,
+    pub currency: Option<String>,
+}
+
+/// 流式回答发起时已知的归属信息
+#[derive(Debug, Clone, Default)]
+pub struct UsageContext {
+    pub session_id: Option<String>,
+    pub role: Option<String>,
+}
+
+/// 技能运行的 AI 消息 id -> skillKey（消息本身不带技能信息）；按插入顺序淘汰
+#[derive(Default)]
+struct SkillHints {
+    keys: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl SkillHints {
+    fn insert(&mut self, message_id: String, skill_key: String) {
+        if self.keys.insert(message_id.clone(), skill_key).is_none() {
+            self.order.push_back(message_id);
+        }
+        while self.order.len() > MAX_SKILL_HINTS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+    }
+
+    /// 取出并移除：记录补上技能后不再需要
+    fn take(&mut self, message_id: &str) -> Option<String> {
+        let key = self.keys.remove(message_id)?;
+        self.order.retain(|id| id != message_id);
+        Some(key)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LedgerFile {
+    #[serde(default)]
+    records: HashMap<String, UsageRecord>,
+}
+
+lazy_static::lazy_static! {
+    static ref STORE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+    static ref LEDGER: RwLock<HashMap<String, UsageRecord>> = RwLock::new(HashMap::new());
+    static ref PRICING: RwLock<Option<UsagePricing>> = RwLock::new(None);
+    static ref SKILL_HINTS: RwLock<SkillHints> = RwLock::new(SkillHints::default());
+}
+
+/// 已有待写盘的变更（延迟写入尚未执行）
+static PERSIST_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// 启动时调用：从 app_data_dir 载入台账（文件损坏时忽略）
+pub fn init(dir: PathBuf) {
+    let file = fs::read_to_string(dir.join(STORE_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str::<LedgerFile>(&s).ok())
+        .unwrap_or_default();
+    *LEDGER.write().unwrap() = file.records;
+    *STORE_DIR.write().unwrap() = Some(dir);
+}
+
+fn persist() -> Result<(), String> {
+    let Some(dir) = STORE_DIR.read().unwrap().clone() else {
+        return Ok(());
+    };
+    let content = {
+        let ledger = LEDGER.read().unwrap();
+        serde_json::to_string(&serde_json::json!({ "records": &*ledger }))
+            .map_err(|e| format!("Failed to serialize usage ledger: {}", e))?
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    // 先写临时文件再 rename：写到一半退出不会留下半截 JSON
+    let tmp = dir.join(format!("{}.tmp", STORE_FILE));
+    fs::write(&tmp, content).map_err(|e| format!("Failed to write usage ledger: {}", e))?;
+    fs::rename(&tmp, dir.join(STORE_FILE))
+        .map_err(|e| format!("Failed to write usage ledger: {}", e))
+}
+
+/// 延迟写盘；等待期间的后续变更合并到同一次写入
+fn schedule_persist() {
+    if PERSIST_PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(|| {
+        std::thread::sleep(PERSIST_DELAY);
+        PERSIST_PENDING.store(false, Ordering::SeqCst);
+        if let Err(e) = persist() {
+            tracing::warn!("{}", e);
+        }
+    });
+}
+
+/// 应用退出时调用：立即写入尚未落盘的变更
+pub fn flush() {
+    if PERSIST_PENDING.swap(false, Ordering::SeqCst) {
+        if let Err(e) = persist() {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+pub fn get_pricing() -> Option<UsagePricing> {
+    PRICING.read().unwrap().clone()
+}
+
+pub fn set_pricing(pricing: Option<UsagePricing>) {
+    *PRICING.write().unwrap() = pricing;
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn non_empty(s: Option<&str>) -> Option<String> {
+    s.map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// 合并一条记录；返回是否有变化
+fn merge(ledger: &mut HashMap<String, UsageRecord>, mut record: UsageRecord) -> bool {
+    if record.skill_key.is_none() {
+        record.skill_key = SKILL_HINTS.write().unwrap().take(&record.message_id);
+    }
+    let Some(existing) = ledger.get_mut(&record.message_id) else {
+        ledger.insert(record.message_id.clone(), record);
+        return true;
+    };
+    let before = existing.clone();
+    if record.input_tokens + record.output_tokens > 0 {
+        existing.input_tokens = record.input_tokens;
+        existing.output_tokens = record.output_tokens;
+    }
+    existing.group_id = existing.group_id.take().or(record.group_id);
+    existing.session_id = existing.session_id.take().or(record.session_id);
+    existing.role = existing.role.take().or(record.role);
+    existing.skill_key = existing.skill_key.take().or(record.skill_key);
+    *existing != before
+}
+
+fn prune(ledger: &mut HashMap<String, UsageRecord>) {
+    if ledger.len() <= MAX_RECORDS {
+        return;
+    }
+    let mut all: Vec<(i64, String)> = ledger
+        .values()
+        .map(|r| (r.at_ms, r.message_id.clone()))
+        .collect();
+    all.sort();
+    let drop = ledger.len() - MAX_RECORDS;
+    for (_, id) in all.into_iter().take(drop) {
+        ledger.remove(&id);
+    }
+}
+
+fn record_all(records: Vec<UsageRecord>) {
+    if records.is_empty() {
+        return;
+    }
+    let changed = {
+        let mut ledger = LEDGER.write().unwrap();
+        let mut changed = false;
+        for r in records {
+            changed |= merge(&mut ledger, r);
+        }
+        prune(&mut ledger);
+        changed
+    };
+    if changed {
+        schedule_persist();
+    }
+}
+
+fn from_message(group_id: Option<&str>, m: &MessageHistoryItem) -> Option<UsageRecord> {
+    if !m.role.eq_ignore_ascii_case("assistant") {
+        return None;
+    }
+    let usage = m.token_usage.as_ref()?;
+    if usage.input + usage.output <= 0 {
+        return None;
+    }
+    Some(UsageRecord {
+        message_id: m.id.clone(),
+        group_id: non_empty(group_id),
+        session_id: None,
+        role: non_empty(m.view_role.as_deref()).map(|r| r.to_ascii_uppercase()),
+        skill_key: None,
+        input_tokens: i64::from(usage.input),
+        output_tokens: i64::from(usage.output),
+        at_ms: DateTime::parse_from_rfc3339(&m.timestamp)
+            .map(|t| t.timestamp_millis())
+            .unwrap_or_else(|_| now_ms()),
+    })
+}
+
+/// 记录历史消息中的用量
+pub fn record_messages(group_id: &str, items: &[MessageHistoryItem]) {
+    record_all(
+        items
+            .iter()
+            .filter_map(|m| from_message(Some(group_id), m))
+            .collect(),
+    );
+}
+
+/// 记录流事件中的用量：群消息流的 message 事件，或回答流的 done 事件（带 tokenUsage）
+pub fn record_stream_event(event: &serde_json::Value, ctx: Option<&UsageContext>) {
+    match event.get("type").and_then(|v| v.as_str()) {
+        Some("message") => {
+            let Some(raw) = event.get("message") else {
+                return;
+            };
+            let group_id = raw.get("groupId").and_then(|v| v.as_str());
+            if let Some(r) = serde_json::from_value::<MessageHistoryItem>(raw.clone())
+                .ok()
+                .and_then(|m| from_message(group_id, &m))
+            {
+                record_all(vec![r]);
+            }
+        }
+        Some("done") => {
+            let Some(message_id) = non_empty(event.get("messageId").and_then(|v| v.as_str()))
+            else {
+                return;
+            };
+            let Some(usage) = event.get("tokenUsage") else {
+                return;
+            };
+            let tokens = |key: &str| usage.get(key).and_then(|v| v.as_i64()).unwrap_or(0);
+            let (input_tokens, output_tokens) = (tokens("input"), tokens("output"));
+            if input_tokens + output_tokens <= 0 {
+                return;
+            }
+            record_all(vec![UsageRecord {
+                message_id,
+                group_id: None,
+                session_id: ctx.and_then(|c| non_empty(c.session_id.as_deref())),
+                role: ctx
+                    .and_then(|c| non_empty(c.role.as_deref()))
+                    .map(|r| r.to_ascii_uppercase()),
+                skill_key: None,
+                input_tokens,
+                output_tokens,
+                at_ms: now_ms(),
+            }]);
+        }
+        _ => {}
+    }
+}
+
+/// 记录技能运行对应的 AI 消息，用于按技能汇总
+pub fn note_skill_run(assistant_message_id: &str, skill_key: &str) {
+    let (Some(message_id), Some(skill_key)) = (
+        non_empty(Some(assistant_message_id)),
+        non_empty(Some(skill_key)),
+    ) else {
+        return;
+    };
+    // 用量已入账时直接补上技能，否则留作提示等用量到达时合并
+    let changed = match LEDGER.write().unwrap().get_mut(&message_id) {
+        Some(r) if r.skill_key.is_none() => {
+            r.skill_key = Some(skill_key);
+            true
+        }
+        Some(_) => false,
+        None => {
+            SKILL_HINTS.write().unwrap().insert(message_id, skill_key);
+            false
+        }
+    };
+    if changed {
+        schedule_persist();
+    }
+}
+
+fn day_key(at_ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(at_ms)
+        .single()
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| UNKNOWN_KEY.to_string())
+}
+
+fn add(row: &mut UsageRow, record: &UsageRecord, pricing: Option<&UsagePricing>) {
+    row.message_count += 1;
+    row.input_tokens += record.input_tokens;
+    row.output_tokens += record.output_tokens;
+    row.total_tokens += record.input_tokens + record.output_tokens;
+    if let Some(p) = pricing {
+        *row.cost.get_or_insert(0.0) += p.cost(record);
+    }
+}
+
+/// 按范围过滤并汇总
+pub fn build_report<'a>(
+    records: impl IntoIterator<Item = &'a UsageRecord>,
+    range: UsageRange,
+    group_by: UsageGroupBy,
+    pricing: Option<&UsagePricing>,
+) -> UsageReport {
+    let mut rows: HashMap<String, UsageRow> = HashMap::new();
+    let mut total = UsageRow {
+        key: "total".to_string(),
+        ..Default::default()
+    };
+    for r in records.into_iter().filter(|r| {
+        range.from_ms.is_none_or(|from| r.at_ms >= from)
+            && range.to_ms.is_none_or(|to| r.at_ms < to)
+    }) {
+        let key = match group_by {
+            UsageGroupBy::Day => Some(day_key(r.at_ms)),
+            UsageGroupBy::Group => r.group_id.clone(),
+            UsageGroupBy::Role => r.role.clone(),
+            UsageGroupBy::Skill => r.skill_key.clone(),
+        }
+        .unwrap_or_else(|| UNKNOWN_KEY.to_string());
+        let row = rows.entry(key.clone()).or_insert_with(|| UsageRow {
+            key,
+            ..Default::default()
+        });
+        add(row, r, pricing);
+        add(&mut total, r, pricing);
+    }
+    if pricing.is_some() {
+        total.cost.get_or_insert(0.0);
+    }
+    let mut rows: Vec<UsageRow> = rows.into_values().collect();
+    match group_by {
+        UsageGroupBy::Day => rows.sort_by(|a, b| a.key.cmp(&b.key)),
+        _ => rows.sort_by(|a, b| {
+            b.total_tokens
+                .cmp(&a.total_tokens)
+                .then_with(|| a.key.cmp(&b.key))
+        }),
+    }
+    UsageReport {
+        group_by,
+        range,
+        rows,
+        total,
+        currency: pricing.map(|p| p.currency.clone()),
+    }
+}
+
+/// 基于本机台账与当前价格表生成报表
+pub fn report(range: UsageRange, group_by: UsageGroupBy) -> UsageReport {
+    let pricing = get_pricing();
+    let ledger = LEDGER.read().unwrap();
+    build_report(ledger.values(), range, group_by, pricing.as_ref())
+}
+
+/// 报表导出为 CSV（含合计行）
+pub fn to_csv(report: &UsageReport) -> Result<String, String> {
+    let csv_err = |e: csv::Error| format!("Failed to write CSV: {}", e);
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let group_by = serde_json::to_value(report.group_by)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let mut header = vec![
+        group_by,
+        "messages".to_string(),
+        "input_tokens".to_string(),
+        "output_tokens".to_string(),
+        "total_tokens".to_string(),
+    ];
+    if let Some(currency) = &report.currency {
+        header.push(format!("cost_{}", currency.to_ascii_lowercase()));
+    }
+    writer.write_record(&header).map_err(csv_err)?;
+    for row in report.rows.iter().chain(std::iter::once(&report.total)) {
+        let mut record = vec![
+            row.key.clone(),
+            row.message_count.to_string(),
+            row.input_tokens.to_string(),
+            row.output_tokens.to_string(),
+            row.total_tokens.to_string(),
+        ];
+        if report.currency.is_some() {
+            record.push(format!("{:.4}", row.cost.unwrap_or(0.0)));
+        }
+        writer.write_record(&record).map_err(csv_err)?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| format!("Failed to write CSV: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("Failed to write CSV: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        id: &str,
+        group: Option<&str>,
+        skill: Option<&str>,
+        tokens: i64,
+        at_ms: i64,
+    ) -> UsageRecord {
+        UsageRecord {
+            message_id: id.to_string(),
+            group_id: group.map(str::to_string),
+            session_id: None,
+            role: None,
+            skill_key: skill.map(str::to_string),
+            input_tokens: tokens,
+            output_tokens: tokens,
+            at_ms,
+        }
+    }
+
+    #[test]
+    fn build_report_filters_range_groups_and_prices() {
+        let records = [
+            record("m1", Some("g1"), None, 100, 1_000),
+            record("m2", Some("g2"), Some("s1"), 500, 2_000),
+            record("m3", None, None, 10, 3_000),
+            record("m4", Some("g1"), None, 1_000, 9_000),
+        ];
+        let pricing = UsagePricing {
+            currency: "USD".to_string(),
+            default_price: TokenPrice {
+                input_per_million: 1.0,
+                output_per_million: 2.0,
+            },
+            skills: HashMap::from([(
+                "s1".to_string(),
+                TokenPrice {
+                    input_per_million: 10.0,
+                    output_per_million: 10.0,
+                },
+            )]),
+        };
+        let range = UsageRange {
+            from_ms: Some(1_000),
+            to_ms: Some(9_000),
+        };
+
+        let report = build_report(&records, range, UsageGroupBy::Group, Some(&pricing));
+        let keys: Vec<_> = report.rows.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, ["g2", "g1", "unknown"]);
+        assert_eq!(report.total.message_count, 3);
+        assert_eq!(report.total.total_tokens, 2 * (100 + 500 + 10));
+        let g2_cost = report.rows[0].cost.unwrap();
+        assert!((g2_cost - 0.01).abs() < 1e-9);
+        assert_eq!(report.currency.as_deref(), Some("USD"));
+
+        let unpriced = build_report(&records, UsageRange::default(), UsageGroupBy::Skill, None);
+        assert_eq!(unpriced.total.message_count, 4);
+        assert_eq!(unpriced.total.cost, None);
+        assert_eq!(unpriced.rows[0].key, "unknown");
+        assert_eq!(unpriced.currency, None);
+    }
+
+    #[test]
+    fn skill_hints_are_bounded_and_consumed() {
+        let mut hints = SkillHints::default();
+        for i in 0..MAX_SKILL_HINTS + 5 {
+            hints.insert(format!("m{}", i), "s".to_string());
+        }
+        assert_eq!(hints.keys.len(), MAX_SKILL_HINTS);
+        assert_eq!(hints.order.len(), MAX_SKILL_HINTS);
+        assert_eq!(hints.take("m0"), None);
+        assert_eq!(hints.take("m5").as_deref(), Some("s"));
+        assert_eq!(hints.take("m5"), None);
+        assert_eq!(hints.order.len(), MAX_SKILL_HINTS - 1);
+    }
+
+    #[test]
+    fn csv_quotes_keys() {
+        let records = [record("m1", Some("g,\"1\""), None, 1, 0)];
+        let report = build_report(&records, UsageRange::default(), UsageGroupBy::Group, None);
+        let csv = to_csv(&report).unwrap();
+        assert_eq!(
+            csv,
+            "group,messages,input_tokens,output_tokens,total_tokens\n\"g,\"\"1\"\"\",1,1,1,2\ntotal,1,1,1,2\n"
+        );
+    }
+
+    #[test]
+    fn day_report_is_sorted_by_date() {
+        let day = 24 * 60 * 60 * 1000;
+        let records = [
+            record("m1", None, None, 1, 3 * day + 1),
+            record("m2", None, None, 1, day + 1),
+        ];
+        let report = build_report(&records, UsageRange::default(), UsageGroupBy::Day, None);
+        assert_eq!(report.rows.len(), 2);
+        assert!(report.rows[0].key < report.rows[1].key);
+    }
+}
@@ -263,10 +263,12 @@ function App() {
       refreshToken: refreshToken ?? null,
       sessionKey: sessionKey ?? null,
       clientType: 'desktop',
+      username: user?.username ?? null,
+      displayName: user?.displayName ?? null,
     }).catch((err) => {
       console.error('Failed to sync auth session:', err);
     });
-  }, [accessToken, refreshToken, sessionKey, user?.userId]);
+  }, [accessToken, refreshToken, sessionKey, user?.userId, user?.username, user?.displayName]);
 
   // 会话 keep-alive：用户可能长时间阅读 PRD/回看历史而不发消息，但仍希望"首次提问"不因为 30min 无写操作而直接过期。
   // 依赖后端 GET /sessions/{id} 会刷新 LastActiveAt + TTL（滑动过期）。